@@ -0,0 +1,104 @@
+//! The payload of error messages (message type 0x81) as laid out by AUTOSAR.
+//!
+//! The payload is a union of exceptions with 32 bit length and type fields.
+//! Exception 0 is the generic exception without fields; exception 1 carries
+//! a UTF-8 description of the error.
+
+use nom::IResult;
+
+use crate::{Error, InnerError, SomeIPType, StringCoding, StructLayout, Value};
+
+pub const GENERIC_EXCEPTION: u64 = 0;
+pub const DESCRIBED_EXCEPTION: u64 = 1;
+
+/// The schema of an AUTOSAR error payload.
+pub fn application_error_type() -> SomeIPType {
+    SomeIPType::Union {
+        length_width: 32,
+        type_width: 32,
+        variants: vec![
+            (
+                GENERIC_EXCEPTION,
+                SomeIPType::Struct {
+                    fields: vec![],
+                    alignment: 1,
+                    length_width: None,
+                    layout: StructLayout::Sequential,
+                },
+            ),
+            (
+                DESCRIBED_EXCEPTION,
+                SomeIPType::Struct {
+                    fields: vec![(
+                        "description".to_string(),
+                        SomeIPType::DynamicString {
+                            length_width: 32,
+                            coding: Some(StringCoding::Utf8),
+                        },
+                    )],
+                    alignment: 1,
+                    length_width: None,
+                    layout: StructLayout::Sequential,
+                },
+            ),
+        ],
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ApplicationError {
+    /// The union type field selecting the exception.
+    pub exception: u64,
+    pub description: Option<String>,
+}
+
+/// Decodes the payload of an error message with [`application_error_type`].
+pub fn decode_application_error(payload: &[u8]) -> IResult<&[u8], ApplicationError, Error<'_>> {
+    let (rest, value) = crate::complete::some_ip_value(payload, &application_error_type())?;
+    let (Some(Value::UInt(exception)), Some(exception_value)) =
+        (value.get("type"), value.get("value"))
+    else {
+        return Err(nom::Err::Error(Error::new(
+            payload,
+            InnerError::UnsupportedType,
+        )));
+    };
+    let description = match exception_value.get("description") {
+        Some(Value::String(description)) => Some(description.clone()),
+        _ => None,
+    };
+    Ok((
+        rest,
+        ApplicationError {
+            exception: *exception,
+            description,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_application_error() {
+        let bytes: Vec<u8> = vec![
+            0x0, 0x0, 0x0, 0x8, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x4, b'b', b'u', b's', b'y',
+        ];
+        let (remaining, error) = decode_application_error(&bytes).unwrap();
+
+        assert_eq!(remaining.len(), 0);
+        assert_eq!(
+            error,
+            ApplicationError {
+                exception: DESCRIBED_EXCEPTION,
+                description: Some("busy".to_string()),
+            }
+        );
+
+        let bytes: Vec<u8> = vec![0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0];
+        let (_, error) = decode_application_error(&bytes).unwrap();
+        assert_eq!(error.exception, GENERIC_EXCEPTION);
+        assert_eq!(error.description, None);
+    }
+}
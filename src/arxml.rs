@@ -0,0 +1,490 @@
+//! Loading of service interfaces from AUTOSAR ARXML files.
+//!
+//! Implementation data types of the categories VALUE, TYPE_REFERENCE,
+//! STRUCTURE, ARRAY and UNION are understood. Service and method ids are
+//! taken from the SOME/IP service interface deployments. Type references
+//! are inlined, so every resulting `SomeIPType` is self-contained.
+
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use roxmltree::{Document, Node};
+
+use crate::{
+    LengthUnit, SomeIPType, StructLayout,
+    catalog::InterfaceCatalog,
+    registry::TypeRegistry,
+    xml::{child, is, text},
+};
+
+/// Maximum nesting of data types, which stops reference cycles.
+const MAX_DEPTH: usize = 64;
+
+/// Width of the length fields of variable size arrays and unions, and of the
+/// type field of unions, as SOME/IP uses by default.
+const DEFAULT_LENGTH_WIDTH: u8 = 32;
+
+#[derive(Debug)]
+pub enum ArxmlError {
+    Xml(roxmltree::Error),
+    /// A required element is missing.
+    Missing(&'static str),
+    /// A reference points to no element with that path.
+    UnknownReference(String),
+    /// A number could not be parsed.
+    InvalidNumber(String),
+    /// A data type outside of the supported subset.
+    Unsupported(String),
+    /// Data types nest too deep, e.g. because they refer to each other.
+    DepthLimitExceeded,
+}
+
+impl fmt::Display for ArxmlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArxmlError::Xml(error) => write!(f, "invalid XML: {error}"),
+            ArxmlError::Missing(name) => write!(f, "missing {name}"),
+            ArxmlError::UnknownReference(path) => write!(f, "unknown reference {path:?}"),
+            ArxmlError::InvalidNumber(text) => write!(f, "invalid number {text:?}"),
+            ArxmlError::Unsupported(what) => write!(f, "unsupported {what}"),
+            ArxmlError::DepthLimitExceeded => write!(f, "data type nesting limit exceeded"),
+        }
+    }
+}
+
+impl std::error::Error for ArxmlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ArxmlError::Xml(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<roxmltree::Error> for ArxmlError {
+    fn from(error: roxmltree::Error) -> Self {
+        ArxmlError::Xml(error)
+    }
+}
+
+/// The contents of an ARXML file.
+#[derive(Debug, Default)]
+pub struct Arxml {
+    /// Every implementation data type, registered under its short name.
+    pub datatypes: TypeRegistry,
+    /// The arguments and return values of every deployed method, as structs
+    /// with one field per argument.
+    pub catalog: InterfaceCatalog,
+}
+
+pub fn parse_arxml(xml: &str) -> Result<Arxml, ArxmlError> {
+    let document = Document::parse(xml)?;
+    let paths: HashMap<String, Node> = document
+        .descendants()
+        .filter(|node| child(*node, "SHORT-NAME").is_some())
+        .map(|node| (path(node), node))
+        .collect();
+    let loader = Loader { paths };
+
+    let mut arxml = Arxml::default();
+    for datatype in document
+        .descendants()
+        .filter(|node| is(node, "IMPLEMENTATION-DATA-TYPE"))
+    {
+        let name = text(datatype, "SHORT-NAME").ok_or(ArxmlError::Missing("SHORT-NAME"))?;
+        arxml
+            .datatypes
+            .register(name, loader.data_type(datatype, 0)?);
+    }
+    for deployment in document
+        .descendants()
+        .filter(|node| is(node, "SOMEIP-SERVICE-INTERFACE-DEPLOYMENT"))
+    {
+        let service_id = number(deployment, "SERVICE-INTERFACE-ID")?;
+        for method_deployment in deployment
+            .descendants()
+            .filter(|node| is(node, "SOMEIP-METHOD-DEPLOYMENT"))
+        {
+            let method_id = number(method_deployment, "METHOD-ID")?;
+            let method = loader.reference(text(method_deployment, "METHOD-REF"), "METHOD-REF")?;
+            let request = loader.arguments(method, &["IN", "INOUT"])?;
+            let response = match text(method, "FIRE-AND-FORGET") {
+                Some("true") => None,
+                _ => Some(loader.arguments(method, &["OUT", "INOUT"])?),
+            };
+            arxml
+                .catalog
+                .register(service_id, method_id, request, response);
+        }
+    }
+    Ok(arxml)
+}
+
+struct Loader<'a, 'input> {
+    /// Every element carrying a short name, by its absolute path.
+    paths: HashMap<String, Node<'a, 'input>>,
+}
+
+impl<'a, 'input> Loader<'a, 'input> {
+    fn reference(
+        &self,
+        path: Option<&str>,
+        name: &'static str,
+    ) -> Result<Node<'a, 'input>, ArxmlError> {
+        let path = path.ok_or(ArxmlError::Missing(name))?;
+        self.paths
+            .get(path)
+            .copied()
+            .ok_or_else(|| ArxmlError::UnknownReference(path.to_string()))
+    }
+
+    /// A struct of the arguments of `method` passed in one of `directions`.
+    fn arguments(&self, method: Node, directions: &[&str]) -> Result<SomeIPType, ArxmlError> {
+        let arguments = child(method, "ARGUMENTS")
+            .into_iter()
+            .flat_map(|arguments| arguments.children())
+            .filter(|node| is(node, "ARGUMENT-DATA-PROTOTYPE"));
+
+        let mut fields = Vec::new();
+        for argument in arguments {
+            let direction = text(argument, "DIRECTION").ok_or(ArxmlError::Missing("DIRECTION"))?;
+            if !directions.contains(&direction) {
+                continue;
+            }
+            let name = text(argument, "SHORT-NAME").ok_or(ArxmlError::Missing("SHORT-NAME"))?;
+            let datatype = self.reference(text(argument, "TYPE-TREF"), "TYPE-TREF")?;
+            fields.push((name.to_string(), self.data_type(datatype, 1)?));
+        }
+        Ok(SomeIPType::Struct {
+            fields,
+            alignment: 1,
+            length_width: None,
+            layout: StructLayout::Sequential,
+        })
+    }
+
+    /// The type of an implementation data type or of one of its elements.
+    fn data_type(&self, node: Node, depth: usize) -> Result<SomeIPType, ArxmlError> {
+        if depth > MAX_DEPTH {
+            return Err(ArxmlError::DepthLimitExceeded);
+        }
+        match text(node, "CATEGORY") {
+            Some("VALUE") => {
+                base_type(self.reference(property(node, "BASE-TYPE-REF"), "BASE-TYPE-REF")?)
+            }
+            Some("TYPE_REFERENCE") => {
+                let target = self.reference(
+                    property(node, "IMPLEMENTATION-DATA-TYPE-REF"),
+                    "IMPLEMENTATION-DATA-TYPE-REF",
+                )?;
+                self.data_type(target, depth + 1)
+            }
+            Some("STRUCTURE") => Ok(SomeIPType::Struct {
+                fields: self.sub_elements(node, depth)?,
+                alignment: 1,
+                length_width: None,
+                layout: StructLayout::Sequential,
+            }),
+            // union members are selected by their position, starting at 1
+            Some("UNION") => Ok(SomeIPType::Union {
+                length_width: DEFAULT_LENGTH_WIDTH,
+                type_width: DEFAULT_LENGTH_WIDTH,
+                variants: self
+                    .sub_elements(node, depth)?
+                    .into_iter()
+                    .zip(1..)
+                    .map(|((_, def), tag)| (tag, def))
+                    .collect(),
+            }),
+            Some("ARRAY") => {
+                let element = sub_elements(node)
+                    .next()
+                    .ok_or(ArxmlError::Missing("IMPLEMENTATION-DATA-TYPE-ELEMENT"))?;
+                let length = number(element, "ARRAY-SIZE")?;
+                let def = Box::new(self.data_type(element, depth + 1)?);
+                match text(element, "ARRAY-SIZE-SEMANTICS") {
+                    Some("VARIABLE-SIZE") => Ok(SomeIPType::DynamicArray {
+                        length_width: DEFAULT_LENGTH_WIDTH,
+                        element: def,
+                        length_unit: LengthUnit::Bytes,
+                        min_length: None,
+                        max_length: None,
+                    }),
+                    _ => Ok(SomeIPType::StaticArray {
+                        length,
+                        element: def,
+                    }),
+                }
+            }
+            other => Err(ArxmlError::Unsupported(format!("category {other:?}"))),
+        }
+    }
+
+    fn sub_elements(
+        &self,
+        node: Node,
+        depth: usize,
+    ) -> Result<Vec<(String, SomeIPType)>, ArxmlError> {
+        sub_elements(node)
+            .map(|element| {
+                let name = text(element, "SHORT-NAME").ok_or(ArxmlError::Missing("SHORT-NAME"))?;
+                Ok((name.to_string(), self.data_type(element, depth + 1)?))
+            })
+            .collect()
+    }
+}
+
+/// The type of a `SW-BASE-TYPE`.
+fn base_type(base: Node) -> Result<SomeIPType, ArxmlError> {
+    let size: u32 = number(base, "BASE-TYPE-SIZE")?;
+    let encoding = text(base, "BASE-TYPE-ENCODING").unwrap_or("NONE");
+    let def = match (encoding, size) {
+        ("NONE" | "BOOLEAN", 8) => SomeIPType::UInt8,
+        ("NONE", 16) => SomeIPType::UInt16,
+        ("NONE", 32) => SomeIPType::UInt32,
+        ("NONE", 64) => SomeIPType::UInt64,
+        ("2C", 8) => SomeIPType::SInt8,
+        ("2C", 16) => SomeIPType::SInt16,
+        ("2C", 32) => SomeIPType::SInt32,
+        ("2C", 64) => SomeIPType::SInt64,
+        ("IEEE754", 32) => SomeIPType::Float32,
+        ("IEEE754", 64) => SomeIPType::Float64,
+        _ => {
+            return Err(ArxmlError::Unsupported(format!(
+                "base type encoding {encoding} of {size} bits"
+            )));
+        }
+    };
+    Ok(def)
+}
+
+fn sub_elements<'a, 'input>(node: Node<'a, 'input>) -> impl Iterator<Item = Node<'a, 'input>> {
+    child(node, "SUB-ELEMENTS")
+        .into_iter()
+        .flat_map(|elements| elements.children())
+        .filter(|node| is(node, "IMPLEMENTATION-DATA-TYPE-ELEMENT"))
+}
+
+/// The reference `name` among the data definition properties of `node`.
+fn property<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, "SW-DATA-DEF-PROPS")?
+        .descendants()
+        .find(|node| is(node, name))?
+        .text()
+        .map(str::trim)
+}
+
+/// The absolute path of `node` made of its and its ancestors' short names.
+fn path(node: Node) -> String {
+    let mut names: Vec<_> = node
+        .ancestors()
+        .filter_map(|ancestor| text(ancestor, "SHORT-NAME"))
+        .collect();
+    names.reverse();
+    format!("/{}", names.join("/"))
+}
+
+fn number<T: FromStr>(node: Node, name: &'static str) -> Result<T, ArxmlError> {
+    let text = text(node, name).ok_or(ArxmlError::Missing(name))?;
+    text.parse()
+        .map_err(|_| ArxmlError::InvalidNumber(text.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SomeIPHeader;
+
+    const ARXML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<AUTOSAR xmlns="http://autosar.org/schema/r4.0">
+  <AR-PACKAGES>
+    <AR-PACKAGE>
+      <SHORT-NAME>Types</SHORT-NAME>
+      <ELEMENTS>
+        <SW-BASE-TYPE>
+          <SHORT-NAME>uint8</SHORT-NAME>
+          <BASE-TYPE-SIZE>8</BASE-TYPE-SIZE>
+          <BASE-TYPE-ENCODING>NONE</BASE-TYPE-ENCODING>
+        </SW-BASE-TYPE>
+        <SW-BASE-TYPE>
+          <SHORT-NAME>sint16</SHORT-NAME>
+          <BASE-TYPE-SIZE>16</BASE-TYPE-SIZE>
+          <BASE-TYPE-ENCODING>2C</BASE-TYPE-ENCODING>
+        </SW-BASE-TYPE>
+        <SW-BASE-TYPE>
+          <SHORT-NAME>float32</SHORT-NAME>
+          <BASE-TYPE-SIZE>32</BASE-TYPE-SIZE>
+          <BASE-TYPE-ENCODING>IEEE754</BASE-TYPE-ENCODING>
+        </SW-BASE-TYPE>
+        <IMPLEMENTATION-DATA-TYPE>
+          <SHORT-NAME>UInt8</SHORT-NAME>
+          <CATEGORY>VALUE</CATEGORY>
+          <SW-DATA-DEF-PROPS>
+            <SW-DATA-DEF-PROPS-VARIANTS>
+              <SW-DATA-DEF-PROPS-CONDITIONAL>
+                <BASE-TYPE-REF DEST="SW-BASE-TYPE">/Types/uint8</BASE-TYPE-REF>
+              </SW-DATA-DEF-PROPS-CONDITIONAL>
+            </SW-DATA-DEF-PROPS-VARIANTS>
+          </SW-DATA-DEF-PROPS>
+        </IMPLEMENTATION-DATA-TYPE>
+        <IMPLEMENTATION-DATA-TYPE>
+          <SHORT-NAME>Reading</SHORT-NAME>
+          <CATEGORY>STRUCTURE</CATEGORY>
+          <SUB-ELEMENTS>
+            <IMPLEMENTATION-DATA-TYPE-ELEMENT>
+              <SHORT-NAME>offset</SHORT-NAME>
+              <CATEGORY>VALUE</CATEGORY>
+              <SW-DATA-DEF-PROPS>
+                <SW-DATA-DEF-PROPS-VARIANTS>
+                  <SW-DATA-DEF-PROPS-CONDITIONAL>
+                    <BASE-TYPE-REF DEST="SW-BASE-TYPE">/Types/sint16</BASE-TYPE-REF>
+                  </SW-DATA-DEF-PROPS-CONDITIONAL>
+                </SW-DATA-DEF-PROPS-VARIANTS>
+              </SW-DATA-DEF-PROPS>
+            </IMPLEMENTATION-DATA-TYPE-ELEMENT>
+            <IMPLEMENTATION-DATA-TYPE-ELEMENT>
+              <SHORT-NAME>samples</SHORT-NAME>
+              <CATEGORY>TYPE_REFERENCE</CATEGORY>
+              <SW-DATA-DEF-PROPS>
+                <SW-DATA-DEF-PROPS-VARIANTS>
+                  <SW-DATA-DEF-PROPS-CONDITIONAL>
+                    <IMPLEMENTATION-DATA-TYPE-REF DEST="IMPLEMENTATION-DATA-TYPE">/Types/Samples</IMPLEMENTATION-DATA-TYPE-REF>
+                  </SW-DATA-DEF-PROPS-CONDITIONAL>
+                </SW-DATA-DEF-PROPS-VARIANTS>
+              </SW-DATA-DEF-PROPS>
+            </IMPLEMENTATION-DATA-TYPE-ELEMENT>
+            <IMPLEMENTATION-DATA-TYPE-ELEMENT>
+              <SHORT-NAME>unit</SHORT-NAME>
+              <CATEGORY>UNION</CATEGORY>
+              <SUB-ELEMENTS>
+                <IMPLEMENTATION-DATA-TYPE-ELEMENT>
+                  <SHORT-NAME>raw</SHORT-NAME>
+                  <CATEGORY>TYPE_REFERENCE</CATEGORY>
+                  <SW-DATA-DEF-PROPS>
+                    <SW-DATA-DEF-PROPS-VARIANTS>
+                      <SW-DATA-DEF-PROPS-CONDITIONAL>
+                        <IMPLEMENTATION-DATA-TYPE-REF DEST="IMPLEMENTATION-DATA-TYPE">/Types/UInt8</IMPLEMENTATION-DATA-TYPE-REF>
+                      </SW-DATA-DEF-PROPS-CONDITIONAL>
+                    </SW-DATA-DEF-PROPS-VARIANTS>
+                  </SW-DATA-DEF-PROPS>
+                </IMPLEMENTATION-DATA-TYPE-ELEMENT>
+                <IMPLEMENTATION-DATA-TYPE-ELEMENT>
+                  <SHORT-NAME>scaled</SHORT-NAME>
+                  <CATEGORY>VALUE</CATEGORY>
+                  <SW-DATA-DEF-PROPS>
+                    <SW-DATA-DEF-PROPS-VARIANTS>
+                      <SW-DATA-DEF-PROPS-CONDITIONAL>
+                        <BASE-TYPE-REF DEST="SW-BASE-TYPE">/Types/float32</BASE-TYPE-REF>
+                      </SW-DATA-DEF-PROPS-CONDITIONAL>
+                    </SW-DATA-DEF-PROPS-VARIANTS>
+                  </SW-DATA-DEF-PROPS>
+                </IMPLEMENTATION-DATA-TYPE-ELEMENT>
+              </SUB-ELEMENTS>
+            </IMPLEMENTATION-DATA-TYPE-ELEMENT>
+          </SUB-ELEMENTS>
+        </IMPLEMENTATION-DATA-TYPE>
+        <IMPLEMENTATION-DATA-TYPE>
+          <SHORT-NAME>Samples</SHORT-NAME>
+          <CATEGORY>ARRAY</CATEGORY>
+          <SUB-ELEMENTS>
+            <IMPLEMENTATION-DATA-TYPE-ELEMENT>
+              <SHORT-NAME>sample</SHORT-NAME>
+              <CATEGORY>TYPE_REFERENCE</CATEGORY>
+              <ARRAY-SIZE>3</ARRAY-SIZE>
+              <ARRAY-SIZE-SEMANTICS>FIXED-SIZE</ARRAY-SIZE-SEMANTICS>
+              <SW-DATA-DEF-PROPS>
+                <SW-DATA-DEF-PROPS-VARIANTS>
+                  <SW-DATA-DEF-PROPS-CONDITIONAL>
+                    <IMPLEMENTATION-DATA-TYPE-REF DEST="IMPLEMENTATION-DATA-TYPE">/Types/UInt8</IMPLEMENTATION-DATA-TYPE-REF>
+                  </SW-DATA-DEF-PROPS-CONDITIONAL>
+                </SW-DATA-DEF-PROPS-VARIANTS>
+              </SW-DATA-DEF-PROPS>
+            </IMPLEMENTATION-DATA-TYPE-ELEMENT>
+          </SUB-ELEMENTS>
+        </IMPLEMENTATION-DATA-TYPE>
+      </ELEMENTS>
+    </AR-PACKAGE>
+    <AR-PACKAGE>
+      <SHORT-NAME>Services</SHORT-NAME>
+      <ELEMENTS>
+        <SERVICE-INTERFACE>
+          <SHORT-NAME>Sensor</SHORT-NAME>
+          <METHODS>
+            <CLIENT-SERVER-OPERATION>
+              <SHORT-NAME>read</SHORT-NAME>
+              <ARGUMENTS>
+                <ARGUMENT-DATA-PROTOTYPE>
+                  <SHORT-NAME>channel</SHORT-NAME>
+                  <TYPE-TREF DEST="IMPLEMENTATION-DATA-TYPE">/Types/UInt8</TYPE-TREF>
+                  <DIRECTION>IN</DIRECTION>
+                </ARGUMENT-DATA-PROTOTYPE>
+                <ARGUMENT-DATA-PROTOTYPE>
+                  <SHORT-NAME>reading</SHORT-NAME>
+                  <TYPE-TREF DEST="IMPLEMENTATION-DATA-TYPE">/Types/Reading</TYPE-TREF>
+                  <DIRECTION>OUT</DIRECTION>
+                </ARGUMENT-DATA-PROTOTYPE>
+              </ARGUMENTS>
+            </CLIENT-SERVER-OPERATION>
+          </METHODS>
+        </SERVICE-INTERFACE>
+        <SOMEIP-SERVICE-INTERFACE-DEPLOYMENT>
+          <SHORT-NAME>SensorDeployment</SHORT-NAME>
+          <METHOD-DEPLOYMENTS>
+            <SOMEIP-METHOD-DEPLOYMENT>
+              <SHORT-NAME>read</SHORT-NAME>
+              <METHOD-REF DEST="CLIENT-SERVER-OPERATION">/Services/Sensor/read</METHOD-REF>
+              <METHOD-ID>7</METHOD-ID>
+            </SOMEIP-METHOD-DEPLOYMENT>
+          </METHOD-DEPLOYMENTS>
+          <SERVICE-INTERFACE-REF DEST="SERVICE-INTERFACE">/Services/Sensor</SERVICE-INTERFACE-REF>
+          <SERVICE-INTERFACE-ID>4660</SERVICE-INTERFACE-ID>
+        </SOMEIP-SERVICE-INTERFACE-DEPLOYMENT>
+      </ELEMENTS>
+    </AR-PACKAGE>
+  </AR-PACKAGES>
+</AUTOSAR>
+"#;
+
+    #[test]
+    fn test_parse_arxml() {
+        let arxml = parse_arxml(ARXML).unwrap();
+        let reading = SomeIPType::Struct {
+            fields: vec![
+                ("offset".to_string(), SomeIPType::SInt16),
+                (
+                    "samples".to_string(),
+                    SomeIPType::StaticArray {
+                        length: 3,
+                        element: Box::new(SomeIPType::UInt8),
+                    },
+                ),
+                (
+                    "unit".to_string(),
+                    SomeIPType::Union {
+                        length_width: 32,
+                        type_width: 32,
+                        variants: vec![(1, SomeIPType::UInt8), (2, SomeIPType::Float32)],
+                    },
+                ),
+            ],
+            alignment: 1,
+            length_width: None,
+            layout: StructLayout::Sequential,
+        };
+        let response = SomeIPHeader::builder()
+            .service_id(0x1234)
+            .method_id(0x7)
+            .response()
+            .build();
+
+        assert_eq!(arxml.datatypes.get("Reading"), Some(&reading));
+        assert_eq!(
+            arxml.catalog.resolve(&response),
+            Some(&SomeIPType::Struct {
+                fields: vec![("reading".to_string(), reading)],
+                alignment: 1,
+                length_width: None,
+                layout: StructLayout::Sequential,
+            })
+        );
+    }
+}
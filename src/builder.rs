@@ -0,0 +1,163 @@
+//! Fluent construction of [`SomeIPHeader`]s.
+
+use crate::{
+    MethodId, PROTOCOL_VERSION, ReturnCode, ServiceId, SomeIPHeader, SomeIPMessageType,
+    encode::EncodeError,
+};
+
+/// Builds a header field by field.
+///
+/// Unset fields default to zero, except `protocol_version` and
+/// `interface_version` which default to 1 and `message_type` which defaults
+/// to `Request`. The `length` is derived from the payload length.
+#[derive(Clone, Debug)]
+pub struct SomeIPHeaderBuilder {
+    header: SomeIPHeader,
+}
+
+impl Default for SomeIPHeaderBuilder {
+    fn default() -> Self {
+        SomeIPHeaderBuilder {
+            header: SomeIPHeader {
+                service_id: ServiceId(0),
+                method_id: MethodId(0),
+                length: 8,
+                client_id: 0,
+                session_id: 0,
+                protocol_version: PROTOCOL_VERSION,
+                interface_version: 1,
+                message_type: SomeIPMessageType::Request(),
+                return_code: ReturnCode::Ok,
+            },
+        }
+    }
+}
+
+impl SomeIPHeaderBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn service_id(mut self, service_id: impl Into<ServiceId>) -> Self {
+        self.header.service_id = service_id.into();
+        self
+    }
+
+    pub fn method_id(mut self, method_id: impl Into<MethodId>) -> Self {
+        self.header.method_id = method_id.into();
+        self
+    }
+
+    pub fn client_id(mut self, client_id: u16) -> Self {
+        self.header.client_id = client_id;
+        self
+    }
+
+    pub fn session_id(mut self, session_id: u16) -> Self {
+        self.header.session_id = session_id;
+        self
+    }
+
+    pub fn protocol_version(mut self, protocol_version: u8) -> Self {
+        self.header.protocol_version = protocol_version;
+        self
+    }
+
+    pub fn interface_version(mut self, interface_version: u8) -> Self {
+        self.header.interface_version = interface_version;
+        self
+    }
+
+    pub fn message_type(mut self, message_type: SomeIPMessageType) -> Self {
+        self.header.message_type = message_type;
+        self
+    }
+
+    pub fn request(self) -> Self {
+        self.message_type(SomeIPMessageType::Request())
+    }
+
+    pub fn request_no_return(self) -> Self {
+        self.message_type(SomeIPMessageType::RequestNoReturn())
+    }
+
+    pub fn notification(self) -> Self {
+        self.message_type(SomeIPMessageType::Notification())
+    }
+
+    pub fn response(self) -> Self {
+        self.message_type(SomeIPMessageType::Response())
+    }
+
+    pub fn return_code(mut self, return_code: ReturnCode) -> Self {
+        self.header.return_code = return_code;
+        self
+    }
+
+    /// Sets `length` to cover a payload of `payload_length` bytes, failing
+    /// with `EncodeError::OutOfRange` if that does not fit the header.
+    pub fn payload_length(mut self, payload_length: u32) -> Result<Self, EncodeError> {
+        self.header.length = payload_length
+            .checked_add(8)
+            .ok_or(EncodeError::OutOfRange)?;
+        Ok(self)
+    }
+
+    /// Sets `length` to cover `payload`, like [`Self::payload_length`].
+    pub fn payload(self, payload: &[u8]) -> Result<Self, EncodeError> {
+        let payload_length = u32::try_from(payload.len()).map_err(|_| EncodeError::OutOfRange)?;
+        self.payload_length(payload_length)
+    }
+
+    pub fn build(self) -> SomeIPHeader {
+        self.header
+    }
+}
+
+impl SomeIPHeader {
+    pub fn builder() -> SomeIPHeaderBuilder {
+        SomeIPHeaderBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode::encode_header;
+
+    #[test]
+    fn test_build_notification_header() {
+        let header = SomeIPHeader::builder()
+            .service_id(0x1234)
+            .method_id(0x8001)
+            .session_id(0x2)
+            .notification()
+            .payload(&[0xde, 0xad, 0xbe, 0xef])
+            .unwrap()
+            .build();
+
+        assert_eq!(header.length, 12);
+        assert_eq!(header.protocol_version, 1);
+
+        let mut bytes = Vec::new();
+        encode_header(&header, &mut bytes).unwrap();
+        assert_eq!(
+            bytes,
+            vec![
+                0x12, 0x34, 0x80, 0x01, 0x0, 0x0, 0x0, 0xc, 0x0, 0x0, 0x0, 0x2, 0x1, 0x1, 0x2, 0x0
+            ]
+        );
+    }
+    #[test]
+    fn test_payload_length_out_of_range() {
+        let header = SomeIPHeader::builder()
+            .payload_length(u32::MAX - 8)
+            .unwrap()
+            .build();
+        assert_eq!(header.length, u32::MAX);
+        assert!(matches!(
+            SomeIPHeader::builder().payload_length(u32::MAX - 7),
+            Err(EncodeError::OutOfRange)
+        ));
+    }
+}
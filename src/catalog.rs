@@ -0,0 +1,86 @@
+//! Payload definitions of the methods and events of known services.
+
+use std::collections::HashMap;
+
+use crate::{SomeIPHeader, SomeIPMessageType, SomeIPType};
+
+/// The payload definitions of a single method or event.
+#[derive(Debug)]
+pub struct MethodSignature {
+    /// Payload of requests, fire-and-forget requests and notifications.
+    pub request: SomeIPType,
+    /// Payload of responses, `None` for methods that are never answered.
+    pub response: Option<SomeIPType>,
+}
+
+/// Maps `(service_id, method_id)` to the payload definitions of that method.
+#[derive(Debug, Default)]
+pub struct InterfaceCatalog {
+    methods: HashMap<(u16, u16), MethodSignature>,
+}
+
+impl InterfaceCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(
+        &mut self,
+        service_id: u16,
+        method_id: u16,
+        request: SomeIPType,
+        response: Option<SomeIPType>,
+    ) {
+        self.methods.insert(
+            (service_id, method_id),
+            MethodSignature { request, response },
+        );
+    }
+
+    pub fn get(&self, service_id: u16, method_id: u16) -> Option<&MethodSignature> {
+        self.methods.get(&(service_id, method_id))
+    }
+
+    /// The definition of the payload following `header`.
+    ///
+    /// Error messages and unknown message types resolve to `None`, since
+    /// their payload does not follow the method's signature.
+    pub fn resolve(&self, header: &SomeIPHeader) -> Option<&SomeIPType> {
+        let signature = self.get(header.service_id.0, header.method_id.0)?;
+        match header.message_type {
+            SomeIPMessageType::Response() => signature.response.as_ref(),
+            SomeIPMessageType::Error() | SomeIPMessageType::Unknown(_) => None,
+            _ => Some(&signature.request),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interface_catalog_resolve() {
+        let mut catalog = InterfaceCatalog::new();
+        catalog.register(0x1234, 0x1, SomeIPType::UInt16, Some(SomeIPType::UInt32));
+        let request = SomeIPHeader::builder()
+            .service_id(0x1234)
+            .method_id(0x1)
+            .request()
+            .build();
+        let response = SomeIPHeader::builder()
+            .service_id(0x1234)
+            .method_id(0x1)
+            .response()
+            .build();
+        let other = SomeIPHeader::builder()
+            .service_id(0x1234)
+            .method_id(0x2)
+            .request()
+            .build();
+
+        assert_eq!(catalog.resolve(&request), Some(&SomeIPType::UInt16));
+        assert_eq!(catalog.resolve(&response), Some(&SomeIPType::UInt32));
+        assert_eq!(catalog.resolve(&other), None);
+    }
+}
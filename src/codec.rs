@@ -0,0 +1,146 @@
+//! A [`tokio_util::codec`] codec, so that a `Framed<TcpStream, SomeIPCodec>`
+//! reads and writes whole messages.
+
+use std::{fmt, io};
+
+use bytes::{Buf, Bytes, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::{
+    InnerError, SomeIPHeader,
+    encode::{EncodeError, encode_header},
+    some_ip_frame,
+};
+
+/// Size of the header up to and including the return code.
+const HEADER_LEN: usize = 16;
+
+/// A message with its payload, as read and written by [`SomeIPCodec`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SomeIPFrame {
+    pub header: SomeIPHeader,
+    pub payload: Bytes,
+}
+
+#[derive(Debug)]
+pub enum CodecError {
+    Io(io::Error),
+    /// A header could not be parsed. It is skipped, so decoding continues
+    /// right after it.
+    Decode(InnerError),
+    Encode(EncodeError),
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodecError::Io(error) => write!(f, "{error}"),
+            CodecError::Decode(error) => write!(f, "{error}"),
+            CodecError::Encode(error) => write!(f, "cannot encode header: {error:?}"),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+impl From<io::Error> for CodecError {
+    fn from(error: io::Error) -> Self {
+        CodecError::Io(error)
+    }
+}
+
+/// Frames messages by their header `length`.
+#[derive(Clone, Debug, Default)]
+pub struct SomeIPCodec;
+
+impl Decoder for SomeIPCodec {
+    type Item = SomeIPFrame;
+    type Error = CodecError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<SomeIPFrame>, CodecError> {
+        let (header, frame_len) = match some_ip_frame(src) {
+            Ok((rest, message)) => (message.header, src.len() - rest.len()),
+            Err(nom::Err::Incomplete(_)) => {
+                src.reserve(HEADER_LEN.saturating_sub(src.len()));
+                return Ok(None);
+            }
+            Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
+                let error = e.error;
+                src.advance(HEADER_LEN);
+                return Err(CodecError::Decode(error));
+            }
+        };
+        let mut frame = src.split_to(frame_len);
+        frame.advance(HEADER_LEN);
+        Ok(Some(SomeIPFrame {
+            header,
+            payload: frame.freeze(),
+        }))
+    }
+}
+
+impl Encoder<SomeIPFrame> for SomeIPCodec {
+    type Error = CodecError;
+
+    /// Writes the frame with its header `length` set to cover the payload.
+    fn encode(&mut self, frame: SomeIPFrame, dst: &mut BytesMut) -> Result<(), CodecError> {
+        let length = u32::try_from(frame.payload.len() + 8)
+            .map_err(|_| CodecError::Encode(EncodeError::OutOfRange))?;
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        encode_header(
+            &SomeIPHeader {
+                length,
+                ..frame.header
+            },
+            &mut header,
+        )
+        .map_err(CodecError::Encode)?;
+        dst.reserve(header.len() + frame.payload.len());
+        dst.extend_from_slice(&header);
+        dst.extend_from_slice(&frame.payload);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read};
+
+    use super::*;
+
+    #[test]
+    fn test_codec_decode_from_reader() {
+        let bytes: Vec<u8> = vec![
+            0x12, 0x34, 0x00, 0x01, 0x0, 0x0, 0x0, 0xc, 0x0, 0x1, 0x0, 0x2, 0x1, 0x1, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x2a, 0x12, 0x34, 0x80, 0x01, 0x0, 0x0, 0x0, 0x8, 0x0, 0x1, 0x0, 0x3,
+            0x1, 0x1, 0x2, 0x0,
+        ];
+        let mut reader = Cursor::new(bytes.clone());
+        let mut codec = SomeIPCodec;
+        let mut src = BytesMut::new();
+        let mut frames = Vec::new();
+        let mut chunk = [0; 7];
+        loop {
+            let read = reader.read(&mut chunk).unwrap();
+            if read == 0 {
+                break;
+            }
+            src.extend_from_slice(&chunk[..read]);
+            while let Some(frame) = codec.decode(&mut src).unwrap() {
+                frames.push(frame);
+            }
+        }
+
+        assert!(src.is_empty());
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].payload, &[0x0, 0x0, 0x0, 0x2a][..]);
+        assert_eq!(frames[1].header.method_id, 0x8001);
+        assert!(frames[1].payload.is_empty());
+
+        let mut out = BytesMut::new();
+        for frame in frames {
+            codec.encode(frame, &mut out).unwrap();
+        }
+        assert_eq!(out, bytes);
+    }
+}
@@ -0,0 +1,177 @@
+//! Parsers for input that is known to be complete, such as a single frame
+//! held in memory.
+//!
+//! The parsers at the crate root are streaming: running out of input yields
+//! `nom::Err::Incomplete` so the caller can wait for more data. The parsers
+//! here treat truncated input as a hard `nom::Err::Error` instead.
+
+use nom::IResult;
+
+use crate::{
+    DecodeOptions, DecodeState, Error, SomeIPHeader, SomeIPMessage, SomeIPType, Value,
+    sd::{self, SdMessage},
+};
+
+/// A message as parsed by [`parse_frame`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame<'a> {
+    pub message: SomeIPMessage<'a>,
+    /// The payload of an SD message.
+    pub sd: Option<SdMessage>,
+}
+
+pub fn some_ip_header(input: &[u8]) -> IResult<&[u8], SomeIPHeader, Error<'_>> {
+    crate::header(input, true)
+}
+
+pub fn some_ip_frame(input: &[u8]) -> IResult<&[u8], SomeIPMessage<'_>, Error<'_>> {
+    crate::frame(input, true)
+}
+
+fn finish(error: nom::Err<Error<'_>>) -> Error<'_> {
+    match error {
+        nom::Err::Error(e) | nom::Err::Failure(e) => e,
+        nom::Err::Incomplete(_) => unreachable!("complete parsers never need more input"),
+    }
+}
+
+/// Parses the first message of `data` and, for SD messages, its payload.
+///
+/// Meant as the entry point for fuzzing: any input yields a result, and
+/// bytes following the message are ignored.
+pub fn parse_frame(data: &[u8]) -> Result<Frame<'_>, Error<'_>> {
+    let (_, message) = some_ip_frame(data).map_err(finish)?;
+    let sd = if message.header.service_id == sd::SD_SERVICE_ID
+        && message.header.method_id == sd::SD_METHOD_ID
+    {
+        Some(sd::sd_message(message.payload).map_err(finish)?.1)
+    } else {
+        None
+    };
+    Ok(Frame { message, sd })
+}
+
+/// Parses the messages a UDP datagram holds back to back.
+///
+/// Unlike on a TCP stream, a message cut off by the end of the datagram is
+/// never continued, so it is an error rather than `Incomplete`.
+pub fn parse_udp_datagram(data: &[u8]) -> Result<Vec<SomeIPMessage<'_>>, Error<'_>> {
+    let mut messages = Vec::new();
+    let mut input = data;
+    while !input.is_empty() {
+        let (rest, message) = some_ip_frame(input).map_err(finish)?;
+        messages.push(message);
+        input = rest;
+    }
+    Ok(messages)
+}
+
+pub fn some_ip_message<'a>(
+    input: &'a [u8],
+    payload_type: &SomeIPType,
+) -> IResult<&'a [u8], (SomeIPHeader, Value), Error<'a>> {
+    crate::message(input, payload_type, true)
+}
+
+pub fn some_ip_value<'a>(input: &'a [u8], def: &SomeIPType) -> IResult<&'a [u8], Value, Error<'a>> {
+    some_ip_value_with_options(input, def, &DecodeOptions::default())
+}
+
+pub fn some_ip_value_with_options<'a>(
+    input: &'a [u8],
+    def: &SomeIPType,
+    options: &DecodeOptions,
+) -> IResult<&'a [u8], Value, Error<'a>> {
+    crate::decode_value(input, def, &mut DecodeState::new(options, true, input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InnerError;
+    use nom::error::ErrorKind;
+
+    #[test]
+    fn test_truncated_uint32_streaming_vs_complete() {
+        let bytes: Vec<u8> = vec![0x12, 0x34];
+        let slice = bytes.as_slice();
+        let def = SomeIPType::UInt32;
+
+        let streaming = crate::some_ip_value(slice, &def);
+        assert!(matches!(streaming, Err(nom::Err::Incomplete(_))));
+
+        let complete = some_ip_value(slice, &def);
+        assert_eq!(
+            complete,
+            Err(nom::Err::Error(Error::new(
+                slice,
+                InnerError::Nom(ErrorKind::Eof)
+            )))
+        );
+    }
+
+    #[test]
+    fn test_parse_frame_adversarial_inputs() {
+        let sd_header = |length: u32| {
+            let mut bytes = vec![0xff, 0xff, 0x81, 0x0];
+            bytes.extend(length.to_be_bytes());
+            bytes.extend([0x0, 0x0, 0x0, 0x1, 0x1, 0x1, 0x2, 0x0]);
+            bytes
+        };
+        let mut huge_entries = sd_header(16);
+        huge_entries.extend([0xc0, 0x0, 0x0, 0x0, 0xff, 0xff, 0xff, 0xff]);
+        let mut short_option = sd_header(25);
+        short_option.extend([0xc0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0]);
+        short_option.extend([0x0, 0x0, 0x0, 0x5, 0x0, 0x0, 0x4, 0x0, 0xc0]);
+
+        for data in [
+            Vec::new(),
+            vec![0xff; 7],
+            sd_header(0),
+            sd_header(u32::MAX),
+            huge_entries,
+            short_option,
+        ] {
+            assert!(parse_frame(&data).is_err(), "accepted {data:x?}");
+        }
+
+        let mut empty_sd = sd_header(20);
+        empty_sd.extend([0xc0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0]);
+        assert!(parse_frame(&empty_sd).unwrap().sd.is_some());
+    }
+
+    #[test]
+    fn test_truncated_header_complete() {
+        let bytes: Vec<u8> = vec![0xff, 0xff, 0x81, 0x0, 0x0, 0x0];
+        let slice = bytes.as_slice();
+
+        assert!(matches!(
+            crate::some_ip_header(slice),
+            Err(nom::Err::Incomplete(_))
+        ));
+        assert!(matches!(some_ip_header(slice), Err(nom::Err::Error(_))));
+    }
+
+    #[test]
+    fn test_parse_udp_datagram() {
+        let mut datagram: Vec<u8> = vec![
+            0x12, 0x34, 0x0, 0x1, 0x0, 0x0, 0x0, 0xa, 0x0, 0x1, 0x0, 0x1, 0x1, 0x1, 0x0, 0x0, 0xab,
+            0xcd,
+        ];
+        datagram.extend([
+            0x12, 0x34, 0x80, 0x2, 0x0, 0x0, 0x0, 0x9, 0x0, 0x0, 0x0, 0x2, 0x1, 0x1, 0x2, 0x0, 0xef,
+        ]);
+
+        let messages = parse_udp_datagram(&datagram).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].payload, [0xab, 0xcd]);
+        assert_eq!(messages[1].header.method_id, 0x8002);
+        assert_eq!(messages[1].payload, [0xef]);
+
+        // the second message declares one payload byte more than it has
+        datagram[25] = 0xa;
+        let error = parse_udp_datagram(&datagram).unwrap_err();
+        assert_eq!(error.error, InnerError::Nom(ErrorKind::Eof));
+        assert_eq!(error.offset(&datagram), Some(34));
+    }
+}
@@ -0,0 +1,236 @@
+//! Conversions of decoded values into native Rust types.
+
+use std::fmt;
+
+use crate::Value;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConversionError {
+    /// The value is of another kind, e.g. a string where a number was
+    /// expected.
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// The number does not fit into the requested type.
+    OutOfRange { expected: &'static str, value: i128 },
+    /// A finite float beyond the range of the requested float type.
+    FloatOutOfRange { expected: &'static str, value: f64 },
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversionError::TypeMismatch { expected, found } => {
+                write!(f, "expected {expected}, found {found}")
+            }
+            ConversionError::OutOfRange { expected, value } => {
+                write!(f, "{value} is out of range for {expected}")
+            }
+            ConversionError::FloatOutOfRange { expected, value } => {
+                write!(f, "{value} is out of range for {expected}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+/// The kind of `value`, for error messages.
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Bool(_) => "bool",
+        Value::Float(_) => "float",
+        Value::UInt(_) | Value::UInt128(_) => "unsigned integer",
+        Value::Int(_) | Value::Int128(_) => "signed integer",
+        Value::Struct { .. } => "struct",
+        Value::Array(_) => "array",
+        Value::Enum(_) | Value::EnumRaw(_) => "enum",
+        Value::String(_) => "string",
+        Value::Bytes(_) => "bytes",
+        Value::Map(_) => "map",
+    }
+}
+
+/// An integer of either sign widened so it can be range checked once.
+fn integer(value: &Value, expected: &'static str) -> Result<i128, ConversionError> {
+    match value {
+        Value::UInt(v) => Ok((*v).into()),
+        Value::Int(v) => Ok((*v).into()),
+        Value::Int128(v) => Ok(*v),
+        // above i128::MAX is out of range for every target type
+        Value::UInt128(v) => Ok(i128::try_from(*v).unwrap_or(i128::MAX)),
+        _ => Err(ConversionError::TypeMismatch {
+            expected,
+            found: kind(value),
+        }),
+    }
+}
+
+/// Implements `TryFrom<Value>` by way of `TryFrom<&Value>`.
+macro_rules! try_from_owned {
+    ($($ty:ty),*) => {
+        $(
+            impl TryFrom<Value> for $ty {
+                type Error = ConversionError;
+
+                fn try_from(value: Value) -> Result<Self, Self::Error> {
+                    <$ty>::try_from(&value)
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! try_from_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl TryFrom<&Value> for $ty {
+                type Error = ConversionError;
+
+                fn try_from(value: &Value) -> Result<Self, Self::Error> {
+                    let expected = stringify!($ty);
+                    let value = integer(value, expected)?;
+                    <$ty>::try_from(value)
+                        .map_err(|_| ConversionError::OutOfRange { expected, value })
+                }
+            }
+        )*
+        try_from_owned!($($ty),*);
+    };
+}
+
+try_from_integer!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+impl TryFrom<&Value> for f64 {
+    type Error = ConversionError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Float(v) => Ok(*v),
+            _ => Err(ConversionError::TypeMismatch {
+                expected: "f64",
+                found: kind(value),
+            }),
+        }
+    }
+}
+
+impl TryFrom<&Value> for f32 {
+    type Error = ConversionError;
+
+    /// Rounds to the nearest `f32`, as decoded `Float32`s round trip exactly.
+    /// Finite values too large for an `f32` are out of range.
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Float(v) if v.is_finite() && !(*v as f32).is_finite() => {
+                Err(ConversionError::FloatOutOfRange {
+                    expected: "f32",
+                    value: *v,
+                })
+            }
+            Value::Float(v) => Ok(*v as f32),
+            _ => Err(ConversionError::TypeMismatch {
+                expected: "f32",
+                found: kind(value),
+            }),
+        }
+    }
+}
+
+impl TryFrom<&Value> for bool {
+    type Error = ConversionError;
+
+    /// Booleans decoded as an integer must be 0 or 1.
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        if let Value::Bool(b) = value {
+            return Ok(*b);
+        }
+        match integer(value, "bool")? {
+            0 => Ok(false),
+            1 => Ok(true),
+            value => Err(ConversionError::OutOfRange {
+                expected: "bool",
+                value,
+            }),
+        }
+    }
+}
+
+impl TryFrom<&Value> for String {
+    type Error = ConversionError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(string) => Ok(string.clone()),
+            _ => Err(ConversionError::TypeMismatch {
+                expected: "String",
+                found: kind(value),
+            }),
+        }
+    }
+}
+
+try_from_owned!(f32, f64, bool);
+
+impl TryFrom<Value> for String {
+    type Error = ConversionError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(string) => Ok(string),
+            _ => Err(ConversionError::TypeMismatch {
+                expected: "String",
+                found: kind(&value),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_from_value() {
+        assert_eq!(u32::try_from(Value::UInt(0x12345678)), Ok(0x12345678));
+        assert_eq!(i8::try_from(&Value::Int(-5)), Ok(-5));
+        assert_eq!(u16::try_from(Value::Int(5)), Ok(5));
+        assert_eq!(f64::try_from(Value::Float(1.5)), Ok(1.5));
+        assert_eq!(bool::try_from(Value::UInt(1)), Ok(true));
+        assert_eq!(
+            String::try_from(Value::String("hi".to_string())),
+            Ok("hi".to_string())
+        );
+
+        let error = u8::try_from(Value::UInt(300)).unwrap_err();
+        assert_eq!(
+            error,
+            ConversionError::OutOfRange {
+                expected: "u8",
+                value: 300
+            }
+        );
+        assert_eq!(error.to_string(), "300 is out of range for u8");
+        assert!(u64::try_from(Value::Int(-1)).is_err());
+        assert!(bool::try_from(Value::UInt(2)).is_err());
+        assert_eq!(
+            f32::try_from(Value::Float(f64::INFINITY)),
+            Ok(f32::INFINITY)
+        );
+        assert_eq!(
+            f32::try_from(Value::Float(1e39)),
+            Err(ConversionError::FloatOutOfRange {
+                expected: "f32",
+                value: 1e39
+            })
+        );
+        assert_eq!(
+            u32::try_from(Value::String("1".to_string())),
+            Err(ConversionError::TypeMismatch {
+                expected: "u32",
+                found: "string"
+            })
+        );
+    }
+}
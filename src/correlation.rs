@@ -0,0 +1,69 @@
+//! Pairing of requests with their responses, e.g. for capture analysis.
+
+use crate::SomeIPMessage;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Correlation<'m, 'a> {
+    /// Every request in capture order, with its response if one was seen.
+    pub pairs: Vec<(&'m SomeIPMessage<'a>, Option<&'m SomeIPMessage<'a>>)>,
+    /// Responses that do not answer any earlier request.
+    pub orphan_responses: Vec<&'m SomeIPMessage<'a>>,
+}
+
+/// Pairs each request with the first later response that answers it.
+///
+/// Messages that are neither requests nor responses, such as notifications,
+/// are ignored.
+pub fn correlate<'m, 'a>(messages: &'m [SomeIPMessage<'a>]) -> Correlation<'m, 'a> {
+    let mut correlation = Correlation::default();
+    for message in messages {
+        if message.header.is_request() {
+            correlation.pairs.push((message, None));
+        } else if message.header.is_response() {
+            let pending = correlation.pairs.iter_mut().find(|(request, response)| {
+                response.is_none() && message.header.is_response_to(&request.header)
+            });
+            match pending {
+                Some((_, response)) => *response = Some(message),
+                None => correlation.orphan_responses.push(message),
+            }
+        }
+    }
+    correlation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MethodId, ReturnCode, ServiceId, SomeIPHeader, SomeIPMessageType};
+
+    fn message(session_id: u16, message_type: SomeIPMessageType) -> SomeIPMessage<'static> {
+        SomeIPMessage {
+            header: SomeIPHeader {
+                service_id: ServiceId(0x1234),
+                method_id: MethodId(0x0001),
+                length: 8,
+                client_id: 0x1,
+                session_id,
+                protocol_version: 0x1,
+                interface_version: 0x1,
+                message_type,
+                return_code: ReturnCode::Ok,
+            },
+            payload: &[],
+        }
+    }
+
+    #[test]
+    fn test_correlate() {
+        let messages = vec![
+            message(0x1, SomeIPMessageType::Request()),
+            message(0x2, SomeIPMessageType::Response()),
+            message(0x1, SomeIPMessageType::Response()),
+        ];
+        let correlation = correlate(&messages);
+
+        assert_eq!(correlation.pairs, vec![(&messages[0], Some(&messages[2]))]);
+        assert_eq!(correlation.orphan_responses, vec![&messages[1]]);
+    }
+}
@@ -0,0 +1,318 @@
+//! Incremental decoding of messages and payloads that arrive in pieces.
+
+use std::task::Poll;
+
+use crate::{
+    DecodeOptions, DecodeState, InnerError, LengthUnit, SomeIPMessage, SomeIPType, StructLayout,
+    Value, decode_value, some_ip_frame, someip_dynamic_length, someip_padding,
+};
+
+/// Size of the header up to and including the return code.
+const HEADER_LEN: usize = 16;
+
+/// Splits bytes fed in arbitrary pieces, e.g. as read from a TCP socket,
+/// into messages framed by their header `length`.
+#[derive(Debug, Default)]
+pub struct SomeIPDecoder {
+    buffer: Vec<u8>,
+    /// Bytes of the message last returned, dropped on the next call.
+    consumed: usize,
+}
+
+impl SomeIPDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// The next message, or `None` until one has been received completely.
+    ///
+    /// A header with an invalid `length` is reported as an error and
+    /// skipped, taking the stream to continue right after it.
+    pub fn next_message(&mut self) -> Option<Result<SomeIPMessage<'_>, InnerError>> {
+        self.buffer.drain(..self.consumed);
+        self.consumed = 0;
+        match some_ip_frame(&self.buffer) {
+            Ok((rest, message)) => {
+                self.consumed = self.buffer.len() - rest.len();
+                Some(Ok(message))
+            }
+            Err(nom::Err::Incomplete(_)) => None,
+            Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
+                self.consumed = HEADER_LEN;
+                Some(Err(e.error))
+            }
+        }
+    }
+}
+
+/// A struct or array whose members are still being received.
+enum Frame<'s> {
+    Struct {
+        fields: &'s [(String, SomeIPType)],
+        alignment: u8,
+        values: Vec<(String, Value)>,
+    },
+    Array {
+        element: &'s SomeIPType,
+        length: u64,
+        values: Vec<Value>,
+    },
+}
+
+impl<'s> Frame<'s> {
+    /// The type of the next member and the alignment it starts on, or `None`
+    /// once every member has been decoded.
+    fn next(&self) -> Option<(&'s SomeIPType, u8)> {
+        match self {
+            Frame::Struct {
+                fields,
+                alignment,
+                values,
+            } => fields.get(values.len()).map(|(_, def)| (def, *alignment)),
+            Frame::Array {
+                element,
+                length,
+                values,
+            } => ((values.len() as u64) < *length).then_some((*element, 1)),
+        }
+    }
+
+    fn push(&mut self, value: Value) {
+        match self {
+            Frame::Struct { fields, values, .. } => {
+                values.push((fields[values.len()].0.clone(), value))
+            }
+            Frame::Array { values, .. } => values.push(value),
+        }
+    }
+
+    fn into_value(self) -> Value {
+        match self {
+            Frame::Struct { values, .. } => Value::Struct { fields: values },
+            Frame::Array { values, .. } => Value::Array(values),
+        }
+    }
+}
+
+/// Decodes a single value from bytes fed in arbitrary pieces.
+///
+/// Progress through structs and arrays is kept between feeds, so each member
+/// is decoded once, as soon as all of its bytes are available. Other
+/// composite types are decoded as a whole once complete. The fed bytes are
+/// retained until the value is complete.
+pub struct ValueDecoder<'s> {
+    def: &'s SomeIPType,
+    options: DecodeOptions,
+    buffer: Vec<u8>,
+    position: usize,
+    stack: Vec<Frame<'s>>,
+    done: bool,
+}
+
+impl<'s> ValueDecoder<'s> {
+    pub fn new(def: &'s SomeIPType) -> Self {
+        ValueDecoder {
+            def,
+            options: DecodeOptions::default(),
+            buffer: Vec::new(),
+            position: 0,
+            stack: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// Bytes fed past the end of the completed value.
+    pub fn remaining(&self) -> &[u8] {
+        &self.buffer[self.position..]
+    }
+
+    /// Adds `bytes` and decodes as far as possible.
+    ///
+    /// Returns `Poll::Pending` until the value is complete. Once it has been
+    /// returned, further feeds only collect bytes into [`Self::remaining`].
+    pub fn feed(&mut self, bytes: &[u8]) -> Poll<Result<Value, InnerError>> {
+        self.buffer.extend_from_slice(bytes);
+        if self.done {
+            return Poll::Pending;
+        }
+        let result = self.advance();
+        if result.is_ready() {
+            self.done = true;
+        }
+        result
+    }
+
+    fn advance(&mut self) -> Poll<Result<Value, InnerError>> {
+        loop {
+            let (def, alignment) = match self.stack.last() {
+                None => (self.def, 1),
+                Some(frame) => match frame.next() {
+                    Some(next) => next,
+                    None => {
+                        let value = self.stack.pop().unwrap().into_value();
+                        match self.stack.last_mut() {
+                            Some(parent) => parent.push(value),
+                            None => return Poll::Ready(Ok(value)),
+                        }
+                        continue;
+                    }
+                },
+            };
+
+            let mut state = DecodeState::new(&self.options, false, &self.buffer);
+            let input = &self.buffer[self.position..];
+            let input = match someip_padding(input, alignment, &state) {
+                Ok((input, _)) => input,
+                Err(nom::Err::Incomplete(_)) => return Poll::Pending,
+                Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
+                    return Poll::Ready(Err(e.error));
+                }
+            };
+
+            let step = match def {
+                SomeIPType::Struct {
+                    fields,
+                    alignment,
+                    length_width: None,
+                    layout: StructLayout::Sequential,
+                } => Ok((
+                    input,
+                    Some(Frame::Struct {
+                        fields,
+                        alignment: *alignment,
+                        values: Vec::new(),
+                    }),
+                    None,
+                )),
+                SomeIPType::StaticArray { length, element } => Ok((
+                    input,
+                    Some(Frame::Array {
+                        element,
+                        length: (*length).into(),
+                        values: Vec::new(),
+                    }),
+                    None,
+                )),
+                SomeIPType::DynamicArray {
+                    length_width,
+                    element,
+                    length_unit: LengthUnit::Elements,
+                    min_length: None,
+                    max_length: None,
+                } => someip_dynamic_length(input, *length_width, false).map(|(i1, length)| {
+                    (
+                        i1,
+                        Some(Frame::Array {
+                            element,
+                            length,
+                            values: Vec::new(),
+                        }),
+                        None,
+                    )
+                }),
+                _ => {
+                    decode_value(input, def, &mut state).map(|(i1, value)| (i1, None, Some(value)))
+                }
+            };
+
+            match step {
+                Ok((rest, frame, value)) => {
+                    self.position = self.buffer.len() - rest.len();
+                    if let Some(frame) = frame {
+                        self.stack.push(frame);
+                    }
+                    if let Some(value) = value {
+                        match self.stack.last_mut() {
+                            Some(parent) => parent.push(value),
+                            None => return Poll::Ready(Ok(value)),
+                        }
+                    }
+                }
+                Err(nom::Err::Incomplete(_)) => return Poll::Pending,
+                Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
+                    return Poll::Ready(Err(e.error));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_someip_decoder_split_frame() {
+        let bytes: Vec<u8> = vec![
+            0x12, 0x34, 0x00, 0x01, 0x0, 0x0, 0x0, 0xc, 0x0, 0x1, 0x0, 0x2, 0x1, 0x1, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x2a,
+        ];
+        let mut decoder = SomeIPDecoder::new();
+
+        decoder.feed(&bytes[..3]);
+        assert!(decoder.next_message().is_none());
+        decoder.feed(&bytes[3..17]);
+        assert!(decoder.next_message().is_none());
+        decoder.feed(&bytes[17..]);
+        let message = decoder.next_message().unwrap().unwrap();
+        assert_eq!(message.header.service_id, 0x1234);
+        assert_eq!(message.payload, &[0x0, 0x0, 0x0, 0x2a]);
+        assert!(decoder.next_message().is_none());
+
+        // a header with a length below 8 is skipped
+        let mut invalid = bytes[..16].to_vec();
+        invalid[7] = 0x4;
+        decoder.feed(&invalid);
+        decoder.feed(&bytes);
+        assert_eq!(
+            decoder.next_message(),
+            Some(Err(InnerError::InvalidLength(0x4)))
+        );
+        assert_eq!(decoder.next_message().unwrap().unwrap().payload.len(), 4);
+    }
+
+    #[test]
+    fn test_value_decoder_byte_by_byte() {
+        let bytes: Vec<u8> = vec![0x12, 0x34, 0x56, 0x78, 0x2, 0x9a, 0xbc, 0xde, 0xf0, 0xff];
+        let def = SomeIPType::Struct {
+            fields: vec![
+                ("field1".to_string(), SomeIPType::UInt32),
+                (
+                    "field2".to_string(),
+                    SomeIPType::DynamicArray {
+                        length_width: 8,
+                        element: Box::new(SomeIPType::UInt16),
+                        length_unit: LengthUnit::Elements,
+                        min_length: None,
+                        max_length: None,
+                    },
+                ),
+            ],
+            alignment: 1,
+            length_width: None,
+            layout: StructLayout::Sequential,
+        };
+        let mut decoder = ValueDecoder::new(&def);
+
+        for byte in &bytes[..8] {
+            assert_eq!(decoder.feed(&[*byte]), Poll::Pending);
+        }
+        assert_eq!(
+            decoder.feed(&bytes[8..]),
+            Poll::Ready(Ok(Value::Struct {
+                fields: vec![
+                    ("field1".to_string(), Value::UInt(0x12345678)),
+                    (
+                        "field2".to_string(),
+                        Value::Array(vec![Value::UInt(0x9abc), Value::UInt(0xdef0)])
+                    ),
+                ]
+            }))
+        );
+        assert_eq!(decoder.remaining(), &[0xff]);
+    }
+}
@@ -0,0 +1,410 @@
+//! AUTOSAR end-to-end (E2E) protection of payloads.
+//!
+//! A protected payload carries an E2E header at a configured offset, holding
+//! a CRC over the payload and a data id together with a sequence counter.
+//! Profile 4 protects with a CRC32, profile 5 with a CRC16 and profile 7 with
+//! a CRC64.
+
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum E2eProfile {
+    /// Length (16 bit), counter (16 bit), data id (32 bit) and CRC32, big
+    /// endian.
+    P4,
+    /// CRC16, little endian, and an 8 bit counter. The data id only enters
+    /// the CRC.
+    P5,
+    /// CRC64, length (32 bit), counter (32 bit) and data id (32 bit), big
+    /// endian.
+    P7,
+}
+
+impl E2eProfile {
+    pub fn header_len(&self) -> usize {
+        match self {
+            E2eProfile::P4 => 12,
+            E2eProfile::P5 => 3,
+            E2eProfile::P7 => 20,
+        }
+    }
+
+    /// Number of distinct counter values before the counter wraps.
+    fn counter_range(&self) -> u64 {
+        match self {
+            E2eProfile::P4 => 1 << 16,
+            E2eProfile::P5 => 1 << 8,
+            E2eProfile::P7 => 1 << 32,
+        }
+    }
+
+    /// Position and width of the CRC within the header.
+    fn crc_field(&self) -> (usize, usize) {
+        match self {
+            E2eProfile::P4 => (8, 4),
+            E2eProfile::P5 => (0, 2),
+            E2eProfile::P7 => (0, 8),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct E2eConfig {
+    pub profile: E2eProfile,
+    pub data_id: u32,
+    /// Position of the E2E header in the payload, in bytes.
+    pub offset: usize,
+    /// Largest accepted counter increment between two received payloads;
+    /// larger increments mean payloads were lost.
+    pub max_delta_counter: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum E2eError {
+    /// The payload ends before the E2E header does.
+    TooShort,
+    /// The payload length does not fit the length field of the header.
+    TooLong(usize),
+    /// The length in the header is not the length of the payload.
+    WrongLength {
+        declared: usize,
+        actual: usize,
+    },
+    /// The header carries another data id than configured.
+    WrongDataId(u32),
+    WrongCrc {
+        declared: u64,
+        computed: u64,
+    },
+    /// The counter did not change since the previous payload.
+    Repeated(u32),
+    /// The counter skipped more than `max_delta_counter` values.
+    WrongSequence {
+        previous: u32,
+        counter: u32,
+    },
+}
+
+impl fmt::Display for E2eError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            E2eError::TooShort => write!(f, "payload too short for its E2E header"),
+            E2eError::TooLong(length) => {
+                write!(f, "payload of {length} bytes too long for its E2E header")
+            }
+            E2eError::WrongLength { declared, actual } => {
+                write!(
+                    f,
+                    "E2E length {declared} does not match payload length {actual}"
+                )
+            }
+            E2eError::WrongDataId(data_id) => write!(f, "unexpected E2E data id {data_id:#x}"),
+            E2eError::WrongCrc { declared, computed } => {
+                write!(
+                    f,
+                    "E2E CRC {declared:#x} does not match computed {computed:#x}"
+                )
+            }
+            E2eError::Repeated(counter) => write!(f, "E2E counter {counter} repeated"),
+            E2eError::WrongSequence { previous, counter } => {
+                write!(f, "E2E counter jumped from {previous} to {counter}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for E2eError {}
+
+/// CRC-16/CCITT-FALSE, used by profile 5.
+fn crc16(bytes: impl IntoIterator<Item = u8>) -> u16 {
+    let mut crc = 0xffffu16;
+    for byte in bytes {
+        crc ^= u16::from(byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// CRC-32P4 with polynomial 0xF4ACFB13, used by profile 4.
+fn crc32(bytes: impl IntoIterator<Item = u8>) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xc8df_352f
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// CRC-64 with the ECMA-182 polynomial, used by profile 7.
+fn crc64(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    let mut crc = u64::MAX;
+    for byte in bytes {
+        crc ^= u64::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xc96c_5795_d787_0f42
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// The CRC over `payload` without its CRC field.
+fn compute_crc(payload: &[u8], config: &E2eConfig) -> u64 {
+    let (crc_pos, crc_len) = config.profile.crc_field();
+    let crc_start = config.offset + crc_pos;
+    let covered = payload[..crc_start]
+        .iter()
+        .chain(&payload[crc_start + crc_len..])
+        .copied();
+    match config.profile {
+        E2eProfile::P4 => crc32(covered).into(),
+        E2eProfile::P5 => {
+            crc16(covered.chain(config.data_id.to_le_bytes().into_iter().take(2))).into()
+        }
+        E2eProfile::P7 => crc64(covered),
+    }
+}
+
+fn read_be(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0, |value, byte| value << 8 | u64::from(*byte))
+}
+
+/// Checks the length, data id and CRC of a protected payload and returns its
+/// counter.
+///
+/// Whether the counter follows the previous one is only known to the
+/// receiver; see [`E2eReceiver`].
+pub fn verify_e2e(payload: &[u8], config: &E2eConfig) -> Result<u32, E2eError> {
+    let header = payload
+        .get(config.offset..config.offset + config.profile.header_len())
+        .ok_or(E2eError::TooShort)?;
+    let (length, counter, data_id, declared_crc) = match config.profile {
+        E2eProfile::P4 => (
+            Some(read_be(&header[0..2])),
+            read_be(&header[2..4]),
+            Some(read_be(&header[4..8])),
+            read_be(&header[8..12]),
+        ),
+        E2eProfile::P5 => (
+            None,
+            header[2].into(),
+            None,
+            u16::from_le_bytes([header[0], header[1]]).into(),
+        ),
+        E2eProfile::P7 => (
+            Some(read_be(&header[8..12])),
+            read_be(&header[12..16]),
+            Some(read_be(&header[16..20])),
+            read_be(&header[0..8]),
+        ),
+    };
+    if let Some(length) = length
+        && length as usize != payload.len()
+    {
+        return Err(E2eError::WrongLength {
+            declared: length as usize,
+            actual: payload.len(),
+        });
+    }
+    if let Some(data_id) = data_id
+        && data_id != u64::from(config.data_id)
+    {
+        return Err(E2eError::WrongDataId(data_id as u32));
+    }
+    let computed_crc = compute_crc(payload, config);
+    if computed_crc != declared_crc {
+        return Err(E2eError::WrongCrc {
+            declared: declared_crc,
+            computed: computed_crc,
+        });
+    }
+    Ok(counter as u32)
+}
+
+/// Fills in the E2E header of `payload`, which must already have room for
+/// it at the configured offset.
+pub fn protect_e2e(payload: &mut [u8], config: &E2eConfig, counter: u32) -> Result<(), E2eError> {
+    let start = config.offset;
+    let end = start + config.profile.header_len();
+    if payload.len() < end {
+        return Err(E2eError::TooShort);
+    }
+    let length = payload.len();
+    let too_long = |_| E2eError::TooLong(length);
+    let header = &mut payload[start..end];
+    match config.profile {
+        E2eProfile::P4 => {
+            let length = u16::try_from(length).map_err(too_long)?;
+            header[0..2].copy_from_slice(&length.to_be_bytes());
+            header[2..4].copy_from_slice(&(counter as u16).to_be_bytes());
+            header[4..8].copy_from_slice(&config.data_id.to_be_bytes());
+        }
+        E2eProfile::P5 => header[2] = counter as u8,
+        E2eProfile::P7 => {
+            let length = u32::try_from(length).map_err(too_long)?;
+            header[8..12].copy_from_slice(&length.to_be_bytes());
+            header[12..16].copy_from_slice(&counter.to_be_bytes());
+            header[16..20].copy_from_slice(&config.data_id.to_be_bytes());
+        }
+    }
+    let crc = compute_crc(payload, config);
+    let header = &mut payload[start..end];
+    match config.profile {
+        E2eProfile::P4 => header[8..12].copy_from_slice(&(crc as u32).to_be_bytes()),
+        E2eProfile::P5 => header[0..2].copy_from_slice(&(crc as u16).to_le_bytes()),
+        E2eProfile::P7 => header[0..8].copy_from_slice(&crc.to_be_bytes()),
+    }
+    Ok(())
+}
+
+/// Verifies a sequence of protected payloads, including that each counter
+/// follows the previous one.
+#[derive(Clone, Debug)]
+pub struct E2eReceiver {
+    config: E2eConfig,
+    previous_counter: Option<u32>,
+}
+
+impl E2eReceiver {
+    pub fn new(config: E2eConfig) -> Self {
+        E2eReceiver {
+            config,
+            previous_counter: None,
+        }
+    }
+
+    /// Verifies `payload` like [`verify_e2e`] and returns its counter. Only
+    /// payloads that pass become the reference for the next counter.
+    pub fn check(&mut self, payload: &[u8]) -> Result<u32, E2eError> {
+        let counter = verify_e2e(payload, &self.config)?;
+        if let Some(previous) = self.previous_counter {
+            let range = self.config.profile.counter_range();
+            let delta = (u64::from(counter) + range - u64::from(previous)) % range;
+            if delta == 0 {
+                return Err(E2eError::Repeated(counter));
+            }
+            if delta > u64::from(self.config.max_delta_counter) {
+                self.previous_counter = Some(counter);
+                return Err(E2eError::WrongSequence { previous, counter });
+            }
+        }
+        self.previous_counter = Some(counter);
+        Ok(counter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc_check_values() {
+        assert_eq!(crc16(*b"123456789"), 0x29b1);
+        assert_eq!(crc32(*b"123456789"), 0x1697_d06a);
+        assert_eq!(crc64(*b"123456789"), 0x995d_c9bb_df19_39fa);
+    }
+
+    #[test]
+    fn test_e2e_known_answers() {
+        let vectors: [(E2eProfile, &[u8]); 3] = [
+            (
+                E2eProfile::P4,
+                &[
+                    0xab, 0xcd, 0x00, 0x12, 0x00, 0x07, 0x00, 0x00, 0x12, 0x34, 0x1a, 0x7b, 0x83,
+                    0x13, 0x01, 0x02, 0x03, 0x04,
+                ],
+            ),
+            (
+                E2eProfile::P5,
+                &[0xab, 0xcd, 0xeb, 0xfd, 0x07, 0x01, 0x02, 0x03, 0x04],
+            ),
+            (
+                E2eProfile::P7,
+                &[
+                    0xab, 0xcd, 0x62, 0xa5, 0xad, 0xca, 0x9e, 0x75, 0xbd, 0xc9, 0x00, 0x00, 0x00,
+                    0x1a, 0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x12, 0x34, 0x01, 0x02, 0x03, 0x04,
+                ],
+            ),
+        ];
+        for (profile, expected) in vectors {
+            let config = E2eConfig {
+                profile,
+                data_id: 0x1234,
+                offset: 2,
+                max_delta_counter: 1,
+            };
+            let mut payload = expected.to_vec();
+            payload[2..2 + profile.header_len()].fill(0);
+            protect_e2e(&mut payload, &config, 7).unwrap();
+
+            assert_eq!(payload, expected);
+            assert_eq!(verify_e2e(expected, &config), Ok(7));
+        }
+
+        let config = E2eConfig {
+            profile: E2eProfile::P4,
+            data_id: 0x1234,
+            offset: 0,
+            max_delta_counter: 1,
+        };
+        let mut payload = vec![0; 0x10000];
+        assert_eq!(
+            protect_e2e(&mut payload, &config, 0),
+            Err(E2eError::TooLong(0x10000))
+        );
+    }
+
+    #[test]
+    fn test_verify_e2e() {
+        for profile in [E2eProfile::P4, E2eProfile::P5, E2eProfile::P7] {
+            let config = E2eConfig {
+                profile,
+                data_id: 0x1234,
+                offset: 2,
+                max_delta_counter: 2,
+            };
+            let mut payload = vec![0xab, 0xcd];
+            payload.resize(2 + profile.header_len(), 0);
+            payload.extend([0x1, 0x2, 0x3, 0x4]);
+            protect_e2e(&mut payload, &config, 7).unwrap();
+
+            assert_eq!(verify_e2e(&payload, &config), Ok(7));
+
+            let mut corrupted = payload.clone();
+            corrupted[1] ^= 0x10;
+            assert!(matches!(
+                verify_e2e(&corrupted, &config),
+                Err(E2eError::WrongCrc { .. })
+            ));
+
+            let mut receiver = E2eReceiver::new(config.clone());
+            assert_eq!(receiver.check(&payload), Ok(7));
+            assert_eq!(receiver.check(&payload), Err(E2eError::Repeated(7)));
+            protect_e2e(&mut payload, &config, 10).unwrap();
+            assert_eq!(
+                receiver.check(&payload),
+                Err(E2eError::WrongSequence {
+                    previous: 7,
+                    counter: 10
+                })
+            );
+        }
+    }
+}
@@ -0,0 +1,578 @@
+//! Serialization of headers and values back into their wire format.
+//!
+//! Encoding is the inverse of decoding with the same `SomeIPType` for plain
+//! structs, arrays, strings and `Opaque` data: a value decoded from those
+//! bytes encodes back to exactly the same bytes, including alignment padding.
+//! Other types lose what their value does not hold:
+//!
+//! - members a length prefixed struct skipped when decoding are not written
+//!   back, and its length shrinks accordingly
+//! - a `Union` is written without the padding after its variant
+//! - a UTF-16 byte order mark is not written back
+//! - bools are written as 0 or 1, whatever byte they were decoded from
+//!
+//! `Float16`, `SignalGroup`, `Ref` and `StructLayout::Tlv` cannot be encoded
+//! at all and fail with `EncodeError::Unsupported`.
+
+use crate::{LengthUnit, SomeIPHeader, SomeIPType, StringCoding, StructLayout, Value};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EncodeError {
+    /// The value does not have the shape the type describes.
+    TypeMismatch,
+    /// An integer does not fit into the wire type.
+    OutOfRange,
+    /// A struct value does not match the fields of its type.
+    FieldMismatch(String),
+    /// An enum value names a variant the type does not define.
+    UnknownVariant(String),
+    /// A static array or string does not have its declared length.
+    LengthMismatch { expected: usize, actual: usize },
+    /// A dynamic length does not fit into its length field.
+    InvalidLengthWidth(u8),
+    /// The type cannot be encoded yet.
+    Unsupported,
+}
+
+/// Appends the 16 header bytes to `out`.
+pub fn encode_header(header: &SomeIPHeader, out: &mut Vec<u8>) -> Result<(), EncodeError> {
+    out.extend_from_slice(&header.service_id.0.to_be_bytes());
+    out.extend_from_slice(&header.method_id.0.to_be_bytes());
+    out.extend_from_slice(&header.length.to_be_bytes());
+    out.extend_from_slice(&header.client_id.to_be_bytes());
+    out.extend_from_slice(&header.session_id.to_be_bytes());
+    out.push(header.protocol_version);
+    out.push(header.interface_version);
+    out.push(header.message_type.as_u8());
+    out.push(header.return_code.into());
+    Ok(())
+}
+
+/// Encodes a full message, deriving the header `length` from the payload.
+pub fn encode_message(
+    header: &SomeIPHeader,
+    value: &Value,
+    payload_type: &SomeIPType,
+) -> Result<Vec<u8>, EncodeError> {
+    let mut payload = Vec::new();
+    encode_value(value, payload_type, &mut payload)?;
+    let length = u32::try_from(payload.len() + 8).map_err(|_| EncodeError::OutOfRange)?;
+
+    let mut out = Vec::with_capacity(16 + payload.len());
+    encode_header(
+        &SomeIPHeader {
+            length,
+            ..header.clone()
+        },
+        &mut out,
+    )?;
+    out.extend_from_slice(&payload);
+    Ok(out)
+}
+
+/// Appends `value` encoded as `def` to `out`.
+///
+/// Alignment is computed relative to the start of `out`, which is expected to
+/// hold nothing but the payload encoded so far.
+pub fn encode_value(value: &Value, def: &SomeIPType, out: &mut Vec<u8>) -> Result<(), EncodeError> {
+    match (def, value) {
+        (SomeIPType::Bool, Value::Bool(v)) => out.push(u8::from(*v)),
+        (SomeIPType::UInt8, Value::UInt(v)) => out.push(narrow::<u8>(*v)?),
+        (SomeIPType::UInt16, Value::UInt(v)) => {
+            out.extend_from_slice(&narrow::<u16>(*v)?.to_be_bytes())
+        }
+        (SomeIPType::UInt32, Value::UInt(v)) => {
+            out.extend_from_slice(&narrow::<u32>(*v)?.to_be_bytes())
+        }
+        (SomeIPType::UInt64, Value::UInt(v)) => out.extend_from_slice(&v.to_be_bytes()),
+        (SomeIPType::SInt8, Value::Int(v)) => {
+            out.extend_from_slice(&narrow::<i8>(*v)?.to_be_bytes())
+        }
+        (SomeIPType::SInt16, Value::Int(v)) => {
+            out.extend_from_slice(&narrow::<i16>(*v)?.to_be_bytes())
+        }
+        (SomeIPType::SInt32, Value::Int(v)) => {
+            out.extend_from_slice(&narrow::<i32>(*v)?.to_be_bytes())
+        }
+        (SomeIPType::SInt64, Value::Int(v)) => out.extend_from_slice(&v.to_be_bytes()),
+        (SomeIPType::UInt128, Value::UInt128(v)) => out.extend_from_slice(&v.to_be_bytes()),
+        (SomeIPType::SInt128, Value::Int128(v)) => out.extend_from_slice(&v.to_be_bytes()),
+        (
+            SomeIPType::Struct {
+                fields,
+                alignment,
+                length_width,
+                layout,
+            },
+            Value::Struct { fields: values },
+        ) => {
+            let length_pos = out.len();
+            if let Some(length_width) = length_width {
+                encode_length(0, *length_width, out)?;
+            }
+            let start = out.len();
+            match layout {
+                StructLayout::Sequential => {
+                    if fields.len() != values.len() {
+                        return Err(EncodeError::LengthMismatch {
+                            expected: fields.len(),
+                            actual: values.len(),
+                        });
+                    }
+                    for ((name, def), (value_name, value)) in fields.iter().zip(values) {
+                        if name != value_name {
+                            return Err(EncodeError::FieldMismatch(value_name.clone()));
+                        }
+                        pad(*alignment, out);
+                        encode_value(value, def, out)?;
+                    }
+                }
+                StructLayout::BitmaskOptional { mask_width } => {
+                    encode_present(fields, values, *mask_width, *alignment, out)?
+                }
+                StructLayout::Tlv => return Err(EncodeError::Unsupported),
+            }
+            if let Some(length_width) = length_width {
+                let mut length = Vec::new();
+                encode_length(out.len() - start, *length_width, &mut length)?;
+                out[length_pos..start].copy_from_slice(&length);
+            }
+        }
+        (SomeIPType::StaticArray { length, element }, Value::Array(elements)) => {
+            if elements.len() != *length as usize {
+                return Err(EncodeError::LengthMismatch {
+                    expected: *length as usize,
+                    actual: elements.len(),
+                });
+            }
+            for element_value in elements {
+                encode_value(element_value, element, out)?;
+            }
+        }
+        (
+            SomeIPType::DynamicArray {
+                length_width,
+                element,
+                length_unit,
+                ..
+            },
+            Value::Array(elements),
+        ) => {
+            let length_pos = out.len();
+            encode_length(elements.len(), *length_width, out)?;
+            let start = out.len();
+            for element_value in elements {
+                encode_value(element_value, element, out)?;
+            }
+            if *length_unit == LengthUnit::Bytes {
+                let mut length = Vec::new();
+                encode_length(out.len() - start, *length_width, &mut length)?;
+                out[length_pos..start].copy_from_slice(&length);
+            }
+        }
+        (SomeIPType::SentinelArray { element, sentinel }, Value::Array(elements)) => {
+            for element_value in elements {
+                encode_value(element_value, element, out)?;
+            }
+            encode_value(sentinel, element, out)?;
+        }
+        (
+            SomeIPType::Enum {
+                open_enum: true,
+                base,
+                ..
+            },
+            Value::EnumRaw(discriminant),
+        ) => encode_discriminant(*discriminant, base.as_deref(), out)?,
+        (SomeIPType::Enum { variants, base, .. }, Value::Enum(name)) => {
+            let (discriminant, _) = variants
+                .iter()
+                .find(|(_, variant)| variant == name)
+                .ok_or_else(|| EncodeError::UnknownVariant(name.clone()))?;
+            encode_discriminant(*discriminant, base.as_deref(), out)?;
+        }
+        (
+            SomeIPType::EnumSelectedStruct {
+                enum_def,
+                variant_structs,
+            },
+            Value::Struct { fields },
+        ) => {
+            let [(_, selector), (_, value)] = fields.as_slice() else {
+                return Err(EncodeError::TypeMismatch);
+            };
+            let Value::Enum(variant) = selector else {
+                return Err(EncodeError::TypeMismatch);
+            };
+            let variant_def = variant_structs
+                .get(variant)
+                .ok_or_else(|| EncodeError::UnknownVariant(variant.clone()))?;
+            encode_value(selector, enum_def, out)?;
+            encode_value(value, variant_def, out)?;
+        }
+        (SomeIPType::HeaderCountBody { count, element }, Value::Struct { fields }) => {
+            let [(_, Value::UInt(length)), (_, Value::Array(items))] = fields.as_slice() else {
+                return Err(EncodeError::TypeMismatch);
+            };
+            if *length != items.len() as u64 {
+                return Err(EncodeError::LengthMismatch {
+                    expected: *length as usize,
+                    actual: items.len(),
+                });
+            }
+            encode_value(&Value::UInt(*length), count, out)?;
+            for item in items {
+                encode_value(item, element, out)?;
+            }
+        }
+        (
+            SomeIPType::Union {
+                length_width,
+                type_width,
+                variants,
+            },
+            Value::Struct { fields },
+        ) => {
+            let [(_, Value::UInt(selector)), (_, value)] = fields.as_slice() else {
+                return Err(EncodeError::TypeMismatch);
+            };
+            let (_, variant_def) = variants
+                .iter()
+                .find(|(tag, _)| tag == selector)
+                .ok_or_else(|| EncodeError::UnknownVariant(selector.to_string()))?;
+            let length_pos = out.len();
+            encode_length(0, *length_width, out)?;
+            encode_length(*selector as usize, *type_width, out)?;
+            let start = out.len();
+            encode_value(value, variant_def, out)?;
+            let mut length = Vec::new();
+            encode_length(out.len() - start, *length_width, &mut length)?;
+            out[length_pos..length_pos + length.len()].copy_from_slice(&length);
+        }
+        (
+            SomeIPType::OptionalGroup {
+                mask_width,
+                members,
+            },
+            Value::Struct { fields: values },
+        ) => encode_present(members, values, *mask_width, 1, out)?,
+        (SomeIPType::Bitfield { storage, fields }, Value::Struct { fields: values }) => {
+            let used = fields.iter().map(|(_, bits)| u16::from(*bits)).sum();
+            let width =
+                crate::storage_width(storage, used).map_err(|_| EncodeError::Unsupported)?;
+            if fields.len() != values.len() {
+                return Err(EncodeError::LengthMismatch {
+                    expected: fields.len(),
+                    actual: values.len(),
+                });
+            }
+            let mut raw = 0u64;
+            let mut shift = u32::from(width);
+            for ((name, bits), (value_name, value)) in fields.iter().zip(values) {
+                if name != value_name {
+                    return Err(EncodeError::FieldMismatch(value_name.clone()));
+                }
+                let Value::UInt(value) = value else {
+                    return Err(EncodeError::TypeMismatch);
+                };
+                shift -= u32::from(*bits);
+                if u64::from(*bits) < 64 && *value >> bits != 0 {
+                    return Err(EncodeError::OutOfRange);
+                }
+                raw |= value.checked_shl(shift).unwrap_or(0);
+            }
+            encode_length(raw as usize, width, out)?;
+        }
+        (SomeIPType::StaticString { length, coding }, Value::String(string)) => {
+            let bytes = encode_string(string, coding);
+            if bytes.len() != *length as usize {
+                return Err(EncodeError::LengthMismatch {
+                    expected: *length as usize,
+                    actual: bytes.len(),
+                });
+            }
+            out.extend_from_slice(&bytes);
+        }
+        (
+            SomeIPType::DynamicString {
+                length_width,
+                coding,
+            },
+            Value::String(string),
+        ) => {
+            let bytes = encode_string(string, coding);
+            encode_length(bytes.len(), *length_width, out)?;
+            out.extend_from_slice(&bytes);
+        }
+        (SomeIPType::CString { coding }, Value::String(string)) => {
+            // a NUL within the string would end it early
+            if string.contains('\0') {
+                return Err(EncodeError::TypeMismatch);
+            }
+            out.extend_from_slice(&encode_string(string, coding));
+            let terminator_len = match coding {
+                Some(StringCoding::Utf16) => 2,
+                _ => 1,
+            };
+            out.resize(out.len() + terminator_len, 0);
+        }
+        (SomeIPType::Opaque { length }, Value::Bytes(bytes)) => {
+            if let Some(length) = length
+                && bytes.len() != *length as usize
+            {
+                return Err(EncodeError::LengthMismatch {
+                    expected: *length as usize,
+                    actual: bytes.len(),
+                });
+            }
+            out.extend_from_slice(bytes);
+        }
+        (SomeIPType::Float32, Value::Float(v)) => out.extend_from_slice(&(*v as f32).to_be_bytes()),
+        (SomeIPType::Float64, Value::Float(v)) => out.extend_from_slice(&v.to_be_bytes()),
+        (
+            SomeIPType::QFixed {
+                int_bits,
+                frac_bits,
+                signed,
+            },
+            Value::Float(v),
+        ) => {
+            let width = u32::from(*int_bits) + u32::from(*frac_bits);
+            if !matches!(width, 8 | 16 | 32 | 64) {
+                return Err(EncodeError::Unsupported);
+            }
+            let raw = (v * 2f64.powi((*frac_bits).into())).round();
+            let (min, max) = if *signed {
+                (
+                    -(2f64.powi(width as i32 - 1)),
+                    2f64.powi(width as i32 - 1) - 1.0,
+                )
+            } else {
+                (0.0, 2f64.powi(width as i32) - 1.0)
+            };
+            if !(min..=max).contains(&raw) {
+                return Err(EncodeError::OutOfRange);
+            }
+            let raw = if *signed {
+                raw as i64 as u64
+            } else {
+                raw as u64
+            };
+            out.extend_from_slice(&raw.to_be_bytes()[8 - width as usize / 8..]);
+        }
+        (SomeIPType::Constrained { base, min, max }, Value::UInt(_) | Value::Int(_)) => {
+            let number = match value {
+                Value::UInt(v) => i128::from(*v),
+                Value::Int(v) => i128::from(*v),
+                _ => unreachable!(),
+            };
+            if !(*min..=*max).contains(&number) {
+                return Err(EncodeError::OutOfRange);
+            }
+            encode_value(value, base, out)?;
+        }
+        (
+            SomeIPType::Scaled {
+                base,
+                keep_raw: true,
+                ..
+            },
+            Value::Struct { fields },
+        ) => {
+            let [(_, _), (_, raw)] = fields.as_slice() else {
+                return Err(EncodeError::TypeMismatch);
+            };
+            encode_value(raw, base, out)?;
+        }
+        (
+            SomeIPType::Scaled {
+                base,
+                factor,
+                offset,
+                keep_raw: false,
+            },
+            Value::Float(v),
+        ) => {
+            let raw = ((v - offset) / factor).round();
+            let raw = match **base {
+                SomeIPType::UInt8
+                | SomeIPType::UInt16
+                | SomeIPType::UInt32
+                | SomeIPType::UInt64 => {
+                    if !(0.0..=u64::MAX as f64).contains(&raw) {
+                        return Err(EncodeError::OutOfRange);
+                    }
+                    Value::UInt(raw as u64)
+                }
+                _ => {
+                    if !(i64::MIN as f64..=i64::MAX as f64).contains(&raw) {
+                        return Err(EncodeError::OutOfRange);
+                    }
+                    Value::Int(raw as i64)
+                }
+            };
+            encode_value(&raw, base, out)?;
+        }
+        (SomeIPType::Float16 | SomeIPType::SignalGroup { .. } | SomeIPType::Ref(_), _) => {
+            return Err(EncodeError::Unsupported);
+        }
+        _ => return Err(EncodeError::TypeMismatch),
+    }
+    Ok(())
+}
+
+fn pad(alignment: u8, out: &mut Vec<u8>) {
+    let alignment = usize::from(alignment.max(1));
+    let padding = (alignment - out.len() % alignment) % alignment;
+    out.resize(out.len() + padding, 0);
+}
+
+/// Encodes the members of `values`, a subset of `members` in their order,
+/// preceded by a mask of `mask_width` bits telling which are present.
+fn encode_present(
+    members: &[(String, SomeIPType)],
+    values: &[(String, Value)],
+    mask_width: u8,
+    alignment: u8,
+    out: &mut Vec<u8>,
+) -> Result<(), EncodeError> {
+    let mask_pos = out.len();
+    encode_length(0, mask_width, out)?;
+    let mut mask = 0u64;
+    let mut values = values.iter().peekable();
+    for (bit, (name, def)) in members.iter().enumerate() {
+        if let Some((_, value)) = values.next_if(|(value_name, _)| value_name == name) {
+            mask |= 1u64
+                .checked_shl(bit as u32)
+                .ok_or(EncodeError::OutOfRange)?;
+            pad(alignment, out);
+            encode_value(value, def, out)?;
+        }
+    }
+    // absent members may be left out, but the rest must keep their order
+    if let Some((name, _)) = values.next() {
+        return Err(EncodeError::FieldMismatch(name.clone()));
+    }
+    let mut encoded_mask = Vec::new();
+    encode_length(mask as usize, mask_width, &mut encoded_mask)?;
+    out[mask_pos..mask_pos + encoded_mask.len()].copy_from_slice(&encoded_mask);
+    Ok(())
+}
+
+fn narrow<T: TryFrom<i128>>(value: impl Into<i128>) -> Result<T, EncodeError> {
+    T::try_from(value.into()).map_err(|_| EncodeError::OutOfRange)
+}
+
+/// Encodes a string in its coding; UTF-16 is written big endian without BOM.
+fn encode_string(string: &str, coding: &Option<StringCoding>) -> Vec<u8> {
+    match coding {
+        None | Some(StringCoding::Utf8) => string.as_bytes().to_vec(),
+        Some(StringCoding::Utf16) => string.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+    }
+}
+
+/// Encodes an enum discriminant as its integer type `base`, `UInt8` if not
+/// set; discriminants of signed bases are sign extended.
+fn encode_discriminant(
+    discriminant: u64,
+    base: Option<&SomeIPType>,
+    out: &mut Vec<u8>,
+) -> Result<(), EncodeError> {
+    let base = base.unwrap_or(&SomeIPType::UInt8);
+    let value = match base {
+        SomeIPType::SInt8 | SomeIPType::SInt16 | SomeIPType::SInt32 | SomeIPType::SInt64 => {
+            Value::Int(discriminant as i64)
+        }
+        _ => Value::UInt(discriminant),
+    };
+    encode_value(&value, base, out)
+}
+
+fn encode_length(length: usize, length_width: u8, out: &mut Vec<u8>) -> Result<(), EncodeError> {
+    let length = length as u64;
+    match length_width {
+        8 => out.push(narrow::<u8>(length)?),
+        16 => out.extend_from_slice(&narrow::<u16>(length)?.to_be_bytes()),
+        32 => out.extend_from_slice(&narrow::<u32>(length)?.to_be_bytes()),
+        64 => out.extend_from_slice(&length.to_be_bytes()),
+        _ => return Err(EncodeError::InvalidLengthWidth(length_width)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::some_ip_message;
+
+    #[test]
+    fn test_roundtrip_message_with_opaque_tail() {
+        let bytes: Vec<u8> = vec![
+            0x12, 0x34, 0x00, 0x01, 0x0, 0x0, 0x0, 0x13, 0x0, 0x1, 0x0, 0x2, 0x1, 0x1, 0x0, 0x0,
+            0xab, 0xcd, 0x0, 0x0, 0x0, 0x2a, 0x1, 0x2, 0x3, 0x4, 0x5,
+        ];
+        let slice = bytes.as_slice();
+        let def = SomeIPType::Struct {
+            fields: vec![
+                ("id".to_string(), SomeIPType::UInt16),
+                ("value".to_string(), SomeIPType::UInt32),
+                ("tail".to_string(), SomeIPType::Opaque { length: None }),
+            ],
+            alignment: 1,
+            length_width: None,
+            layout: StructLayout::Sequential,
+        };
+        let (remaining, (header, value)) = some_ip_message(slice, &def).unwrap();
+
+        assert_eq!(remaining.len(), 0);
+        assert_eq!(
+            value,
+            Value::Struct {
+                fields: vec![
+                    ("id".to_string(), Value::UInt(0xabcd)),
+                    ("value".to_string(), Value::UInt(0x2a)),
+                    (
+                        "tail".to_string(),
+                        Value::Bytes(vec![0x1, 0x2, 0x3, 0x4, 0x5])
+                    ),
+                ]
+            }
+        );
+        assert_eq!(encode_message(&header, &value, &def).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_encode_value_out_of_range() {
+        let mut out = Vec::new();
+
+        assert_eq!(
+            encode_value(&Value::UInt(0x100), &SomeIPType::UInt8, &mut out),
+            Err(EncodeError::OutOfRange)
+        );
+        assert_eq!(
+            encode_value(
+                &Value::UInt(0x1),
+                &SomeIPType::Ref("Point".to_string()),
+                &mut out
+            ),
+            Err(EncodeError::Unsupported)
+        );
+    }
+
+    #[test]
+    fn test_encode_qfixed() {
+        let def = SomeIPType::QFixed {
+            int_bits: 8,
+            frac_bits: 8,
+            signed: true,
+        };
+        let mut out = Vec::new();
+        encode_value(&Value::Float(-1.5), &def, &mut out).unwrap();
+
+        assert_eq!(out, vec![0xfe, 0x80]);
+        assert_eq!(
+            encode_value(&Value::Float(128.0), &def, &mut out),
+            Err(EncodeError::OutOfRange)
+        );
+    }
+}
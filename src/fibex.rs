@@ -0,0 +1,483 @@
+//! Loading of service interfaces from FIBEX files.
+//!
+//! Only the datatypes common in SOME/IP interfaces are understood: integers,
+//! floats, strings, structures, arrays and enumerations. Datatype references
+//! are inlined, so every resulting `SomeIPType` is self-contained.
+
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use roxmltree::{Document, Node};
+
+use crate::{
+    LengthUnit, SomeIPType, StringCoding, StructLayout,
+    catalog::InterfaceCatalog,
+    registry::TypeRegistry,
+    xml::{child, is, text},
+};
+
+/// Maximum nesting of datatypes, which stops reference cycles.
+const MAX_DEPTH: usize = 64;
+
+/// SOME/IP length fields are 32 bits unless configured otherwise.
+const DEFAULT_LENGTH_WIDTH: u8 = 32;
+
+#[derive(Debug)]
+pub enum FibexError {
+    Xml(roxmltree::Error),
+    /// A required element or attribute is missing.
+    Missing(&'static str),
+    /// An `ID-REF` points to no element with that `ID`.
+    UnknownReference(String),
+    /// A number could not be parsed.
+    InvalidNumber(String),
+    /// A datatype or coding outside of the supported subset.
+    Unsupported(String),
+    /// Datatypes nest too deep, e.g. because they refer to each other.
+    DepthLimitExceeded,
+}
+
+impl fmt::Display for FibexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FibexError::Xml(error) => write!(f, "invalid XML: {error}"),
+            FibexError::Missing(name) => write!(f, "missing {name}"),
+            FibexError::UnknownReference(id) => write!(f, "unknown reference {id:?}"),
+            FibexError::InvalidNumber(text) => write!(f, "invalid number {text:?}"),
+            FibexError::Unsupported(what) => write!(f, "unsupported {what}"),
+            FibexError::DepthLimitExceeded => write!(f, "datatype nesting limit exceeded"),
+        }
+    }
+}
+
+impl std::error::Error for FibexError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FibexError::Xml(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<roxmltree::Error> for FibexError {
+    fn from(error: roxmltree::Error) -> Self {
+        FibexError::Xml(error)
+    }
+}
+
+/// The contents of a FIBEX file.
+#[derive(Debug, Default)]
+pub struct Fibex {
+    /// Every datatype, registered under its short name.
+    pub datatypes: TypeRegistry,
+    /// The arguments and return values of every method, as structs with one
+    /// field per parameter.
+    pub catalog: InterfaceCatalog,
+}
+
+pub fn parse_fibex(xml: &str) -> Result<Fibex, FibexError> {
+    let document = Document::parse(xml)?;
+    let ids: HashMap<&str, Node> = document
+        .descendants()
+        .filter_map(|node| Some((node.attribute("ID")?, node)))
+        .collect();
+    let loader = Loader { ids };
+
+    let mut fibex = Fibex::default();
+    for datatype in document.descendants().filter(|node| is(node, "DATATYPE")) {
+        let name = text(datatype, "SHORT-NAME").ok_or(FibexError::Missing("SHORT-NAME"))?;
+        fibex
+            .datatypes
+            .register(name, loader.datatype(datatype, 0)?);
+    }
+    for interface in document
+        .descendants()
+        .filter(|node| is(node, "SERVICE-INTERFACE"))
+    {
+        let service_id = number(interface, "SERVICE-IDENTIFIER")?;
+        for method in interface.descendants().filter(|node| is(node, "METHOD")) {
+            let method_id = number(method, "METHOD-IDENTIFIER")?;
+            let request = loader.parameters(method, "INPUT-PARAMETERS")?;
+            let response = match text(method, "CALL-SEMANTIC") {
+                Some("FIRE_AND_FORGET") => None,
+                _ => Some(loader.parameters(method, "RETURN-PARAMETERS")?),
+            };
+            fibex
+                .catalog
+                .register(service_id, method_id, request, response);
+        }
+    }
+    Ok(fibex)
+}
+
+struct Loader<'a, 'input> {
+    /// Every element carrying an `ID`, by that id.
+    ids: HashMap<&'a str, Node<'a, 'input>>,
+}
+
+impl<'a, 'input> Loader<'a, 'input> {
+    fn reference(&self, node: Node, name: &'static str) -> Result<Node<'a, 'input>, FibexError> {
+        let id = child(node, name)
+            .and_then(|reference| reference.attribute("ID-REF"))
+            .ok_or(FibexError::Missing(name))?;
+        self.ids
+            .get(id)
+            .copied()
+            .ok_or_else(|| FibexError::UnknownReference(id.to_string()))
+    }
+
+    /// A struct of the parameters in the `list` element of `method`, empty if
+    /// the method has none.
+    fn parameters(&self, method: Node, list: &str) -> Result<SomeIPType, FibexError> {
+        let parameters = child(method, list)
+            .map(|list| list.children().filter(Node::is_element).collect())
+            .unwrap_or_default();
+        self.members(parameters, 0)
+    }
+
+    /// A struct of `members` ordered by their `POSITION`.
+    fn members(&self, mut members: Vec<Node>, depth: usize) -> Result<SomeIPType, FibexError> {
+        let mut positions = HashMap::new();
+        for member in &members {
+            positions.insert(member.id(), number::<u32>(*member, "POSITION")?);
+        }
+        members.sort_by_key(|member| positions[&member.id()]);
+
+        let fields = members
+            .into_iter()
+            .map(|member| {
+                let name = text(member, "SHORT-NAME").ok_or(FibexError::Missing("SHORT-NAME"))?;
+                let datatype = self.reference(member, "DATATYPE-REF")?;
+                let def = self.datatype(datatype, depth + 1)?;
+                Ok((name.to_string(), array(member, def)?))
+            })
+            .collect::<Result<_, FibexError>>()?;
+        Ok(SomeIPType::Struct {
+            fields,
+            alignment: 1,
+            length_width: None,
+            layout: StructLayout::Sequential,
+        })
+    }
+
+    fn datatype(&self, datatype: Node, depth: usize) -> Result<SomeIPType, FibexError> {
+        if depth > MAX_DEPTH {
+            return Err(FibexError::DepthLimitExceeded);
+        }
+        match xsi_type(datatype) {
+            Some("COMMON-DATATYPE-TYPE") => coded_type(self.reference(datatype, "CODING-REF")?),
+            Some("ENUM-DATATYPE-TYPE") => {
+                let coded = coded_type(self.reference(datatype, "CODING-REF")?)?;
+                if coded != SomeIPType::UInt8 {
+                    return Err(FibexError::Unsupported(format!(
+                        "enumeration coding {coded:?}"
+                    )));
+                }
+                let variants = datatype
+                    .descendants()
+                    .filter(|node| is(node, "ENUM-ELEMENT"))
+                    .map(|element| {
+                        let name =
+                            text(element, "SYNONYM").ok_or(FibexError::Missing("SYNONYM"))?;
+                        Ok((number(element, "VALUE")?, name.to_string()))
+                    })
+                    .collect::<Result<_, FibexError>>()?;
+                Ok(SomeIPType::Enum {
+                    variants,
+                    open_enum: false,
+                    base: None,
+                })
+            }
+            Some("COMPLEX-DATATYPE-TYPE") => match text(datatype, "COMPLEX-DATATYPE-CLASS") {
+                Some("STRUCTURE") => {
+                    let members = child(datatype, "MEMBERS")
+                        .map(|members| members.children().filter(Node::is_element).collect())
+                        .unwrap_or_default();
+                    self.members(members, depth)
+                }
+                class => Err(FibexError::Unsupported(format!(
+                    "complex datatype class {class:?}"
+                ))),
+            },
+            other => Err(FibexError::Unsupported(format!("datatype type {other:?}"))),
+        }
+    }
+}
+
+/// The type described by the `CODED-TYPE` of `coding`.
+fn coded_type(coding: Node) -> Result<SomeIPType, FibexError> {
+    let coded = child(coding, "CODED-TYPE").ok_or(FibexError::Missing("CODED-TYPE"))?;
+    let base = attribute(coded, "BASE-DATA-TYPE").ok_or(FibexError::Missing("BASE-DATA-TYPE"))?;
+    let category = attribute(coded, "CATEGORY");
+    let bits = || number::<u32>(coded, "BIT-LENGTH");
+
+    let def = match base {
+        "A_UINT8" => SomeIPType::UInt8,
+        "A_UINT16" => SomeIPType::UInt16,
+        "A_UINT32" => SomeIPType::UInt32,
+        "A_UINT64" => SomeIPType::UInt64,
+        "A_INT8" => SomeIPType::SInt8,
+        "A_INT16" => SomeIPType::SInt16,
+        "A_INT32" => SomeIPType::SInt32,
+        "A_INT64" => SomeIPType::SInt64,
+        "A_FLOAT32" => SomeIPType::Float32,
+        "A_FLOAT64" => SomeIPType::Float64,
+        "A_UNICODE2STRING" | "A_ASCIISTRING" => {
+            let coding = match attribute(coded, "ENCODING") {
+                None | Some("UTF-8") => Some(StringCoding::Utf8),
+                Some("UTF-16") | Some("UCS-2") => Some(StringCoding::Utf16),
+                Some(other) => {
+                    return Err(FibexError::Unsupported(format!(
+                        "string encoding {other:?}"
+                    )));
+                }
+            };
+            match category {
+                // the bit length is that of the length field
+                Some("LEADING-LENGTH-INFO-TYPE") => SomeIPType::DynamicString {
+                    length_width: width(bits()?)?,
+                    coding,
+                },
+                Some("STANDARD-LENGTH-TYPE") => SomeIPType::StaticString {
+                    length: bits()? / 8,
+                    coding,
+                },
+                other => {
+                    return Err(FibexError::Unsupported(format!(
+                        "string category {other:?}"
+                    )));
+                }
+            }
+        }
+        other => return Err(FibexError::Unsupported(format!("base data type {other:?}"))),
+    };
+    Ok(def)
+}
+
+/// Wraps `def` in the arrays declared on `member`, outermost dimension first.
+fn array(member: Node, def: SomeIPType) -> Result<SomeIPType, FibexError> {
+    let Some(declaration) = child(member, "ARRAY-DECLARATION") else {
+        return Ok(def);
+    };
+    let mut dimensions = declaration
+        .children()
+        .filter(|node| is(node, "ARRAY-DIMENSION"))
+        .map(|dimension| Ok((number::<u32>(dimension, "DIMENSION")?, dimension)))
+        .collect::<Result<Vec<_>, FibexError>>()?;
+    dimensions.sort_by_key(|(index, _)| *index);
+
+    dimensions
+        .into_iter()
+        .rev()
+        .try_fold(def, |element, (_, dimension)| {
+            let element = Box::new(element);
+            let minimum = number::<u32>(dimension, "MINIMUM-SIZE")?;
+            let maximum = number::<u32>(dimension, "MAXIMUM-SIZE")?;
+            if minimum == maximum {
+                return Ok(SomeIPType::StaticArray {
+                    length: maximum,
+                    element,
+                });
+            }
+            let length_width = match text(dimension, "ARRAY-LENGTH-FIELD-SIZE") {
+                Some(_) => width(number(dimension, "ARRAY-LENGTH-FIELD-SIZE")?)?,
+                None => DEFAULT_LENGTH_WIDTH,
+            };
+            Ok(SomeIPType::DynamicArray {
+                length_width,
+                element,
+                length_unit: LengthUnit::Bytes,
+                min_length: None,
+                max_length: None,
+            })
+        })
+}
+
+/// A length field width in bits.
+fn width(bits: u32) -> Result<u8, FibexError> {
+    match bits {
+        8 | 16 | 32 | 64 => Ok(bits as u8),
+        _ => Err(FibexError::Unsupported(format!(
+            "length field of {bits} bits"
+        ))),
+    }
+}
+
+fn number<T: FromStr>(node: Node, name: &'static str) -> Result<T, FibexError> {
+    let text = text(node, name).ok_or(FibexError::Missing(name))?;
+    text.parse()
+        .map_err(|_| FibexError::InvalidNumber(text.to_string()))
+}
+
+/// Attributes are matched by local name, whatever namespace prefix they use.
+fn attribute<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.attributes()
+        .find(|attribute| attribute.name() == name)
+        .map(|attribute| attribute.value())
+}
+
+/// The `xsi:type` of `node` without its namespace prefix.
+fn xsi_type<'a>(node: Node<'a, '_>) -> Option<&'a str> {
+    let value = attribute(node, "type")?;
+    Some(value.rsplit(':').next().unwrap_or(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SomeIPHeader;
+
+    const FIBEX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<fx:FIBEX xmlns:fx="http://www.asam.net/xml/fbx" xmlns:ho="http://www.asam.net/xml"
+    xmlns:service="http://www.asam.net/xml/fbx/services"
+    xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+  <fx:ELEMENTS>
+    <fx:SERVICE-INTERFACES>
+      <fx:SERVICE-INTERFACE ID="si_lights">
+        <ho:SHORT-NAME>Lights</ho:SHORT-NAME>
+        <fx:SERVICE-IDENTIFIER>4660</fx:SERVICE-IDENTIFIER>
+        <service:METHODS>
+          <service:METHOD ID="m_set">
+            <ho:SHORT-NAME>set</ho:SHORT-NAME>
+            <service:METHOD-IDENTIFIER>1</service:METHOD-IDENTIFIER>
+            <service:INPUT-PARAMETERS>
+              <service:INPUT-PARAMETER ID="p_mode">
+                <ho:SHORT-NAME>mode</ho:SHORT-NAME>
+                <fx:DATATYPE-REF ID-REF="dt_mode"/>
+                <service:POSITION>1</service:POSITION>
+              </service:INPUT-PARAMETER>
+              <service:INPUT-PARAMETER ID="p_lamp">
+                <ho:SHORT-NAME>lamp</ho:SHORT-NAME>
+                <fx:DATATYPE-REF ID-REF="dt_lamp"/>
+                <service:POSITION>0</service:POSITION>
+              </service:INPUT-PARAMETER>
+            </service:INPUT-PARAMETERS>
+            <service:RETURN-PARAMETERS>
+              <service:RETURN-PARAMETER ID="p_ok">
+                <ho:SHORT-NAME>ok</ho:SHORT-NAME>
+                <fx:DATATYPE-REF ID-REF="dt_uint8"/>
+                <service:POSITION>0</service:POSITION>
+              </service:RETURN-PARAMETER>
+            </service:RETURN-PARAMETERS>
+          </service:METHOD>
+        </service:METHODS>
+      </fx:SERVICE-INTERFACE>
+    </fx:SERVICE-INTERFACES>
+    <fx:DATATYPES>
+      <fx:DATATYPE xsi:type="fx:COMMON-DATATYPE-TYPE" ID="dt_uint8">
+        <ho:SHORT-NAME>UINT8</ho:SHORT-NAME>
+        <fx:CODING-REF ID-REF="c_uint8"/>
+      </fx:DATATYPE>
+      <fx:DATATYPE xsi:type="fx:COMMON-DATATYPE-TYPE" ID="dt_name">
+        <ho:SHORT-NAME>Name</ho:SHORT-NAME>
+        <fx:CODING-REF ID-REF="c_string"/>
+      </fx:DATATYPE>
+      <fx:DATATYPE xsi:type="fx:ENUM-DATATYPE-TYPE" ID="dt_mode">
+        <ho:SHORT-NAME>Mode</ho:SHORT-NAME>
+        <fx:CODING-REF ID-REF="c_uint8"/>
+        <fx:ENUMERATION-ELEMENTS>
+          <fx:ENUM-ELEMENT><fx:VALUE>0</fx:VALUE><fx:SYNONYM>Off</fx:SYNONYM></fx:ENUM-ELEMENT>
+          <fx:ENUM-ELEMENT><fx:VALUE>1</fx:VALUE><fx:SYNONYM>On</fx:SYNONYM></fx:ENUM-ELEMENT>
+        </fx:ENUMERATION-ELEMENTS>
+      </fx:DATATYPE>
+      <fx:DATATYPE xsi:type="fx:COMPLEX-DATATYPE-TYPE" ID="dt_lamp">
+        <ho:SHORT-NAME>Lamp</ho:SHORT-NAME>
+        <fx:COMPLEX-DATATYPE-CLASS>STRUCTURE</fx:COMPLEX-DATATYPE-CLASS>
+        <fx:MEMBERS>
+          <fx:MEMBER ID="mb_name">
+            <ho:SHORT-NAME>name</ho:SHORT-NAME>
+            <fx:DATATYPE-REF ID-REF="dt_name"/>
+            <fx:POSITION>0</fx:POSITION>
+          </fx:MEMBER>
+          <fx:MEMBER ID="mb_levels">
+            <ho:SHORT-NAME>levels</ho:SHORT-NAME>
+            <fx:DATATYPE-REF ID-REF="dt_uint8"/>
+            <fx:POSITION>1</fx:POSITION>
+            <fx:ARRAY-DECLARATION>
+              <fx:ARRAY-DIMENSION>
+                <fx:DIMENSION>1</fx:DIMENSION>
+                <fx:MINIMUM-SIZE>4</fx:MINIMUM-SIZE>
+                <fx:MAXIMUM-SIZE>4</fx:MAXIMUM-SIZE>
+              </fx:ARRAY-DIMENSION>
+            </fx:ARRAY-DECLARATION>
+          </fx:MEMBER>
+        </fx:MEMBERS>
+      </fx:DATATYPE>
+    </fx:DATATYPES>
+  </fx:ELEMENTS>
+  <fx:PROCESSING-INFORMATION>
+    <fx:CODINGS>
+      <fx:CODING ID="c_uint8">
+        <ho:SHORT-NAME>UINT8</ho:SHORT-NAME>
+        <ho:CODED-TYPE ho:BASE-DATA-TYPE="A_UINT8" CATEGORY="STANDARD-LENGTH-TYPE" ENCODING="UNSIGNED">
+          <ho:BIT-LENGTH>8</ho:BIT-LENGTH>
+        </ho:CODED-TYPE>
+      </fx:CODING>
+      <fx:CODING ID="c_string">
+        <ho:SHORT-NAME>String</ho:SHORT-NAME>
+        <ho:CODED-TYPE ho:BASE-DATA-TYPE="A_UNICODE2STRING" CATEGORY="LEADING-LENGTH-INFO-TYPE" ENCODING="UTF-8">
+          <ho:BIT-LENGTH>16</ho:BIT-LENGTH>
+        </ho:CODED-TYPE>
+      </fx:CODING>
+    </fx:CODINGS>
+  </fx:PROCESSING-INFORMATION>
+</fx:FIBEX>
+"#;
+
+    #[test]
+    fn test_parse_fibex() {
+        let fibex = parse_fibex(FIBEX).unwrap();
+        let lamp = SomeIPType::Struct {
+            fields: vec![
+                (
+                    "name".to_string(),
+                    SomeIPType::DynamicString {
+                        length_width: 16,
+                        coding: Some(StringCoding::Utf8),
+                    },
+                ),
+                (
+                    "levels".to_string(),
+                    SomeIPType::StaticArray {
+                        length: 4,
+                        element: Box::new(SomeIPType::UInt8),
+                    },
+                ),
+            ],
+            alignment: 1,
+            length_width: None,
+            layout: StructLayout::Sequential,
+        };
+        let request = SomeIPHeader::builder()
+            .service_id(0x1234)
+            .method_id(0x1)
+            .request()
+            .build();
+
+        assert_eq!(fibex.datatypes.get("Lamp"), Some(&lamp));
+        assert_eq!(
+            fibex.catalog.resolve(&request),
+            Some(&SomeIPType::Struct {
+                fields: vec![
+                    ("lamp".to_string(), lamp),
+                    (
+                        "mode".to_string(),
+                        SomeIPType::Enum {
+                            variants: vec![(0, "Off".to_string()), (1, "On".to_string())],
+                            open_enum: false,
+                            base: None,
+                        },
+                    ),
+                ],
+                alignment: 1,
+                length_width: None,
+                layout: StructLayout::Sequential,
+            })
+        );
+
+        let latin1 = FIBEX.replace(r#"ENCODING="UTF-8""#, r#"ENCODING="ISO-8859-1""#);
+        assert!(matches!(
+            parse_fibex(&latin1),
+            Err(FibexError::Unsupported(what)) if what.contains("ISO-8859-1")
+        ));
+    }
+}
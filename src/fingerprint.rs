@@ -0,0 +1,386 @@
+//! Stable hashes of type definitions, e.g. for cache keys.
+//!
+//! The hash is FNV-1a over a fixed encoding of the definition, so it does not
+//! change between runs, platforms or compiler versions.
+
+use crate::{LengthUnit, SomeIPType, StringCoding, StructLayout, Value};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.bytes(&[value]);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    /// Length prefixed, so consecutive strings cannot run into each other.
+    fn str(&mut self, value: &str) {
+        self.u64(value.len() as u64);
+        self.bytes(value.as_bytes());
+    }
+
+    fn coding(&mut self, coding: &Option<StringCoding>) {
+        self.u8(match coding {
+            None => 0,
+            Some(StringCoding::Utf8) => 1,
+            Some(StringCoding::Utf16) => 2,
+        });
+    }
+
+    fn def(&mut self, def: &SomeIPType) {
+        match def {
+            SomeIPType::Float16 => self.u8(0),
+            SomeIPType::Float32 => self.u8(1),
+            SomeIPType::Float64 => self.u8(2),
+            SomeIPType::QFixed {
+                int_bits,
+                frac_bits,
+                signed,
+            } => {
+                self.u8(3);
+                self.u8(*int_bits);
+                self.u8(*frac_bits);
+                self.u8((*signed).into());
+            }
+            SomeIPType::Scaled {
+                base,
+                factor,
+                offset,
+                keep_raw,
+            } => {
+                self.u8(25);
+                self.def(base);
+                self.u64(factor.to_bits());
+                self.u64(offset.to_bits());
+                self.u8((*keep_raw).into());
+            }
+            SomeIPType::OptionalGroup {
+                mask_width,
+                members,
+            } => {
+                self.u8(28);
+                self.u8(*mask_width);
+                self.u64(members.len() as u64);
+                for (name, def) in members {
+                    self.str(name);
+                    self.def(def);
+                }
+            }
+            SomeIPType::Constrained { base, min, max } => {
+                self.u8(27);
+                self.def(base);
+                self.bytes(&min.to_be_bytes());
+                self.bytes(&max.to_be_bytes());
+            }
+            SomeIPType::SInt8 => self.u8(4),
+            SomeIPType::SInt16 => self.u8(5),
+            SomeIPType::SInt32 => self.u8(6),
+            SomeIPType::SInt64 => self.u8(7),
+            SomeIPType::UInt8 => self.u8(8),
+            SomeIPType::UInt16 => self.u8(9),
+            SomeIPType::UInt32 => self.u8(10),
+            SomeIPType::UInt64 => self.u8(11),
+            SomeIPType::UInt128 => self.u8(30),
+            SomeIPType::SInt128 => self.u8(31),
+            SomeIPType::Bool => self.u8(32),
+            SomeIPType::Struct {
+                fields,
+                alignment,
+                length_width,
+                layout,
+            } => {
+                self.u8(12);
+                self.u8(*alignment);
+                // absent for plain structs, which keep their fingerprint
+                if let Some(length_width) = length_width {
+                    self.u8(0xff);
+                    self.u8(*length_width);
+                }
+                match layout {
+                    StructLayout::Sequential => {}
+                    StructLayout::Tlv => self.u8(0xfe),
+                    StructLayout::BitmaskOptional { mask_width } => {
+                        self.u8(0xfd);
+                        self.u8(*mask_width);
+                    }
+                }
+                self.u64(fields.len() as u64);
+                for (name, field) in fields {
+                    self.str(name);
+                    self.def(field);
+                }
+            }
+            SomeIPType::StaticArray { length, element } => {
+                self.u8(13);
+                self.u32(*length);
+                self.def(element);
+            }
+            SomeIPType::DynamicArray {
+                length_width,
+                element,
+                length_unit,
+                min_length,
+                max_length,
+            } => {
+                self.u8(14);
+                self.u8(*length_width);
+                self.u8(match length_unit {
+                    LengthUnit::Bytes => 0,
+                    LengthUnit::Elements => 1,
+                });
+                // absent for unbounded arrays, which keep their fingerprint
+                if let Some(min_length) = min_length {
+                    self.u8(0xff);
+                    self.u32(*min_length);
+                }
+                if let Some(max_length) = max_length {
+                    self.u8(0xfe);
+                    self.u32(*max_length);
+                }
+                self.def(element);
+            }
+            SomeIPType::SentinelArray { element, sentinel } => {
+                self.u8(15);
+                self.def(element);
+                self.value(sentinel);
+            }
+            SomeIPType::Enum {
+                variants,
+                open_enum,
+                base,
+            } => {
+                self.u8(16);
+                self.u64(variants.len() as u64);
+                for (tag, name) in variants {
+                    self.u64(*tag);
+                    self.str(name);
+                }
+                // only marked when set, so closed enums keep their fingerprint
+                if *open_enum {
+                    self.u8(1);
+                }
+                if let Some(base) = base {
+                    self.u8(2);
+                    self.def(base);
+                }
+            }
+            SomeIPType::EnumSelectedStruct {
+                enum_def,
+                variant_structs,
+            } => {
+                self.u8(17);
+                self.def(enum_def);
+                // map iteration order is random
+                let mut variants: Vec<_> = variant_structs.iter().collect();
+                variants.sort_by_key(|(name, _)| *name);
+                self.u64(variants.len() as u64);
+                for (name, variant_def) in variants {
+                    self.str(name);
+                    self.def(variant_def);
+                }
+            }
+            SomeIPType::HeaderCountBody { count, element } => {
+                self.u8(23);
+                self.def(count);
+                self.def(element);
+            }
+            SomeIPType::Union {
+                length_width,
+                type_width,
+                variants,
+            } => {
+                self.u8(22);
+                self.u8(*length_width);
+                self.u8(*type_width);
+                self.u64(variants.len() as u64);
+                for (tag, variant_def) in variants {
+                    self.u64(*tag);
+                    self.def(variant_def);
+                }
+            }
+            SomeIPType::Bitfield { storage, fields } => {
+                self.u8(24);
+                self.def(storage);
+                self.u64(fields.len() as u64);
+                for (name, bits) in fields {
+                    self.str(name);
+                    self.u8(*bits);
+                }
+            }
+            SomeIPType::SignalGroup { storage, signals } => {
+                self.u8(26);
+                self.def(storage);
+                self.u64(signals.len() as u64);
+                for signal in signals {
+                    self.str(&signal.name);
+                    self.u8(signal.offset_bits);
+                    self.u8(signal.width);
+                    self.u8(signal.signed.into());
+                    self.u64(signal.factor.to_bits());
+                    self.u64(signal.offset.to_bits());
+                    self.str(signal.unit.as_deref().unwrap_or(""));
+                }
+            }
+            SomeIPType::StaticString { length, coding } => {
+                self.u8(18);
+                self.u32(*length);
+                self.coding(coding);
+            }
+            SomeIPType::DynamicString {
+                length_width,
+                coding,
+            } => {
+                self.u8(19);
+                self.u8(*length_width);
+                self.coding(coding);
+            }
+            SomeIPType::CString { coding } => {
+                self.u8(29);
+                self.coding(coding);
+            }
+            SomeIPType::Opaque { length } => {
+                self.u8(20);
+                match length {
+                    Some(length) => {
+                        self.u8(1);
+                        self.u32(*length);
+                    }
+                    None => self.u8(0),
+                }
+            }
+            SomeIPType::Ref(name) => {
+                self.u8(21);
+                self.str(name);
+            }
+        }
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::Float(float) => {
+                self.u8(0);
+                self.u64(float.to_bits());
+            }
+            Value::UInt(uint) => {
+                self.u8(1);
+                self.u64(*uint);
+            }
+            Value::Int(int) => {
+                self.u8(2);
+                self.u64(*int as u64);
+            }
+            Value::UInt128(uint) => {
+                self.u8(10);
+                self.bytes(&uint.to_be_bytes());
+            }
+            Value::Int128(int) => {
+                self.u8(11);
+                self.bytes(&int.to_be_bytes());
+            }
+            Value::Bool(b) => {
+                self.u8(12);
+                self.u8(u8::from(*b));
+            }
+            Value::Struct { fields } => {
+                self.u8(3);
+                self.u64(fields.len() as u64);
+                for (name, field) in fields {
+                    self.str(name);
+                    self.value(field);
+                }
+            }
+            Value::Array(elements) => {
+                self.u8(4);
+                self.u64(elements.len() as u64);
+                for element in elements {
+                    self.value(element);
+                }
+            }
+            Value::Enum(name) => {
+                self.u8(5);
+                self.str(name);
+            }
+            Value::String(string) => {
+                self.u8(6);
+                self.str(string);
+            }
+            Value::EnumRaw(raw) => {
+                self.u8(8);
+                self.u64(*raw);
+            }
+            Value::Bytes(bytes) => {
+                self.u8(7);
+                self.u64(bytes.len() as u64);
+                self.bytes(bytes);
+            }
+            Value::Map(map) => {
+                self.u8(9);
+                self.u64(map.len() as u64);
+                for (name, field) in map {
+                    self.str(name);
+                    self.value(field);
+                }
+            }
+        }
+    }
+}
+
+impl SomeIPType {
+    /// A hash of the whole definition, including field and variant names.
+    ///
+    /// Structurally equal definitions have equal fingerprints. Named types
+    /// are hashed by name, not by the definition they refer to.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = Fnv1a(FNV_OFFSET_BASIS);
+        hasher.def(self);
+        hasher.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(y_name: &str) -> SomeIPType {
+        SomeIPType::Struct {
+            fields: vec![
+                ("x".to_string(), SomeIPType::UInt16),
+                (y_name.to_string(), SomeIPType::UInt16),
+            ],
+            alignment: 1,
+            length_width: None,
+            layout: StructLayout::Sequential,
+        }
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        assert_eq!(point("y").fingerprint(), point("y").fingerprint());
+        assert_ne!(point("y").fingerprint(), point("z").fingerprint());
+        assert_ne!(
+            point("y").fingerprint(),
+            SomeIPType::StaticArray {
+                length: 1,
+                element: Box::new(point("y")),
+            }
+            .fingerprint()
+        );
+    }
+}
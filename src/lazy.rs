@@ -0,0 +1,286 @@
+//! On demand decoding of single fields, e.g. for a UI that expands fields as
+//! the user clicks on them.
+
+use std::collections::HashMap;
+
+use nom::error::ErrorKind;
+
+use crate::{
+    DecodeOptions, DecodeState, Error, InnerError, LengthUnit, SomeIPType, StructLayout, Value,
+    decode_value, someip_dynamic_length,
+};
+
+/// A step of a path as accepted by [`Value::get_path`].
+enum Step<'p> {
+    Field(&'p str),
+    Index(usize),
+}
+
+/// Splits `path` into steps, each with the length of the path up to and
+/// including it.
+fn steps(path: &str) -> Option<Vec<(Step<'_>, usize)>> {
+    let mut steps = Vec::new();
+    let mut start = 0;
+    for segment in path.split('.') {
+        let name_len = segment.find('[').unwrap_or(segment.len());
+        let mut end = start + name_len;
+        if name_len > 0 {
+            steps.push((Step::Field(&segment[..name_len]), end));
+        }
+        let mut indices = &segment[name_len..];
+        while let Some(rest) = indices.strip_prefix('[') {
+            let (index, rest) = rest.split_once(']')?;
+            end += index.len() + 2;
+            steps.push((Step::Index(index.parse().ok()?), end));
+            indices = rest;
+        }
+        if !indices.is_empty() {
+            return None;
+        }
+        start += segment.len() + 1;
+    }
+    Some(steps)
+}
+
+/// A payload that is decoded one subtree at a time.
+///
+/// Fields are located by skipping their predecessors: members of a fixed
+/// size are skipped without decoding them, others are decoded once and
+/// cached. Paths reaching into types that cannot be located this way, such
+/// as byte-counted arrays, decode the innermost locatable value as a whole.
+pub struct LazyValue<'a> {
+    input: &'a [u8],
+    def: &'a SomeIPType,
+    options: DecodeOptions,
+    /// Decoded subtrees by path.
+    cache: HashMap<String, Value>,
+    /// End offset of decoded subtrees by path.
+    ends: HashMap<String, usize>,
+    decoded_nodes: usize,
+}
+
+impl<'a> LazyValue<'a> {
+    pub fn new(input: &'a [u8], def: &'a SomeIPType) -> Self {
+        LazyValue {
+            input,
+            def,
+            options: DecodeOptions::default(),
+            cache: HashMap::new(),
+            ends: HashMap::new(),
+            decoded_nodes: 0,
+        }
+    }
+
+    /// The number of `Value` nodes decoded so far.
+    pub fn decoded_nodes(&self) -> usize {
+        self.decoded_nodes
+    }
+
+    /// The value at `path`, written as for [`Value::get_path`].
+    pub fn get(&mut self, path: &str) -> Result<&Value, Error<'a>> {
+        let invalid_path = || Error::new(self.input, InnerError::InvalidPath(path.to_string()));
+        let steps = steps(path).ok_or_else(invalid_path)?;
+        let (def, offset, located) = self.locate(path, &steps)?;
+        let (prefix, rest) = path.split_at(located);
+        if !self.cache.contains_key(prefix) {
+            let (end, value) = self.decode(def, offset)?;
+            self.ends.insert(prefix.to_string(), end);
+            self.cache.insert(prefix.to_string(), value);
+        }
+        let rest = rest.strip_prefix('.').unwrap_or(rest);
+        let value = &self.cache[prefix];
+        if rest.is_empty() {
+            return Ok(value);
+        }
+        value.get_path(rest).ok_or_else(invalid_path)
+    }
+
+    /// The type and offset of the innermost value along `steps` that can be
+    /// located without decoding it, and the length of its path.
+    fn locate(
+        &mut self,
+        path: &str,
+        steps: &[(Step, usize)],
+    ) -> Result<(&'a SomeIPType, usize, usize), Error<'a>> {
+        let invalid_path = || Error::new(self.input, InnerError::InvalidPath(path.to_string()));
+        let mut def = self.def;
+        let mut offset = 0;
+        let mut located = 0;
+        for (step, end) in steps {
+            match (step, def) {
+                (
+                    Step::Field(name),
+                    SomeIPType::Struct {
+                        fields,
+                        alignment,
+                        length_width: None,
+                        layout: StructLayout::Sequential,
+                    },
+                ) => {
+                    let alignment = usize::from((*alignment).max(1));
+                    let mut found = None;
+                    for (field, field_def) in fields {
+                        offset += (alignment - offset % alignment) % alignment;
+                        if field == name {
+                            found = Some(field_def);
+                            break;
+                        }
+                        let sibling = match located {
+                            0 => field.clone(),
+                            _ => format!("{}.{field}", &path[..located]),
+                        };
+                        offset = self.end(field_def, offset, sibling)?;
+                    }
+                    def = found.ok_or_else(invalid_path)?;
+                }
+                (Step::Index(index), SomeIPType::StaticArray { length, element }) => {
+                    let Some(stride) = element.fixed_size() else {
+                        break;
+                    };
+                    if *index >= *length as usize {
+                        return Err(invalid_path());
+                    }
+                    offset += index * stride;
+                    def = element;
+                }
+                (
+                    Step::Index(index),
+                    SomeIPType::DynamicArray {
+                        length_width,
+                        element,
+                        length_unit: LengthUnit::Elements,
+                        min_length: None,
+                        max_length: None,
+                    },
+                ) => {
+                    let Some(stride) = element.fixed_size() else {
+                        break;
+                    };
+                    let (rest, length) =
+                        someip_dynamic_length(self.at(offset)?, *length_width, true)
+                            .map_err(|e| self.error(e))?;
+                    if *index as u64 >= length {
+                        return Err(invalid_path());
+                    }
+                    offset = self.input.len() - rest.len() + index * stride;
+                    def = element;
+                }
+                _ => break,
+            }
+            located = *end;
+        }
+        Ok((def, offset, located))
+    }
+
+    /// The offset after the value of type `def` at `offset`, decoding and
+    /// caching it under `path` if its size is not fixed.
+    fn end(
+        &mut self,
+        def: &'a SomeIPType,
+        offset: usize,
+        path: String,
+    ) -> Result<usize, Error<'a>> {
+        if let Some(size) = def.fixed_size() {
+            return Ok(offset + size);
+        }
+        if let Some(end) = self.ends.get(&path) {
+            return Ok(*end);
+        }
+        let (end, value) = self.decode(def, offset)?;
+        self.ends.insert(path.clone(), end);
+        self.cache.insert(path, value);
+        Ok(end)
+    }
+
+    fn decode(&mut self, def: &SomeIPType, offset: usize) -> Result<(usize, Value), Error<'a>> {
+        let mut state = DecodeState::new(&self.options, true, self.input);
+        let result = decode_value(self.at(offset)?, def, &mut state);
+        self.decoded_nodes += state.nodes;
+        let (rest, value) = result.map_err(|e| self.error(e))?;
+        Ok((self.input.len() - rest.len(), value))
+    }
+
+    fn at(&self, offset: usize) -> Result<&'a [u8], Error<'a>> {
+        self.input.get(offset..).ok_or_else(|| {
+            Error::new(
+                &self.input[self.input.len()..],
+                InnerError::Nom(ErrorKind::Eof),
+            )
+        })
+    }
+
+    fn error(&self, error: nom::Err<Error<'a>>) -> Error<'a> {
+        match error {
+            nom::Err::Error(e) | nom::Err::Failure(e) => e,
+            nom::Err::Incomplete(_) => Error::new(
+                &self.input[self.input.len()..],
+                InnerError::Nom(ErrorKind::Eof),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lazy_value_get() {
+        let mut bytes: Vec<u8> = vec![0x0; 400];
+        bytes[12..16].copy_from_slice(&[0x0, 0x0, 0x0, 0x2a]);
+        bytes.extend([0x12, 0x34, 0x0, 0x0, 0x0, 0x7, 0x2, 0xab, 0xcd]);
+        let def = SomeIPType::Struct {
+            fields: vec![
+                (
+                    "samples".to_string(),
+                    SomeIPType::StaticArray {
+                        length: 100,
+                        element: Box::new(SomeIPType::UInt32),
+                    },
+                ),
+                (
+                    "position".to_string(),
+                    SomeIPType::Struct {
+                        fields: vec![
+                            ("x".to_string(), SomeIPType::UInt16),
+                            ("y".to_string(), SomeIPType::UInt32),
+                        ],
+                        alignment: 1,
+                        length_width: None,
+                        layout: StructLayout::Sequential,
+                    },
+                ),
+                (
+                    "tail".to_string(),
+                    SomeIPType::DynamicArray {
+                        length_width: 8,
+                        element: Box::new(SomeIPType::UInt8),
+                        length_unit: LengthUnit::Bytes,
+                        min_length: None,
+                        max_length: None,
+                    },
+                ),
+            ],
+            alignment: 1,
+            length_width: None,
+            layout: StructLayout::Sequential,
+        };
+        let mut lazy = LazyValue::new(&bytes, &def);
+
+        assert_eq!(lazy.get("position.y"), Ok(&Value::UInt(0x7)));
+        assert_eq!(lazy.decoded_nodes(), 1);
+        assert_eq!(lazy.get("samples[3]"), Ok(&Value::UInt(0x2a)));
+        assert_eq!(lazy.decoded_nodes(), 2);
+        assert_eq!(lazy.get("tail[1]"), Ok(&Value::UInt(0xcd)));
+        assert_eq!(lazy.decoded_nodes(), 5);
+        assert_eq!(lazy.get("tail[0]"), Ok(&Value::UInt(0xab)));
+        assert_eq!(lazy.decoded_nodes(), 5);
+        assert!(matches!(
+            lazy.get("position.z"),
+            Err(Error {
+                error: InnerError::InvalidPath(_),
+                ..
+            })
+        ));
+    }
+}
@@ -1,34 +1,277 @@
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
 use nom::{
-    IResult, Input, Parser,
-    error::{Error as NomError, ErrorKind, ParseError},
-    number::{
-        streaming::{be_i8, be_i16, be_i32, be_i64},
-        streaming::{be_u8, be_u16, be_u32, be_u64},
-    },
+    IResult, Parser,
+    error::{ErrorKind, ParseError},
 };
 
-/// client id / session id
-type RequestId = u32;
+pub mod application_error;
+#[cfg(feature = "arxml")]
+pub mod arxml;
+pub mod builder;
+pub mod catalog;
+#[cfg(feature = "tokio")]
+pub mod codec;
+pub mod complete;
+pub mod convert;
+pub mod correlation;
+pub mod decoder;
+pub mod e2e;
+pub mod encode;
+#[cfg(feature = "fibex")]
+pub mod fibex;
+pub mod lazy;
+pub mod migrate;
+pub mod registry;
+pub mod schema;
+pub mod sd;
+pub mod tp;
+pub mod value_ref;
+pub mod visit;
+
+mod fingerprint;
+#[cfg(any(feature = "arxml", feature = "fibex"))]
+mod xml;
+
+use registry::TypeRegistry;
+
+/// Runs a `nom::number` parser in its streaming or complete flavour.
+macro_rules! number {
+    ($parser:ident, $input:expr, $complete:expr) => {
+        if $complete {
+            nom::number::complete::$parser($input)
+        } else {
+            nom::number::streaming::$parser($input)
+        }
+    };
+}
+
+/// Takes `count` bytes, either streaming or complete.
+fn take_bytes<'a>(
+    input: &'a [u8],
+    count: impl nom::ToUsize,
+    complete: bool,
+) -> IResult<&'a [u8], &'a [u8], Error<'a>> {
+    if complete {
+        nom::bytes::complete::take(count).parse(input)
+    } else {
+        nom::bytes::streaming::take(count).parse(input)
+    }
+}
+
+/// The SOME/IP protocol version this crate implements.
+pub const PROTOCOL_VERSION: u8 = 0x01;
+
 type InterfaceVersion = u8;
 type ProtocolVersion = u8;
-type ReturnCode = u8;
 type ClientId = u16;
 type SessionId = u16;
-type MessageId = u16;
 
-#[derive(Debug, PartialEq)]
+/// Identifies a service, e.g. in [`SomeIPHeader::service_id`]. Displayed
+/// in hex, as service ids are usually written.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct ServiceId(pub u16);
+
+/// Identifies a method or, with the most significant bit set, an event of
+/// a service. Displayed in hex.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct MethodId(pub u16);
+
+/// Implements the conversions from and to `u16` and hex `Display` for an id.
+macro_rules! id_conversions {
+    ($($id:ident),*) => {
+        $(
+            impl From<u16> for $id {
+                fn from(value: u16) -> Self {
+                    $id(value)
+                }
+            }
+
+            impl From<$id> for u16 {
+                fn from(value: $id) -> Self {
+                    value.0
+                }
+            }
+
+            impl PartialEq<u16> for $id {
+                fn eq(&self, other: &u16) -> bool {
+                    self.0 == *other
+                }
+            }
+
+            impl fmt::Display for $id {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    write!(f, "{:#06x}", self.0)
+                }
+            }
+        )*
+    };
+}
+
+id_conversions!(ServiceId, MethodId);
+
+/// The method a message belongs to, e.g. as key of a dispatch table.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct MessageKey {
+    pub service_id: ServiceId,
+    pub method_id: MethodId,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SomeIPHeader {
-    pub service_id: u16,
-    pub method_id: u16,
+    pub service_id: ServiceId,
+    pub method_id: MethodId,
     pub length: u32,
-    pub client_id: u16,
-    pub session_id: u16,
+    pub client_id: ClientId,
+    pub session_id: SessionId,
     pub protocol_version: ProtocolVersion,
     pub interface_version: InterfaceVersion,
     pub message_type: SomeIPMessageType,
     pub return_code: ReturnCode,
 }
 
+/// Read-only access to every field, for callers that should keep working
+/// should the fields stop being public.
+impl SomeIPHeader {
+    pub fn service_id(&self) -> ServiceId {
+        self.service_id
+    }
+
+    pub fn method_id(&self) -> MethodId {
+        self.method_id
+    }
+
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+
+    pub fn client_id(&self) -> u16 {
+        self.client_id
+    }
+
+    pub fn session_id(&self) -> u16 {
+        self.session_id
+    }
+
+    pub fn protocol_version(&self) -> u8 {
+        self.protocol_version
+    }
+
+    pub fn interface_version(&self) -> u8 {
+        self.interface_version
+    }
+
+    pub fn message_type(&self) -> SomeIPMessageType {
+        self.message_type
+    }
+
+    pub fn return_code(&self) -> ReturnCode {
+        self.return_code
+    }
+}
+
+impl SomeIPHeader {
+    pub fn is_request(&self) -> bool {
+        matches!(
+            self.message_type,
+            SomeIPMessageType::Request() | SomeIPMessageType::TPRequest()
+        )
+    }
+
+    pub fn is_response(&self) -> bool {
+        matches!(
+            self.message_type,
+            SomeIPMessageType::Response() | SomeIPMessageType::Error()
+        )
+    }
+
+    /// The service and method, which a response shares with its request.
+    pub fn key(&self) -> MessageKey {
+        MessageKey {
+            service_id: self.service_id,
+            method_id: self.method_id,
+        }
+    }
+
+    /// Method ids with the most significant bit set identify events.
+    pub fn is_event(&self) -> bool {
+        self.method_id.0 & 0x8000 != 0
+    }
+
+    /// The lower 15 bits of the method id, if it identifies an event.
+    pub fn event_id(&self) -> Option<u16> {
+        self.is_event().then_some(self.method_id.0 & 0x7fff)
+    }
+
+    /// The length of the payload, which `length` covers together with the
+    /// 8 header bytes following it. `None` if `length` is below 8.
+    pub fn payload_length(&self) -> Option<u32> {
+        self.length.checked_sub(8)
+    }
+
+    /// Whether this is a magic cookie, which senders insert into TCP streams
+    /// so receivers can find message boundaries again: method 0x0000 from a
+    /// client, 0x8000 from a server, of service 0xffff with request id
+    /// 0xdeadbeef and no payload. Magic cookies belong to no service and
+    /// should be skipped.
+    pub fn is_magic_cookie(&self) -> bool {
+        let message_type = match self.method_id.0 {
+            0x0000 => SomeIPMessageType::RequestNoReturn(),
+            0x8000 => SomeIPMessageType::Notification(),
+            _ => return false,
+        };
+        self.service_id == 0xffff
+            && self.length == 8
+            && self.client_id == 0xdead
+            && self.session_id == 0xbeef
+            && self.protocol_version == PROTOCOL_VERSION
+            && self.interface_version == 0x01
+            && self.message_type == message_type
+            && self.return_code == ReturnCode::Ok
+    }
+
+    /// Session id 0 means the sender does not use session handling.
+    pub fn session_handling_active(&self) -> bool {
+        self.session_id != 0
+    }
+
+    /// Whether this is the response (or error) to `request`, i.e. it answers
+    /// the same method from the same client. Session ids are only compared
+    /// when the request uses session handling.
+    pub fn is_response_to(&self, request: &SomeIPHeader) -> bool {
+        self.is_response()
+            && request.is_request()
+            && self.service_id == request.service_id
+            && self.method_id == request.method_id
+            && self.client_id == request.client_id
+            && (!request.session_handling_active() || self.session_id == request.session_id)
+    }
+
+    /// The header of a reply to this request: a `Response` if `return_code`
+    /// is `Ok`, an `Error` otherwise. The `length` covers an empty payload.
+    pub fn reply(&self, return_code: ReturnCode) -> SomeIPHeader {
+        let message_type = match return_code {
+            ReturnCode::Ok => SomeIPMessageType::Response(),
+            _ => SomeIPMessageType::Error(),
+        };
+        SomeIPHeader {
+            length: 8,
+            message_type,
+            return_code,
+            ..self.clone()
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Error<'a> {
     pub input: &'a [u8],
@@ -38,12 +281,126 @@ pub struct Error<'a> {
 #[derive(Clone, Debug, PartialEq)]
 pub enum InnerError {
     Nom(ErrorKind),
+    /// The selector of an `EnumSelectedStruct` did not decode to an enum variant.
+    InvalidSelector,
+    /// No struct is mapped to the decoded enum variant.
+    UnmappedVariant(String),
+    /// The header `length` is smaller than the 8 bytes it always covers.
+    InvalidLength(u32),
+    /// Decoding would create more values than `DecodeOptions::max_nodes` allows.
+    NodeLimitExceeded,
+    /// An element of a byte-counted array extends past the array's length.
+    ArrayBoundaryCrossed,
+    /// An enclosing length does not match the length of what it encloses.
+    LengthMismatch {
+        declared: u32,
+        actual: u32,
+    },
+    /// A string's bytes are not valid in its coding.
+    InvalidString,
+    /// A string's byte length does not fit its coding, e.g. odd for UTF-16.
+    InvalidStringLength(u32),
+    /// The type cannot be decoded by the called parser.
+    UnsupportedType,
+    /// A bit width the type cannot be stored in.
+    InvalidBitWidth(u16),
+    /// A `SomeIPType::Ref` names a type that is not registered.
+    UnknownType(String),
+    /// Types nest deeper than `DecodeOptions::max_depth` allows.
+    DepthLimitExceeded,
+    /// A header carries a protocol version other than [`PROTOCOL_VERSION`].
+    UnsupportedProtocolVersion(u8),
+    /// An unsigned integer does not fit into `Value::Int`.
+    IntegerOverflow(u64),
+    /// A message type with bits set that SOME/IP leaves reserved.
+    ReservedMessageTypeBits(u8),
+    /// A closed enum's discriminant does not belong to any of its variants.
+    UnknownDiscriminant(u64),
+    /// A path does not lead to a value.
+    InvalidPath(String),
+    /// A length or type field width other than 8, 16, 32 or 64 bits.
+    InvalidLengthWidth(u8),
+    /// The options of an SD message do not add up to the options length.
+    SdLengthMismatch {
+        declared: u32,
+        actual: u32,
+    },
+    /// An SD endpoint option with a transport protocol other than TCP or UDP.
+    UnknownL4Proto(u8),
+    /// An integer outside of the range its `SomeIPType::Constrained` allows.
+    ValueOutOfRange(i128),
+    /// A `UInt128` too large for the `i128` range of any
+    /// `SomeIPType::Constrained`.
+    UInt128OutOfRange(u128),
+    /// The input ends before the terminator of a `SomeIPType::CString`.
+    MissingTerminator,
+    /// A header `length` disagrees with the size of a fixed size payload type.
+    SchemaLengthMismatch {
+        payload_length: u32,
+        schema_size: usize,
+    },
+    /// A `SomeIPType::Bool` byte other than 0 or 1.
+    InvalidBool(u8),
+    /// Payload bytes left over after the value, with
+    /// `MessageOptions::strict_trailing`.
+    TrailingBytes(u32),
+    /// A `SomeIPType::DynamicArray` with fewer or more elements than its
+    /// `min_length` and `max_length` allow.
+    ArrayLengthConstraint(u64),
 }
 
 impl<'a> Error<'a> {
     pub fn new(input: &'a [u8], error: InnerError) -> Error<'a> {
         Error { input, error }
     }
+
+    /// Position of the error within `original`, the input the failed parse
+    /// started on. `None` if the error does not point into `original`.
+    pub fn offset(&self, original: &[u8]) -> Option<usize> {
+        let start = original.as_ptr() as usize;
+        let position = self.input.as_ptr() as usize;
+        let offset = position.checked_sub(start)?;
+        (offset + self.input.len() <= original.len()).then_some(offset)
+    }
+
+    /// A hex dump of `original` around the error, 16 bytes per row, with a
+    /// caret under the byte the error points at, for diagnostics.
+    pub fn annotated_dump(&self, original: &[u8]) -> String {
+        use fmt::Write;
+
+        const ROW: usize = 16;
+        let mut out = String::new();
+        let Some(offset) = self.offset(original) else {
+            writeln!(out, "{} (outside of the input)", self.error).unwrap();
+            return out;
+        };
+        let row = offset / ROW;
+        // the row before gives context, the one after shows what follows
+        let last_row = original.len().saturating_sub(1) / ROW;
+        for r in row.saturating_sub(1)..=(row + 1).min(last_row).max(row) {
+            let start = r * ROW;
+            let bytes = &original[start.min(original.len())..(start + ROW).min(original.len())];
+            write!(out, "{start:08x} ").unwrap();
+            for byte in bytes {
+                write!(out, " {byte:02x}").unwrap();
+            }
+            out.push('\n');
+            if r == row {
+                let column = 10 + (offset - start) * 3;
+                let at = match original.get(offset) {
+                    Some(byte) => format!("byte {byte:#04x}"),
+                    None => "end of input".to_string(),
+                };
+                writeln!(
+                    out,
+                    "{:column$}^^ offset {offset:#x} ({at}): {}",
+                    "", self.error
+                )
+                .unwrap();
+            }
+        }
+        out
+    }
 }
 
 impl<'a> ParseError<&'a [u8]> for Error<'a> {
@@ -54,7 +411,7 @@ impl<'a> ParseError<&'a [u8]> for Error<'a> {
         }
     }
 
-    fn append(input: &'a [u8], kind: ErrorKind, other: Self) -> Self {
+    fn append(input: &'a [u8], kind: ErrorKind, _other: Self) -> Self {
         Error {
             input,
             error: InnerError::Nom(kind),
@@ -62,319 +419,4307 @@ impl<'a> ParseError<&'a [u8]> for Error<'a> {
     }
 }
 
-pub fn some_ip_header(input: &[u8]) -> IResult<&[u8], SomeIPHeader, Error> {
-    let (i1, service_id) = be_u16(input)?;
-    let (i2, method_id) = be_u16(i1)?;
-    let (i3, length) = be_u32(i2)?;
-    let (i4, client_id) = be_u16(i3)?;
-    let (i5, session_id) = be_u16(i4)?;
-    let (i6, protocol_version) = be_u8(i5)?;
-    let (i7, interface_version) = be_u8(i6)?;
-    let (i8, message_type) = be_u8(i7)?;
-    let (i9, return_code) = be_u8(i8)?;
+impl fmt::Display for InnerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InnerError::Nom(kind) => write!(f, "parse error: {}", kind.description()),
+            InnerError::InvalidSelector => write!(f, "selector is not an enum variant"),
+            InnerError::UnmappedVariant(variant) => {
+                write!(f, "no struct mapped to enum variant {variant}")
+            }
+            InnerError::InvalidLength(length) => {
+                write!(f, "invalid length {length}, must be at least 8")
+            }
+            InnerError::NodeLimitExceeded => write!(f, "value node limit exceeded"),
+            InnerError::ArrayBoundaryCrossed => {
+                write!(f, "array element extends past the array length")
+            }
+            InnerError::LengthMismatch { declared, actual } => {
+                write!(f, "length mismatch: declared {declared}, actual {actual}")
+            }
+            InnerError::InvalidString => write!(f, "string is not valid in its coding"),
+            InnerError::InvalidStringLength(length) => {
+                write!(f, "string length {length} does not fit its coding")
+            }
+            InnerError::UnsupportedType => write!(f, "type not supported by this parser"),
+            InnerError::InvalidBitWidth(width) => write!(f, "invalid bit width {width}"),
+            InnerError::UnknownType(name) => write!(f, "unknown type {name:?}"),
+            InnerError::DepthLimitExceeded => write!(f, "type nesting limit exceeded"),
+            InnerError::UnsupportedProtocolVersion(version) => {
+                write!(f, "unsupported protocol version {version:#04x}")
+            }
+            InnerError::IntegerOverflow(value) => write!(f, "integer {value} overflows i64"),
+            InnerError::ReservedMessageTypeBits(message_type) => {
+                write!(f, "message type {message_type:#04x} has reserved bits set")
+            }
+            InnerError::UnknownDiscriminant(value) => {
+                write!(f, "enum discriminant {value} has no variant")
+            }
+            InnerError::InvalidPath(path) => write!(f, "no value at path {path:?}"),
+            InnerError::InvalidLengthWidth(width) => write!(f, "invalid length width {width}"),
+            InnerError::SdLengthMismatch { declared, actual } => write!(
+                f,
+                "SD options length {declared} does not match options of {actual} bytes"
+            ),
+            InnerError::MissingTerminator => write!(f, "string is missing its terminator"),
+            InnerError::ValueOutOfRange(value) => {
+                write!(f, "value {value} is outside of its allowed range")
+            }
+            InnerError::UInt128OutOfRange(value) => {
+                write!(f, "value {value} is outside of its allowed range")
+            }
+            InnerError::UnknownL4Proto(proto) => {
+                write!(f, "unknown L4 protocol {proto:#04x}, expected TCP or UDP")
+            }
+            InnerError::SchemaLengthMismatch {
+                payload_length,
+                schema_size,
+            } => write!(
+                f,
+                "payload length {payload_length} does not match schema size {schema_size}"
+            ),
+            InnerError::InvalidBool(value) => write!(f, "invalid boolean {value:#04x}"),
+            InnerError::TrailingBytes(count) => {
+                write!(f, "{count} bytes left over after the payload")
+            }
+            InnerError::ArrayLengthConstraint(length) => {
+                write!(f, "array of {length} elements violates its length bounds")
+            }
+        }
+    }
+}
+
+impl std::error::Error for InnerError {}
+
+impl fmt::Display for Error<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({} bytes left)", self.error, self.input.len())
+    }
+}
+
+impl std::error::Error for Error<'_> {}
+
+/// An [`Error`] that no longer borrows the input, e.g. to be returned as a
+/// `Box<dyn std::error::Error>`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OwnedError {
+    /// Number of input bytes left when the error occurred.
+    pub remaining: usize,
+    pub error: InnerError,
+}
+
+impl From<Error<'_>> for OwnedError {
+    fn from(error: Error<'_>) -> Self {
+        OwnedError {
+            remaining: error.input.len(),
+            error: error.error,
+        }
+    }
+}
+
+impl fmt::Display for OwnedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({} bytes left)", self.error, self.remaining)
+    }
+}
+
+impl std::error::Error for OwnedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+pub fn some_ip_header(input: &[u8]) -> IResult<&[u8], SomeIPHeader, Error<'_>> {
+    header(input, false)
+}
+
+/// Like [`some_ip_header`], but rejects any protocol version other than the
+/// only standardized one, 0x01.
+pub fn some_ip_header_strict(input: &[u8]) -> IResult<&[u8], SomeIPHeader, Error<'_>> {
+    let (i1, header) = header(input, false)?;
+    if header.protocol_version != PROTOCOL_VERSION {
+        return Err(nom::Err::Error(Error::new(
+            &input[12..],
+            InnerError::UnsupportedProtocolVersion(header.protocol_version),
+        )));
+    }
+    Ok((i1, header))
+}
+
+/// Bits of the message type byte besides the response (0x80) and TP (0x20)
+/// flags and the type in the lowest two bits.
+const RESERVED_MESSAGE_TYPE_BITS: u8 = 0x5c;
+
+/// Switches applied while parsing a header.
+#[derive(Clone, Debug, Default)]
+pub struct HeaderOptions {
+    /// Reject message types with reserved bits set, e.g. 0x40, instead of
+    /// parsing them as `SomeIPMessageType::Unknown`.
+    pub reject_reserved_message_type_bits: bool,
+}
+
+pub fn some_ip_header_with_options<'a>(
+    input: &'a [u8],
+    options: &HeaderOptions,
+) -> IResult<&'a [u8], SomeIPHeader, Error<'a>> {
+    let (i1, header) = header(input, false)?;
+    let message_type = input[14];
+    if options.reject_reserved_message_type_bits && message_type & RESERVED_MESSAGE_TYPE_BITS != 0 {
+        return Err(nom::Err::Error(Error::new(
+            &input[14..],
+            InnerError::ReservedMessageTypeBits(message_type),
+        )));
+    }
+    Ok((i1, header))
+}
+
+fn header(input: &[u8], complete: bool) -> IResult<&[u8], SomeIPHeader, Error<'_>> {
+    let (i1, service_id) = number!(be_u16, input, complete)?;
+    let (i2, method_id) = number!(be_u16, i1, complete)?;
+    let (i3, length) = number!(be_u32, i2, complete)?;
+    let (i4, client_id) = number!(be_u16, i3, complete)?;
+    let (i5, session_id) = number!(be_u16, i4, complete)?;
+    let (i6, protocol_version) = number!(be_u8, i5, complete)?;
+    let (i7, interface_version) = number!(be_u8, i6, complete)?;
+    let (i8, message_type) = number!(be_u8, i7, complete)?;
+    let (i9, return_code) = number!(be_u8, i8, complete)?;
     Ok((
         i9,
         SomeIPHeader {
-            service_id,
-            method_id,
+            service_id: ServiceId(service_id),
+            method_id: MethodId(method_id),
             length,
             client_id,
             session_id,
             protocol_version,
             interface_version,
             message_type: message_type.into(),
-            return_code,
+            return_code: return_code.into(),
         },
     ))
 }
 
-pub fn some_ip_value<'a>(
+/// Parses a header and decodes its payload with `payload_type`.
+///
+/// The payload is bounded by the header `length`, so decoding never reads
+/// into the following message; a payload shorter than `payload_type`
+/// requires is reported as an error rather than `Incomplete`.
+pub fn some_ip_message<'a>(
     input: &'a [u8],
-    def: &'a SomeIPType,
-) -> IResult<&'a [u8], Value, Error<'a>> {
-    let (i1, value) = match def {
-        SomeIPType::UInt8 => {
-            let (i1, val) = be_u8(input)?;
-            (i1, Value::UInt(val.into()))
-        }
-        SomeIPType::UInt16 => {
-            let (i1, val) = be_u16(input)?;
-            (i1, Value::UInt(val.into()))
-        }
-        SomeIPType::UInt32 => {
-            let (i1, val) = be_u32(input)?;
-            (i1, Value::UInt(val.into()))
-        }
-        SomeIPType::UInt64 => {
-            let (i1, val) = be_u64(input)?;
-            (i1, Value::UInt(val))
-        }
-        SomeIPType::SInt8 => {
-            let (i1, val) = be_i8(input)?;
-            (i1, Value::Int(val.into()))
-        }
-        SomeIPType::SInt16 => {
-            let (i1, val) = be_i16(input)?;
-            (i1, Value::Int(val.into()))
-        }
-        SomeIPType::SInt32 => {
-            let (i1, val) = be_i32(input)?;
-            (i1, Value::Int(val.into()))
-        }
-        SomeIPType::SInt64 => {
-            let (i1, val) = be_i64(input)?;
-            (i1, Value::Int(val))
-        }
-        SomeIPType::Struct { fields } => someip_struct(input, fields),
-        SomeIPType::DynamicArray {
-            length_width,
-            element,
-        } => {
-            let (i1, length) = someip_dynamic_length(input, length_width)?;
-
-            someip_array(i1, element, length)?
-        }
-        SomeIPType::StaticArray { length, element } => {
-            someip_array(input, element, *length as u64)?
-        }
-        SomeIPType::Enum { variants } => {
-            let (i1, variant) = be_u8(input)?;
-
-            (
-                i1,
-                Value::Enum(
-                    variants
-                        .iter()
-                        .find(|(i, _)| *i == variant.into())
-                        .unwrap()
-                        .1
-                        .clone(),
-                ),
-            )
-        }
-        SomeIPType::StaticString { length, coding: _ } => {
-            let (i1, str_bytes) = nom::bytes::streaming::take(*length).parse(input)?;
-            let str = String::from_utf8(str_bytes.to_vec()).unwrap();
-            (i1, Value::String(str))
-        }
-        SomeIPType::DynamicString {
-            length_width,
-            coding: _,
-        } => {
-            let (i1, length) = someip_dynamic_length(input, length_width)?;
-            let (i2, str_bytes) = nom::bytes::streaming::take(length).parse(i1)?;
-            let str = String::from_utf8(str_bytes.to_vec()).unwrap();
-            (i2, Value::String(str))
-        }
-
-        _ => {
-            panic!("not implemented")
-        }
-    };
-    Ok((i1, value))
-    //Ok((input, Value::Int(8)))
+    payload_type: &SomeIPType,
+) -> IResult<&'a [u8], (SomeIPHeader, Value), Error<'a>> {
+    message(input, payload_type, false)
 }
 
-fn someip_dynamic_length<'a>(
+/// Like [`some_ip_message`], but for payload types of a fixed size first
+/// checks that the header `length` covers exactly that size, as a mismatch
+/// means the message is corrupt. Other payload types are not checked.
+pub fn some_ip_message_validated<'a>(
     input: &'a [u8],
-    length_width: &'a u8,
-) -> Result<(&'a [u8], u64), nom::Err<Error<'a>>> {
-    let (i1, length) = match length_width {
-        8 => {
-            let (input, length) = be_u8(input)?;
-            (input, length as u64)
-        }
-        16 => {
-            let (input, length) = be_u16(input)?;
-            (input, length as u64)
-        }
-        32 => {
-            let (input, length) = be_u32(input)?;
-            (input, length as u64)
-        }
-        64 => {
-            let (input, length) = be_u64(input)?;
-            (input, length)
-        }
-        _ => {
-            panic!("invalid length width")
-        }
-    };
-    Ok((i1, length))
-}
-
-fn someip_array<'a>(
-    mut input: &'a [u8],
-    element: &'a SomeIPType,
-    length: u64,
-) -> Result<(&'a [u8], Value), nom::Err<Error<'a>>> {
-    let mut elements = Vec::new();
-    for _ in 0..length {
-        let (new_input, value) = some_ip_value(input, element)?;
-        input = new_input;
-        elements.push(value);
+    payload_type: &SomeIPType,
+) -> IResult<&'a [u8], (SomeIPHeader, Value), Error<'a>> {
+    let (_, header) = header(input, false)?;
+    if let (Some(schema_size), Some(payload_length)) =
+        (payload_type.fixed_size(), header.payload_length())
+        && payload_length as usize != schema_size
+    {
+        return Err(nom::Err::Error(Error::new(
+            &input[4..],
+            InnerError::SchemaLengthMismatch {
+                payload_length,
+                schema_size,
+            },
+        )));
     }
-    Ok((input, Value::Array(elements)))
+    message(input, payload_type, false)
 }
 
-fn someip_struct<'a>(input: &'a [u8], fields: &'a [(String, SomeIPType)]) -> (&'a [u8], Value) {
-    let mut i1 = input;
-    let fields = fields
-        .iter()
-        .map(|(name, def)| {
-            let (new_input, value) = some_ip_value(i1, def).unwrap();
-            i1 = new_input;
-            (name.clone(), value)
-        })
-        .collect();
-    (i1, Value::Struct { fields })
+/// Switches applied while decoding a message.
+#[derive(Clone, Debug, Default)]
+pub struct MessageOptions {
+    /// Reject payload bytes the payload type leaves over instead of
+    /// reporting them as `DecodedMessage::trailing`.
+    pub strict_trailing: bool,
 }
-#[derive(Debug, PartialEq)]
-pub enum SomeIPMessageType {
-    Request(),
-    RequestNoReturn(),
-    Notification(),
-    Response(),
-    Error(),
-    TPRequest(),
-    TPRequestNoReturn(),
-    TPNotification(),
-    Unknown(),
+
+/// A message decoded by [`some_ip_message_with_options`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedMessage<'a> {
+    pub header: SomeIPHeader,
+    pub value: Value,
+    /// Bytes within the header `length` after the value, e.g. padding or
+    /// fields of a newer interface version.
+    pub trailing: &'a [u8],
 }
 
-impl From<u8> for SomeIPMessageType {
-    fn from(value: u8) -> Self {
-        match value {
-            0x00 => Self::Request(),
-            0x01 => Self::RequestNoReturn(),
-            0x02 => Self::Notification(),
-            0x80 => Self::Response(),
-            0x81 => Self::Error(),
-            0x20 => Self::TPRequest(),
-            0x21 => Self::TPRequestNoReturn(),
-            0x22 => Self::TPNotification(),
-            _ => Self::Unknown(),
-        }
+/// Like [`some_ip_message`], but also returns the payload bytes left over
+/// after the value, or rejects them with `options.strict_trailing`.
+pub fn some_ip_message_with_options<'a>(
+    input: &'a [u8],
+    payload_type: &SomeIPType,
+    options: &MessageOptions,
+) -> IResult<&'a [u8], DecodedMessage<'a>, Error<'a>> {
+    let (i1, message) = decoded_message(input, payload_type, false)?;
+    if options.strict_trailing && !message.trailing.is_empty() {
+        return Err(nom::Err::Error(Error::new(
+            message.trailing,
+            InnerError::TrailingBytes(message.trailing.len() as u32),
+        )));
     }
+    Ok((i1, message))
 }
 
-#[derive(Debug, PartialEq)]
-pub struct SomeIPMessage {
-    header: SomeIPHeader,
+fn message<'a>(
+    input: &'a [u8],
+    payload_type: &SomeIPType,
+    complete: bool,
+) -> IResult<&'a [u8], (SomeIPHeader, Value), Error<'a>> {
+    let (i1, DecodedMessage { header, value, .. }) =
+        decoded_message(input, payload_type, complete)?;
+    Ok((i1, (header, value)))
 }
 
-pub struct SomeIPMessageBody {}
+fn decoded_message<'a>(
+    input: &'a [u8],
+    payload_type: &SomeIPType,
+    complete: bool,
+) -> IResult<&'a [u8], DecodedMessage<'a>, Error<'a>> {
+    let (i1, SomeIPMessage { header, payload }) = frame(input, complete)?;
+    let options = DecodeOptions::default();
+    let (trailing, value) = decode_value(
+        payload,
+        payload_type,
+        &mut DecodeState::new(&options, true, payload),
+    )?;
+    Ok((
+        i1,
+        DecodedMessage {
+            header,
+            value,
+            trailing,
+        },
+    ))
+}
 
-pub enum StringCoding {
-    Utf8,
-    Utf16,
+/// Parses a header and slices out the payload its `length` declares, e.g.
+/// to route a message before its payload type is known.
+pub fn some_ip_frame(input: &[u8]) -> IResult<&[u8], SomeIPMessage<'_>, Error<'_>> {
+    frame(input, false)
 }
 
-pub enum SomeIPType {
-    Float32,
-    Float64,
-    SInt8,
-    SInt16,
-    SInt32,
-    SInt64,
-    UInt8,
-    UInt16,
-    UInt32,
-    UInt64,
-    Struct {
-        fields: Vec<(String, SomeIPType)>,
-    },
-    StaticArray {
-        length: u32,
-        element: Box<SomeIPType>,
-    },
-    DynamicArray {
-        length_width: u8,
-        element: Box<SomeIPType>,
-    },
-    Enum {
-        variants: Vec<(u64, String)>,
-    },
-    StaticString {
-        length: u32,
-        coding: Option<StringCoding>,
-    },
-    DynamicString {
-        length_width: u8,
-        coding: Option<StringCoding>,
-    },
+/// Takes the payload `header` declares from `input`, the bytes following
+/// the header, without decoding it, e.g. to step over messages of services
+/// that cannot be decoded.
+pub fn skip_payload<'a>(
+    input: &'a [u8],
+    header: &SomeIPHeader,
+) -> IResult<&'a [u8], &'a [u8], Error<'a>> {
+    let Some(payload_length) = header.payload_length() else {
+        return Err(nom::Err::Error(Error::new(
+            input,
+            InnerError::InvalidLength(header.length),
+        )));
+    };
+    take_bytes(input, payload_length, false)
 }
 
-#[derive(Debug, PartialEq)]
-pub enum Value {
-    Float(f64),
-    UInt(u64),
-    Int(i64),
-    Struct { fields: Vec<(String, Value)> },
-    Array(Vec<Value>),
-    Enum(String),
-    String(String),
+fn frame(input: &[u8], complete: bool) -> IResult<&[u8], SomeIPMessage<'_>, Error<'_>> {
+    let (i1, header) = header(input, complete)?;
+    let Some(payload_length) = header.payload_length() else {
+        return Err(nom::Err::Error(Error::new(
+            input,
+            InnerError::InvalidLength(header.length),
+        )));
+    };
+    let (i2, payload) = take_bytes(i1, payload_length, complete)?;
+    Ok((i2, SomeIPMessage { header, payload }))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*; 
+/// Iterates over messages concatenated back to back, as on a TCP stream.
+///
+/// Each message is framed by its header `length`. Iteration ends at the end
+/// of the input or after the first error; a truncated trailing message
+/// yields `nom::Err::Incomplete`.
+pub fn some_ip_messages(input: &[u8]) -> SomeIPMessages<'_> {
+    SomeIPMessages {
+        input,
+        input_len: input.len(),
+        alignment: 1,
+        failed: false,
+    }
+}
+
+pub struct SomeIPMessages<'a> {
+    input: &'a [u8],
+    /// Length of the whole input, to derive offsets.
+    input_len: usize,
+    alignment: u8,
+    failed: bool,
+}
+
+impl<'a> SomeIPMessages<'a> {
+    /// Skips the padding some senders insert so that each message starts on
+    /// an `alignment` boundary, counted from the start of the input.
+    pub fn aligned(mut self, alignment: u8) -> Self {
+        self.alignment = alignment.max(1);
+        self
+    }
+
+    /// The input not consumed by the messages returned so far.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.input
+    }
+}
+
+impl<'a> Iterator for SomeIPMessages<'a> {
+    type Item = Result<SomeIPMessage<'a>, nom::Err<Error<'a>>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let alignment = usize::from(self.alignment);
+        let offset = self.input_len - self.input.len();
+        let padding = (alignment - offset % alignment) % alignment;
+        // the padding after the last message may be left out
+        self.input = &self.input[padding.min(self.input.len())..];
+        if self.input.is_empty() {
+            return None;
+        }
+        match frame(self.input, false) {
+            Ok((rest, message)) => {
+                self.input = rest;
+                Some(Ok(message))
+            }
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Parses a message preceded by an outer 4 byte length covering exactly the
+/// message, as used by transports that frame SOME/IP once more.
+pub fn someip_length_framed(input: &[u8]) -> IResult<&[u8], SomeIPMessage<'_>, Error<'_>> {
+    let (i1, frame_length) = number!(be_u32, input, false)?;
+    let (i2, frame_bytes) = take_bytes(i1, frame_length, false)?;
+    let (rest, message) = frame(frame_bytes, true)?;
+    if !rest.is_empty() {
+        return Err(nom::Err::Error(Error::new(
+            frame_bytes,
+            InnerError::LengthMismatch {
+                declared: frame_length,
+                actual: message.header.length + 8,
+            },
+        )));
+    }
+    Ok((i2, message))
+}
+
+/// Parses a message preceded by a big endian 8 byte timestamp in
+/// nanoseconds, as some capture tools prepend to every record.
+pub fn someip_with_timestamp(input: &[u8]) -> IResult<&[u8], (u64, SomeIPMessage<'_>), Error<'_>> {
+    let (i1, timestamp_ns) = number!(be_u64, input, false)?;
+    let (i2, message) = frame(i1, false)?;
+    Ok((i2, (timestamp_ns, message)))
+}
+
+/// Limits and switches applied while decoding a payload.
+#[derive(Clone, Debug)]
+pub struct DecodeOptions {
+    /// Maximum number of `Value` nodes a single decode may create, counting
+    /// every struct, array and element. `None` means unlimited.
+    pub max_nodes: Option<usize>,
+    /// Maximum nesting of types, which stops reference cycles between named
+    /// types. `None` means unlimited.
+    pub max_depth: Option<usize>,
+    /// Decode unsigned integers as `Value::Int` too, so every integer has the
+    /// same variant. An unsigned value above `i64::MAX` is an error.
+    pub unify_integers: bool,
+    /// Coding of strings whose type has no `coding`, UTF-8 unless changed.
+    pub default_coding: StringCoding,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        DecodeOptions {
+            max_nodes: None,
+            max_depth: Some(64),
+            unify_integers: false,
+            default_coding: StringCoding::default(),
+        }
+    }
+}
+
+/// A recoverable anomaly in a payload, reported by
+/// [`some_ip_value_lenient`] instead of failing.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseWarning {
+    /// A `SomeIPType::Bool` byte other than 0 or 1, taken as `true`.
+    NonCanonicalBool { offset: usize, value: u8 },
+    /// A UTF-16 string without byte order mark, taken as big endian.
+    MissingBom { offset: usize },
+    /// Bytes left over after the value, which were ignored.
+    TrailingBytes(usize),
+}
+
+/// Bookkeeping threaded through a single decode.
+struct DecodeState<'o> {
+    options: &'o DecodeOptions,
+    /// Treat running out of input as an error instead of `Incomplete`.
+    complete: bool,
+    /// Length of the payload the decode started on, to derive offsets.
+    payload_len: usize,
+    /// Types `SomeIPType::Ref` is resolved against.
+    registry: Option<&'o TypeRegistry>,
+    nodes: usize,
+    depth: usize,
+    /// Anomalies that are tolerated rather than failed on; `None` for strict
+    /// decoding.
+    warnings: Option<Vec<ParseWarning>>,
+}
+
+impl<'o> DecodeState<'o> {
+    fn new(options: &'o DecodeOptions, complete: bool, payload: &[u8]) -> Self {
+        DecodeState {
+            options,
+            complete,
+            payload_len: payload.len(),
+            registry: None,
+            nodes: 0,
+            depth: 0,
+            warnings: None,
+        }
+    }
+
+    /// Counts a node about to be decoded from `input` one level deeper,
+    /// failing if that exceeds a limit.
+    fn enter<'a>(&mut self, input: &'a [u8]) -> Result<(), nom::Err<Error<'a>>> {
+        self.nodes += 1;
+        if self.options.max_nodes.is_some_and(|max| self.nodes > max) {
+            return Err(nom::Err::Failure(Error::new(
+                input,
+                InnerError::NodeLimitExceeded,
+            )));
+        }
+        self.depth += 1;
+        if self.options.max_depth.is_some_and(|max| self.depth > max) {
+            return Err(nom::Err::Failure(Error::new(
+                input,
+                InnerError::DepthLimitExceeded,
+            )));
+        }
+        Ok(())
+    }
+
+    /// Offset of `input` from the start of the payload.
+    fn offset(&self, input: &[u8]) -> usize {
+        self.payload_len - input.len()
+    }
+
+    /// Records a warning, or returns `false` if decoding is strict.
+    fn warn(&mut self, warning: ParseWarning) -> bool {
+        match &mut self.warnings {
+            Some(warnings) => {
+                warnings.push(warning);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The coding of a string type with `coding`.
+    fn coding(&self, coding: &Option<StringCoding>) -> StringCoding {
+        coding.unwrap_or(self.options.default_coding)
+    }
+
+    /// Warns about UTF-16 `str_bytes` at `input` that lack a byte order mark.
+    fn check_bom(&mut self, input: &[u8], str_bytes: &[u8], coding: StringCoding) {
+        if coding == StringCoding::Utf16
+            && !str_bytes.is_empty()
+            && !matches!(str_bytes, [0xfe, 0xff, ..] | [0xff, 0xfe, ..])
+        {
+            let offset = self.offset(input);
+            self.warn(ParseWarning::MissingBom { offset });
+        }
+    }
+
+    /// An unsigned integer as `Value::UInt`, or as `Value::Int` if integers
+    /// are unified.
+    fn uint<'a>(&self, input: &'a [u8], value: u64) -> Result<Value, nom::Err<Error<'a>>> {
+        if !self.options.unify_integers {
+            return Ok(Value::UInt(value));
+        }
+        i64::try_from(value)
+            .map(Value::Int)
+            .map_err(|_| nom::Err::Error(Error::new(input, InnerError::IntegerOverflow(value))))
+    }
+}
+
+pub fn some_ip_value<'a>(input: &'a [u8], def: &SomeIPType) -> IResult<&'a [u8], Value, Error<'a>> {
+    some_ip_value_with_options(input, def, &DecodeOptions::default())
+}
+
+pub fn some_ip_value_with_options<'a>(
+    input: &'a [u8],
+    def: &SomeIPType,
+    options: &DecodeOptions,
+) -> IResult<&'a [u8], Value, Error<'a>> {
+    decode_value(input, def, &mut DecodeState::new(options, false, input))
+}
+
+/// Parses a value like [`some_ip_value`], but tolerates inputs a sloppy
+/// sender may produce, returning what was tolerated alongside the result.
+pub fn some_ip_value_lenient<'a>(
+    input: &'a [u8],
+    def: &SomeIPType,
+) -> (IResult<&'a [u8], Value, Error<'a>>, Vec<ParseWarning>) {
+    let options = DecodeOptions::default();
+    let mut state = DecodeState::new(&options, false, input);
+    state.warnings = Some(Vec::new());
+    let result = decode_value(input, def, &mut state);
+    let mut warnings = state.warnings.unwrap_or_default();
+    if let Ok((rest, _)) = &result
+        && !rest.is_empty()
+    {
+        warnings.push(ParseWarning::TrailingBytes(rest.len()));
+    }
+    (result, warnings)
+}
+
+/// Parses a value whose definition may refer to types in `registry`.
+pub fn some_ip_value_with_registry<'a>(
+    input: &'a [u8],
+    def: &SomeIPType,
+    registry: &TypeRegistry,
+    options: &DecodeOptions,
+) -> IResult<&'a [u8], Value, Error<'a>> {
+    let mut state = DecodeState::new(options, false, input);
+    state.registry = Some(registry);
+    decode_value(input, def, &mut state)
+}
+
+/// Parses a `StaticString` or `DynamicString` without copying it when the
+/// input already holds it as UTF-8.
+pub fn some_ip_string<'a>(
+    input: &'a [u8],
+    def: &SomeIPType,
+) -> IResult<&'a [u8], Cow<'a, str>, Error<'a>> {
+    let (i1, str_bytes, coding) = match def {
+        SomeIPType::StaticString { length, coding } => {
+            let (i1, str_bytes) = take_bytes(input, *length, false)?;
+            (i1, str_bytes, coding)
+        }
+        SomeIPType::DynamicString {
+            length_width,
+            coding,
+        } => {
+            let (i1, length) = someip_dynamic_length(input, *length_width, false)?;
+            let (i2, str_bytes) = take_bytes(i1, length, false)?;
+            (i2, str_bytes, coding)
+        }
+        _ => {
+            return Err(nom::Err::Error(Error::new(
+                input,
+                InnerError::UnsupportedType,
+            )));
+        }
+    };
+    Ok((i1, someip_string(str_bytes, coding.unwrap_or_default())?))
+}
+
+/// Decodes only the first field of a struct, or the whole value of any other
+/// type, e.g. to route a request by a leading command id. The remaining
+/// fields are neither decoded nor required to be present.
+pub fn decode_first_field<'a>(input: &'a [u8], def: &SomeIPType) -> Result<Value, Error<'a>> {
+    let options = DecodeOptions::default();
+    let mut state = DecodeState::new(&options, true, input);
+    let result = match def {
+        SomeIPType::Struct {
+            fields,
+            length_width,
+            ..
+        } => {
+            let body = match length_width {
+                Some(length_width) => {
+                    someip_dynamic_length(input, *length_width, true).map(|(body, _)| body)
+                }
+                None => Ok(input),
+            };
+            body.and_then(|body| match fields.first() {
+                Some((_, field_def)) => decode_value(body, field_def, &mut state),
+                None => Ok((body, Value::Struct { fields: Vec::new() })),
+            })
+        }
+        _ => decode_value(input, def, &mut state),
+    };
+    match result {
+        Ok((_, value)) => Ok(value),
+        Err(nom::Err::Error(e) | nom::Err::Failure(e)) => Err(e),
+        Err(nom::Err::Incomplete(_)) => unreachable!("complete decodes never need more input"),
+    }
+}
+
+fn decode_value<'a>(
+    input: &'a [u8],
+    def: &SomeIPType,
+    state: &mut DecodeState,
+) -> IResult<&'a [u8], Value, Error<'a>> {
+    state.enter(input)?;
+    let result = decode_type(input, def, state);
+    state.depth -= 1;
+    result
+}
+
+fn decode_type<'a>(
+    input: &'a [u8],
+    def: &SomeIPType,
+    state: &mut DecodeState,
+) -> IResult<&'a [u8], Value, Error<'a>> {
+    let (i1, value) = match def {
+        SomeIPType::Bool => {
+            let (i1, val) = number!(be_u8, input, state.complete)?;
+            if val > 1 {
+                let offset = state.offset(input);
+                if !state.warn(ParseWarning::NonCanonicalBool { offset, value: val }) {
+                    return Err(nom::Err::Error(Error::new(
+                        input,
+                        InnerError::InvalidBool(val),
+                    )));
+                }
+            }
+            (i1, Value::Bool(val != 0))
+        }
+        SomeIPType::UInt8 => {
+            let (i1, val) = number!(be_u8, input, state.complete)?;
+            (i1, state.uint(input, val.into())?)
+        }
+        SomeIPType::UInt16 => {
+            let (i1, val) = number!(be_u16, input, state.complete)?;
+            (i1, state.uint(input, val.into())?)
+        }
+        SomeIPType::UInt32 => {
+            let (i1, val) = number!(be_u32, input, state.complete)?;
+            (i1, state.uint(input, val.into())?)
+        }
+        SomeIPType::UInt64 => {
+            let (i1, val) = number!(be_u64, input, state.complete)?;
+            (i1, state.uint(input, val)?)
+        }
+        SomeIPType::SInt8 => {
+            let (i1, val) = number!(be_i8, input, state.complete)?;
+            (i1, Value::Int(val.into()))
+        }
+        SomeIPType::SInt16 => {
+            let (i1, val) = number!(be_i16, input, state.complete)?;
+            (i1, Value::Int(val.into()))
+        }
+        SomeIPType::SInt32 => {
+            let (i1, val) = number!(be_i32, input, state.complete)?;
+            (i1, Value::Int(val.into()))
+        }
+        SomeIPType::SInt64 => {
+            let (i1, val) = number!(be_i64, input, state.complete)?;
+            (i1, Value::Int(val))
+        }
+        SomeIPType::UInt128 => {
+            let (i1, val) = number!(be_u128, input, state.complete)?;
+            (i1, Value::UInt128(val))
+        }
+        SomeIPType::SInt128 => {
+            let (i1, val) = number!(be_i128, input, state.complete)?;
+            (i1, Value::Int128(val))
+        }
+        SomeIPType::Float16 => {
+            let (i1, val) = number!(be_u16, input, state.complete)?;
+            (i1, Value::Float(f16_to_f64(val)))
+        }
+        SomeIPType::QFixed {
+            int_bits,
+            frac_bits,
+            signed,
+        } => {
+            let width = u16::from(*int_bits) + u16::from(*frac_bits);
+            let Some(width @ (8 | 16 | 32 | 64)) = u8::try_from(width).ok() else {
+                return Err(nom::Err::Error(Error::new(
+                    input,
+                    InnerError::InvalidBitWidth(width),
+                )));
+            };
+            let (i1, raw) = someip_dynamic_length(input, width, state.complete)?;
+            let raw = if *signed {
+                // sign extend from `width` bits
+                let shift = 64 - u32::from(width);
+                (((raw << shift) as i64) >> shift) as f64
+            } else {
+                raw as f64
+            };
+            (i1, Value::Float(raw / 2f64.powi((*frac_bits).into())))
+        }
+        SomeIPType::Scaled {
+            base,
+            factor,
+            offset,
+            keep_raw,
+        } => {
+            let (i1, raw) = decode_value(input, base, state)?;
+            let physical = match raw {
+                Value::UInt(raw) => raw as f64,
+                Value::Int(raw) => raw as f64,
+                _ => {
+                    return Err(nom::Err::Error(Error::new(
+                        input,
+                        InnerError::UnsupportedType,
+                    )));
+                }
+            } * factor
+                + offset;
+            let value = if *keep_raw {
+                Value::Struct {
+                    fields: vec![
+                        ("value".to_string(), Value::Float(physical)),
+                        ("raw".to_string(), raw),
+                    ],
+                }
+            } else {
+                Value::Float(physical)
+            };
+            (i1, value)
+        }
+        SomeIPType::Constrained { base, min, max } => {
+            let (i1, value) = decode_value(input, base, state)?;
+            let number = match value {
+                Value::UInt(v) => i128::from(v),
+                Value::Int(v) => i128::from(v),
+                Value::Int128(v) => v,
+                Value::UInt128(v) => i128::try_from(v).map_err(|_| {
+                    nom::Err::Error(Error::new(input, InnerError::UInt128OutOfRange(v)))
+                })?,
+                _ => {
+                    return Err(nom::Err::Error(Error::new(
+                        input,
+                        InnerError::UnsupportedType,
+                    )));
+                }
+            };
+            if !(*min..=*max).contains(&number) {
+                return Err(nom::Err::Error(Error::new(
+                    input,
+                    InnerError::ValueOutOfRange(number),
+                )));
+            }
+            (i1, value)
+        }
+        SomeIPType::Float32 => {
+            let (i1, val) = number!(be_f32, input, state.complete)?;
+            (i1, Value::Float(val.into()))
+        }
+        SomeIPType::Float64 => {
+            let (i1, val) = number!(be_f64, input, state.complete)?;
+            (i1, Value::Float(val))
+        }
+        SomeIPType::Struct {
+            fields,
+            alignment,
+            length_width: None,
+            layout,
+        } => someip_struct(input, fields, *alignment, *layout, state)?,
+        SomeIPType::Struct {
+            fields,
+            alignment,
+            length_width: Some(length_width),
+            layout,
+        } => {
+            let (i1, length) = someip_dynamic_length(input, *length_width, state.complete)?;
+            let (i2, body) = take_bytes(i1, length, state.complete)?;
+            // the body is complete, and offsets stay those in the payload
+            let outer = (state.complete, state.payload_len);
+            state.complete = true;
+            state.payload_len -= i2.len();
+            let result = someip_struct(body, fields, *alignment, *layout, state);
+            (state.complete, state.payload_len) = outer;
+            // fields added by a newer interface version are skipped
+            (i2, result?.1)
+        }
+        SomeIPType::OptionalGroup {
+            mask_width,
+            members,
+        } => someip_struct(
+            input,
+            members,
+            1,
+            StructLayout::BitmaskOptional {
+                mask_width: *mask_width,
+            },
+            state,
+        )?,
+        SomeIPType::DynamicArray {
+            length_width,
+            element,
+            length_unit,
+            min_length,
+            max_length,
+        } => {
+            let (i1, length) = someip_dynamic_length(input, *length_width, state.complete)?;
+            let check_length = |count: u64| {
+                if min_length.is_some_and(|min| count < u64::from(min))
+                    || max_length.is_some_and(|max| count > u64::from(max))
+                {
+                    return Err(nom::Err::Error(Error::new(
+                        input,
+                        InnerError::ArrayLengthConstraint(count),
+                    )));
+                }
+                Ok(())
+            };
+
+            match length_unit {
+                LengthUnit::Elements => {
+                    check_length(length)?;
+                    someip_array(i1, element, length, state)?
+                }
+                LengthUnit::Bytes => {
+                    let (i2, value) = someip_array_bytes(i1, element, length, state)?;
+                    if let Value::Array(elements) = &value {
+                        check_length(elements.len() as u64)?;
+                    }
+                    (i2, value)
+                }
+            }
+        }
+        SomeIPType::StaticArray { length, element } => match element.fixed_size() {
+            Some(stride) if stride > 0 => {
+                someip_array_fixed(input, element, *length as u64, stride, state)?
+            }
+            _ => someip_array(input, element, *length as u64, state)?,
+        },
+        SomeIPType::SentinelArray { element, sentinel } => {
+            let mut i1 = input;
+            let mut elements = Vec::new();
+            loop {
+                let (new_input, value) = decode_value(i1, element, state)?;
+                if value == *sentinel {
+                    i1 = new_input;
+                    break;
+                }
+                // an element taking no bytes would never reach the sentinel
+                if new_input.len() == i1.len() {
+                    return Err(nom::Err::Error(Error::new(
+                        i1,
+                        InnerError::Nom(ErrorKind::Many0),
+                    )));
+                }
+                i1 = new_input;
+                elements.push(value);
+            }
+            (i1, Value::Array(elements))
+        }
+        SomeIPType::Enum {
+            variants,
+            open_enum,
+            base,
+        } => {
+            let (i1, variant) = enum_discriminant(input, base.as_deref(), state.complete)?;
+            match variants.iter().find(|(i, _)| *i == variant) {
+                Some((_, name)) => (i1, Value::Enum(name.clone())),
+                None if *open_enum => (i1, Value::EnumRaw(variant)),
+                None => {
+                    return Err(nom::Err::Error(Error::new(
+                        input,
+                        InnerError::UnknownDiscriminant(variant),
+                    )));
+                }
+            }
+        }
+        SomeIPType::EnumSelectedStruct {
+            enum_def,
+            variant_structs,
+        } => {
+            let (i1, selector) = decode_value(input, enum_def, state)?;
+            let Value::Enum(variant) = &selector else {
+                return Err(nom::Err::Error(Error::new(
+                    input,
+                    InnerError::InvalidSelector,
+                )));
+            };
+            let Some(variant_def) = variant_structs.get(variant) else {
+                return Err(nom::Err::Error(Error::new(
+                    input,
+                    InnerError::UnmappedVariant(variant.clone()),
+                )));
+            };
+            let (i2, value) = decode_value(i1, variant_def, state)?;
+            (
+                i2,
+                Value::Struct {
+                    fields: vec![
+                        ("variant".to_string(), selector),
+                        ("value".to_string(), value),
+                    ],
+                },
+            )
+        }
+        SomeIPType::HeaderCountBody { count, element } => {
+            let (i1, count) = decode_value(input, count, state)?;
+            let length = match count {
+                Value::UInt(length) => length,
+                Value::Int(length) if length >= 0 => length as u64,
+                _ => {
+                    return Err(nom::Err::Error(Error::new(
+                        input,
+                        InnerError::UnsupportedType,
+                    )));
+                }
+            };
+            let (i2, items) = someip_array(i1, element, length, state)?;
+            (
+                i2,
+                Value::Struct {
+                    fields: vec![("count".to_string(), count), ("items".to_string(), items)],
+                },
+            )
+        }
+        SomeIPType::Union {
+            length_width,
+            type_width,
+            variants,
+        } => {
+            let (i1, length) = someip_dynamic_length(input, *length_width, state.complete)?;
+            let (i2, selector) = someip_dynamic_length(i1, *type_width, state.complete)?;
+            let Some((_, variant_def)) = variants.iter().find(|(tag, _)| *tag == selector) else {
+                return Err(nom::Err::Error(Error::new(
+                    i1,
+                    InnerError::UnmappedVariant(selector.to_string()),
+                )));
+            };
+            let (i3, value) = decode_value(i2, variant_def, state)?;
+            let Some(padding) = length.checked_sub((i2.len() - i3.len()) as u64) else {
+                return Err(nom::Err::Error(Error::new(
+                    i2,
+                    InnerError::LengthMismatch {
+                        declared: length as u32,
+                        actual: (i2.len() - i3.len()) as u32,
+                    },
+                )));
+            };
+            let (i4, _padding) = take_bytes(i3, padding, state.complete)?;
+            (
+                i4,
+                Value::Struct {
+                    fields: vec![
+                        ("type".to_string(), Value::UInt(selector)),
+                        ("value".to_string(), value),
+                    ],
+                },
+            )
+        }
+        SomeIPType::Bitfield { storage, fields } => {
+            let used = fields.iter().map(|(_, bits)| u16::from(*bits)).sum();
+            let width = storage_width(storage, used)
+                .map_err(|error| nom::Err::Error(Error::new(input, error)))?;
+            let (i1, raw) = someip_dynamic_length(input, width, state.complete)?;
+            let mut shift = u32::from(width);
+            let fields = fields
+                .iter()
+                .map(|(name, bits)| {
+                    shift -= u32::from(*bits);
+                    let mask = u64::MAX.checked_shr(64 - u32::from(*bits)).unwrap_or(0);
+                    Ok((name.clone(), state.uint(input, (raw >> shift) & mask)?))
+                })
+                .collect::<Result<_, _>>()?;
+            (i1, Value::Struct { fields })
+        }
+        SomeIPType::SignalGroup { storage, signals } => {
+            let used = signals
+                .iter()
+                .map(|signal| u16::from(signal.offset_bits) + u16::from(signal.width))
+                .max()
+                .unwrap_or(0);
+            let width = storage_width(storage, used)
+                .map_err(|error| nom::Err::Error(Error::new(input, error)))?;
+            let (i1, raw) = someip_dynamic_length(input, width, state.complete)?;
+            let fields = signals
+                .iter()
+                .map(|signal| {
+                    let shift = u32::from(width - signal.offset_bits - signal.width);
+                    let bits = u32::from(signal.width);
+                    let mask = u64::MAX.checked_shr(64 - bits).unwrap_or(0);
+                    let raw = (raw >> shift) & mask;
+                    let raw = if signal.signed && bits > 0 {
+                        // sign extend from `width` bits
+                        (((raw << (64 - bits)) as i64) >> (64 - bits)) as f64
+                    } else {
+                        raw as f64
+                    };
+                    (
+                        signal.name.clone(),
+                        Value::Float(raw * signal.factor + signal.offset),
+                    )
+                })
+                .collect();
+            (i1, Value::Struct { fields })
+        }
+        SomeIPType::StaticString { length, coding } => {
+            let coding = state.coding(coding);
+            let (i1, str_bytes) = take_bytes(input, *length, state.complete)?;
+            state.check_bom(input, str_bytes, coding);
+            let str = someip_string(str_bytes, coding)?;
+            (i1, Value::String(str.into_owned()))
+        }
+        SomeIPType::CString { coding } => {
+            let coding = state.coding(coding);
+            let (i1, str_bytes) = someip_cstring(input, coding, state.complete)?;
+            state.check_bom(input, str_bytes, coding);
+            let str = someip_string(str_bytes, coding)?;
+            (i1, Value::String(str.into_owned()))
+        }
+        SomeIPType::DynamicString {
+            length_width,
+            coding,
+        } => {
+            let coding = state.coding(coding);
+            let (i1, length) = someip_dynamic_length(input, *length_width, state.complete)?;
+            let (i2, str_bytes) = take_bytes(i1, length, state.complete)?;
+            state.check_bom(i1, str_bytes, coding);
+            let str = someip_string(str_bytes, coding)?;
+            (i2, Value::String(str.into_owned()))
+        }
+
+        SomeIPType::Opaque { length } => {
+            let (i1, bytes) = match length {
+                Some(length) => take_bytes(input, *length, state.complete)?,
+                None => (&input[input.len()..], input),
+            };
+            (i1, Value::Bytes(bytes.to_vec()))
+        }
+        SomeIPType::Ref(name) => {
+            // the node was already counted for the reference itself
+            state.nodes -= 1;
+            let Some(def) = state.registry.and_then(|registry| registry.get(name)) else {
+                return Err(nom::Err::Error(Error::new(
+                    input,
+                    InnerError::UnknownType(name.clone()),
+                )));
+            };
+            decode_value(input, def, state)?
+        }
+    };
+    Ok((i1, value))
+    //Ok((input, Value::Int(8)))
+}
+
+/// Reads the discriminant of an enum with integer type `base`, sign
+/// extending it to 64 bits if `base` is signed.
+pub(crate) fn enum_discriminant<'a>(
+    input: &'a [u8],
+    base: Option<&SomeIPType>,
+    complete: bool,
+) -> Result<(&'a [u8], u64), nom::Err<Error<'a>>> {
+    let (width, signed) = match base {
+        None | Some(SomeIPType::UInt8) => (8, false),
+        Some(SomeIPType::UInt16) => (16, false),
+        Some(SomeIPType::UInt32) => (32, false),
+        Some(SomeIPType::UInt64) => (64, false),
+        Some(SomeIPType::SInt8) => (8, true),
+        Some(SomeIPType::SInt16) => (16, true),
+        Some(SomeIPType::SInt32) => (32, true),
+        Some(SomeIPType::SInt64) => (64, true),
+        Some(_) => {
+            return Err(nom::Err::Error(Error::new(
+                input,
+                InnerError::UnsupportedType,
+            )));
+        }
+    };
+    let (i1, raw) = someip_dynamic_length(input, width, complete)?;
+    if !signed {
+        return Ok((i1, raw));
+    }
+    let shift = 64 - u32::from(width);
+    Ok((i1, (((raw << shift) as i64) >> shift) as u64))
+}
+
+/// The width in bits of the `storage` of a bitfield or signal group, which
+/// must be an unsigned integer of at least `used` bits.
+pub(crate) fn storage_width(storage: &SomeIPType, used: u16) -> Result<u8, InnerError> {
+    let width = match storage {
+        SomeIPType::UInt8 => 8,
+        SomeIPType::UInt16 => 16,
+        SomeIPType::UInt32 => 32,
+        SomeIPType::UInt64 => 64,
+        _ => return Err(InnerError::UnsupportedType),
+    };
+    if used > u16::from(width) {
+        return Err(InnerError::InvalidBitWidth(used));
+    }
+    Ok(width)
+}
+
+/// Widens an IEEE 754 half precision float.
+fn f16_to_f64(bits: u16) -> f64 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = i32::from((bits >> 10) & 0x1f);
+    let fraction = f64::from(bits & 0x3ff);
+    match exponent {
+        0 => sign * fraction * 2f64.powi(-24),
+        0x1f if fraction == 0.0 => sign * f64::INFINITY,
+        0x1f => f64::NAN,
+        _ => sign * (1.0 + fraction / 1024.0) * 2f64.powi(exponent - 15),
+    }
+}
+
+/// Decodes string bytes; without a coding they are taken to be UTF-8.
+///
+/// UTF-8 strings borrow from `input`. UTF-16 strings are big endian unless
+/// they start with a little endian byte order mark.
+fn someip_string<'a>(
+    input: &'a [u8],
+    coding: StringCoding,
+) -> Result<Cow<'a, str>, nom::Err<Error<'a>>> {
+    let invalid = || nom::Err::Error(Error::new(input, InnerError::InvalidString));
+    match coding {
+        StringCoding::Utf8 => std::str::from_utf8(input)
+            .map(Cow::Borrowed)
+            .map_err(|_| invalid()),
+        StringCoding::Utf16 => {
+            if !input.len().is_multiple_of(2) {
+                return Err(nom::Err::Error(Error::new(
+                    input,
+                    InnerError::InvalidStringLength(input.len() as u32),
+                )));
+            }
+            let (units, little_endian) = match input {
+                [0xfe, 0xff, rest @ ..] => (rest, false),
+                [0xff, 0xfe, rest @ ..] => (rest, true),
+                _ => (input, false),
+            };
+            let units = units.chunks_exact(2).map(|unit| {
+                if little_endian {
+                    u16::from_le_bytes([unit[0], unit[1]])
+                } else {
+                    u16::from_be_bytes([unit[0], unit[1]])
+                }
+            });
+            char::decode_utf16(units)
+                .collect::<Result<String, _>>()
+                .map(Cow::Owned)
+                .map_err(|_| invalid())
+        }
+    }
+}
+
+/// Splits off the bytes of a NUL terminated string, consuming the
+/// terminator.
+fn someip_cstring<'a>(
+    input: &'a [u8],
+    coding: StringCoding,
+    complete: bool,
+) -> Result<(&'a [u8], &'a [u8]), nom::Err<Error<'a>>> {
+    let unit = match coding {
+        StringCoding::Utf8 => 1,
+        StringCoding::Utf16 => 2,
+    };
+    let Some(end) = input
+        .chunks_exact(unit)
+        .position(|chunk| chunk.iter().all(|byte| *byte == 0))
+        .map(|position| position * unit)
+    else {
+        if !complete {
+            return Err(nom::Err::Incomplete(nom::Needed::Unknown));
+        }
+        return Err(nom::Err::Error(Error::new(
+            input,
+            InnerError::MissingTerminator,
+        )));
+    };
+    Ok((&input[end + unit..], &input[..end]))
+}
+
+fn someip_dynamic_length<'a>(
+    input: &'a [u8],
+    length_width: u8,
+    complete: bool,
+) -> Result<(&'a [u8], u64), nom::Err<Error<'a>>> {
+    let (i1, length) = match length_width {
+        8 => {
+            let (input, length) = number!(be_u8, input, complete)?;
+            (input, length as u64)
+        }
+        16 => {
+            let (input, length) = number!(be_u16, input, complete)?;
+            (input, length as u64)
+        }
+        32 => {
+            let (input, length) = number!(be_u32, input, complete)?;
+            (input, length as u64)
+        }
+        64 => {
+            let (input, length) = number!(be_u64, input, complete)?;
+            (input, length)
+        }
+        _ => {
+            return Err(nom::Err::Error(Error::new(
+                input,
+                InnerError::InvalidLengthWidth(length_width),
+            )));
+        }
+    };
+    Ok((i1, length))
+}
+
+fn someip_array<'a>(
+    mut input: &'a [u8],
+    element: &SomeIPType,
+    length: u64,
+    state: &mut DecodeState,
+) -> Result<(&'a [u8], Value), nom::Err<Error<'a>>> {
+    let mut elements = Vec::new();
+    for _ in 0..length {
+        let (new_input, value) = decode_value(input, element, state)?;
+        input = new_input;
+        elements.push(value);
+    }
+    Ok((input, Value::Array(elements)))
+}
+
+/// Like [`someip_array`] for elements that always take `stride` bytes: the
+/// length of the whole array is checked at once before the elements are
+/// decoded.
+fn someip_array_fixed<'a>(
+    mut input: &'a [u8],
+    element: &SomeIPType,
+    length: u64,
+    stride: usize,
+    state: &mut DecodeState,
+) -> Result<(&'a [u8], Value), nom::Err<Error<'a>>> {
+    let Some(size) = length.checked_mul(stride as u64) else {
+        return someip_array(input, element, length, state);
+    };
+    take_bytes(input, size, state.complete)?;
+    let mut elements = Vec::with_capacity(length as usize);
+    for _ in 0..length {
+        let (new_input, value) = decode_value(input, element, state)?;
+        input = new_input;
+        elements.push(value);
+    }
+    Ok((input, Value::Array(elements)))
+}
+
+/// Skips the padding needed to move `input` onto the next `alignment` boundary.
+fn someip_padding<'a>(
+    input: &'a [u8],
+    alignment: u8,
+    state: &DecodeState,
+) -> IResult<&'a [u8], &'a [u8], Error<'a>> {
+    let alignment = usize::from(alignment.max(1));
+    let padding = (alignment - state.offset(input) % alignment) % alignment;
+    take_bytes(input, padding, state.complete)
+}
+
+/// Parses elements until exactly `length` bytes have been consumed.
+fn someip_array_bytes<'a>(
+    mut input: &'a [u8],
+    element: &SomeIPType,
+    length: u64,
+    state: &mut DecodeState,
+) -> Result<(&'a [u8], Value), nom::Err<Error<'a>>> {
+    // a length from the wire may point far beyond the input
+    take_bytes(input, length, state.complete)?;
+    let end = state.offset(input) as u64 + length;
+    let mut elements = Vec::new();
+    while (state.offset(input) as u64) < end {
+        let (new_input, value) = decode_value(input, element, state)?;
+        if state.offset(new_input) as u64 > end {
+            return Err(nom::Err::Error(Error::new(
+                input,
+                InnerError::ArrayBoundaryCrossed,
+            )));
+        }
+        // an element taking no bytes would never reach the end
+        if new_input.len() == input.len() {
+            return Err(nom::Err::Error(Error::new(
+                input,
+                InnerError::Nom(ErrorKind::Many0),
+            )));
+        }
+        input = new_input;
+        elements.push(value);
+    }
+    Ok((input, Value::Array(elements)))
+}
+
+fn someip_struct<'a>(
+    input: &'a [u8],
+    fields: &[(String, SomeIPType)],
+    alignment: u8,
+    layout: StructLayout,
+    state: &mut DecodeState,
+) -> Result<(&'a [u8], Value), nom::Err<Error<'a>>> {
+    let (mut i1, mask) = match layout {
+        StructLayout::Sequential => (input, None),
+        StructLayout::BitmaskOptional { mask_width } => {
+            let (i1, mask) = someip_dynamic_length(input, mask_width, state.complete)?;
+            (i1, Some(mask))
+        }
+        StructLayout::Tlv => {
+            return Err(nom::Err::Error(Error::new(
+                input,
+                InnerError::UnsupportedType,
+            )));
+        }
+    };
+    let mut values = Vec::new();
+    for (bit, (name, def)) in fields.iter().enumerate() {
+        if mask.is_some_and(|mask| mask.checked_shr(bit as u32).unwrap_or(0) & 1 == 0) {
+            continue;
+        }
+        let (aligned, _padding) = someip_padding(i1, alignment, state)?;
+        let (new_input, value) = decode_value(aligned, def, state)?;
+        i1 = new_input;
+        values.push((name.clone(), value));
+    }
+    Ok((i1, Value::Struct { fields: values }))
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SomeIPMessageType {
+    Request(),
+    RequestNoReturn(),
+    Notification(),
+    Response(),
+    Error(),
+    TPRequest(),
+    TPRequestNoReturn(),
+    TPNotification(),
+    /// A byte naming none of the above, kept so it can be written back.
+    Unknown(u8),
+}
+
+impl From<u8> for SomeIPMessageType {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => Self::Request(),
+            0x01 => Self::RequestNoReturn(),
+            0x02 => Self::Notification(),
+            0x80 => Self::Response(),
+            0x81 => Self::Error(),
+            0x20 => Self::TPRequest(),
+            0x21 => Self::TPRequestNoReturn(),
+            0x22 => Self::TPNotification(),
+            _ => Self::Unknown(value),
+        }
+    }
+}
+
+impl From<&SomeIPMessageType> for u8 {
+    fn from(value: &SomeIPMessageType) -> Self {
+        match value {
+            SomeIPMessageType::Request() => 0x00,
+            SomeIPMessageType::RequestNoReturn() => 0x01,
+            SomeIPMessageType::Notification() => 0x02,
+            SomeIPMessageType::Response() => 0x80,
+            SomeIPMessageType::Error() => 0x81,
+            SomeIPMessageType::TPRequest() => 0x20,
+            SomeIPMessageType::TPRequestNoReturn() => 0x21,
+            SomeIPMessageType::TPNotification() => 0x22,
+            SomeIPMessageType::Unknown(value) => *value,
+        }
+    }
+}
+
+impl From<SomeIPMessageType> for u8 {
+    fn from(value: SomeIPMessageType) -> Self {
+        u8::from(&value)
+    }
+}
+
+impl SomeIPMessageType {
+    /// The byte the message type is written as.
+    pub fn as_u8(&self) -> u8 {
+        self.into()
+    }
+
+    /// The name the SOME/IP specification uses for the message type.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Request() => "REQUEST",
+            Self::RequestNoReturn() => "REQUEST_NO_RETURN",
+            Self::Notification() => "NOTIFICATION",
+            Self::Response() => "RESPONSE",
+            Self::Error() => "ERROR",
+            Self::TPRequest() => "TP_REQUEST",
+            Self::TPRequestNoReturn() => "TP_REQUEST_NO_RETURN",
+            Self::TPNotification() => "TP_NOTIFICATION",
+            Self::Unknown(_) => "UNKNOWN",
+        }
+    }
+}
+
+/// The return code of a header, as assigned by the SOME/IP specification.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ReturnCode {
+    Ok,
+    NotOk,
+    UnknownService,
+    UnknownMethod,
+    NotReady,
+    NotReachable,
+    Timeout,
+    WrongProtocolVersion,
+    WrongInterfaceVersion,
+    MalformedMessage,
+    WrongMessageType,
+    E2eRepeated,
+    E2eWrongSequence,
+    E2e,
+    E2eNotAvailable,
+    E2eNoNewData,
+    /// A code from 0x20 to 0x5e, left to services and methods for their own
+    /// errors.
+    VendorSpecific(u8),
+    /// A reserved code.
+    Unknown(u8),
+}
+
+impl From<u8> for ReturnCode {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => Self::Ok,
+            0x01 => Self::NotOk,
+            0x02 => Self::UnknownService,
+            0x03 => Self::UnknownMethod,
+            0x04 => Self::NotReady,
+            0x05 => Self::NotReachable,
+            0x06 => Self::Timeout,
+            0x07 => Self::WrongProtocolVersion,
+            0x08 => Self::WrongInterfaceVersion,
+            0x09 => Self::MalformedMessage,
+            0x0a => Self::WrongMessageType,
+            0x0b => Self::E2eRepeated,
+            0x0c => Self::E2eWrongSequence,
+            0x0d => Self::E2e,
+            0x0e => Self::E2eNotAvailable,
+            0x0f => Self::E2eNoNewData,
+            0x20..=0x5e => Self::VendorSpecific(value),
+            _ => Self::Unknown(value),
+        }
+    }
+}
+
+impl From<ReturnCode> for u8 {
+    fn from(value: ReturnCode) -> Self {
+        match value {
+            ReturnCode::Ok => 0x00,
+            ReturnCode::NotOk => 0x01,
+            ReturnCode::UnknownService => 0x02,
+            ReturnCode::UnknownMethod => 0x03,
+            ReturnCode::NotReady => 0x04,
+            ReturnCode::NotReachable => 0x05,
+            ReturnCode::Timeout => 0x06,
+            ReturnCode::WrongProtocolVersion => 0x07,
+            ReturnCode::WrongInterfaceVersion => 0x08,
+            ReturnCode::MalformedMessage => 0x09,
+            ReturnCode::WrongMessageType => 0x0a,
+            ReturnCode::E2eRepeated => 0x0b,
+            ReturnCode::E2eWrongSequence => 0x0c,
+            ReturnCode::E2e => 0x0d,
+            ReturnCode::E2eNotAvailable => 0x0e,
+            ReturnCode::E2eNoNewData => 0x0f,
+            ReturnCode::VendorSpecific(value) | ReturnCode::Unknown(value) => value,
+        }
+    }
+}
+
+impl ReturnCode {
+    /// The name the SOME/IP specification uses for the code, e.g. `E_OK`.
+    pub fn description(&self) -> Cow<'static, str> {
+        let name = match self {
+            Self::Ok => "E_OK",
+            Self::NotOk => "E_NOT_OK",
+            Self::UnknownService => "E_UNKNOWN_SERVICE",
+            Self::UnknownMethod => "E_UNKNOWN_METHOD",
+            Self::NotReady => "E_NOT_READY",
+            Self::NotReachable => "E_NOT_REACHABLE",
+            Self::Timeout => "E_TIMEOUT",
+            Self::WrongProtocolVersion => "E_WRONG_PROTOCOL_VERSION",
+            Self::WrongInterfaceVersion => "E_WRONG_INTERFACE_VERSION",
+            Self::MalformedMessage => "E_MALFORMED_MESSAGE",
+            Self::WrongMessageType => "E_WRONG_MESSAGE_TYPE",
+            Self::E2eRepeated => "E_E2E_REPEATED",
+            Self::E2eWrongSequence => "E_E2E_WRONG_SEQUENCE",
+            Self::E2e => "E_E2E",
+            Self::E2eNotAvailable => "E_E2E_NOT_AVAILABLE",
+            Self::E2eNoNewData => "E_E2E_NO_NEW_DATA",
+            Self::VendorSpecific(value) => {
+                return Cow::Owned(format!("VENDOR_SPECIFIC({value:#04x})"));
+            }
+            Self::Unknown(value) => return Cow::Owned(format!("UNKNOWN({value:#04x})")),
+        };
+        Cow::Borrowed(name)
+    }
+
+    pub fn is_vendor_specific(&self) -> bool {
+        matches!(self, Self::VendorSpecific(_))
+    }
+}
+
+/// A header together with its still undecoded payload.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SomeIPMessage<'a> {
+    pub header: SomeIPHeader,
+    pub payload: &'a [u8],
+}
+
+pub struct SomeIPMessageBody {}
+
+/// What the length field of a dynamic array counts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LengthUnit {
+    Bytes,
+    Elements,
+}
+
+/// How the members of a `SomeIPType::Struct` are arranged on the wire.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StructLayout {
+    /// Every member in declared order, without gaps beyond alignment.
+    #[default]
+    Sequential,
+    /// Members identified by a tag in front of each, in any order. Not
+    /// decodable yet.
+    Tlv,
+    /// Members in declared order, preceded by a mask of `mask_width` bits
+    /// telling which of them are present, as for `SomeIPType::OptionalGroup`.
+    BitmaskOptional { mask_width: u8 },
+}
+
+/// The coding of a string type. Types that leave it `None` are decoded
+/// with `DecodeOptions::default_coding` and encoded as UTF-8.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StringCoding {
+    #[default]
+    Utf8,
+    Utf16,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SomeIPType {
+    /// One byte, 0 for false and 1 for true.
+    Bool,
+    /// IEEE 754 half precision, decoded into `Value::Float`.
+    Float16,
+    Float32,
+    Float64,
+    /// Fixed point number in Q format, e.g. Q8.8, stored in an integer of
+    /// `int_bits + frac_bits` bits, which must be 8, 16, 32 or 64. For signed
+    /// numbers `int_bits` includes the sign bit. Decoded into `Value::Float`.
+    QFixed {
+        int_bits: u8,
+        frac_bits: u8,
+        signed: bool,
+    },
+    /// An integer `base` holding a physical value as `raw * factor + offset`,
+    /// decoded into `Value::Float`.
+    ///
+    /// With `keep_raw` it decodes to a struct holding the physical value as
+    /// `value` and the integer as `raw`.
+    Scaled {
+        base: Box<SomeIPType>,
+        factor: f64,
+        offset: f64,
+        #[cfg_attr(feature = "serde", serde(default))]
+        keep_raw: bool,
+    },
+    /// An integer `base` whose value must lie within `min..=max`, as FIBEX
+    /// and ARXML data types may declare.
+    Constrained {
+        base: Box<SomeIPType>,
+        min: i128,
+        max: i128,
+    },
+    SInt8,
+    SInt16,
+    SInt32,
+    SInt64,
+    /// Decodes to `Value::Int128`.
+    SInt128,
+    UInt8,
+    UInt16,
+    UInt32,
+    UInt64,
+    /// Decodes to `Value::UInt128`, e.g. for UUIDs.
+    UInt128,
+    Struct {
+        fields: Vec<(String, SomeIPType)>,
+        /// Byte boundary each member starts on, relative to the start of the
+        /// payload. Padding before a member is skipped; 0 and 1 mean packed.
+        alignment: u8,
+        /// Width in bits of a length field preceding the members. Bytes the
+        /// length covers beyond the known members are skipped and not kept
+        /// in the decoded value, so encoding it again writes only the known
+        /// members and a shorter length.
+        #[cfg_attr(feature = "serde", serde(default))]
+        length_width: Option<u8>,
+        #[cfg_attr(feature = "serde", serde(default))]
+        layout: StructLayout,
+    },
+    /// Optional members preceded by a mask of `mask_width` bits telling
+    /// which of them are present. The least significant bit stands for the
+    /// first member.
+    ///
+    /// Decodes to a struct holding only the present members.
+    OptionalGroup {
+        mask_width: u8,
+        members: Vec<(String, SomeIPType)>,
+    },
+    StaticArray {
+        length: u32,
+        element: Box<SomeIPType>,
+    },
+    DynamicArray {
+        length_width: u8,
+        element: Box<SomeIPType>,
+        length_unit: LengthUnit,
+        /// Fewest elements the array may have.
+        #[cfg_attr(feature = "serde", serde(default))]
+        min_length: Option<u32>,
+        /// Most elements the array may have. Counted lengths are checked
+        /// before any element is decoded.
+        #[cfg_attr(feature = "serde", serde(default))]
+        max_length: Option<u32>,
+    },
+    /// An array without length field, ended by the first element equal to
+    /// `sentinel`. The sentinel is consumed but not part of the array.
+    SentinelArray {
+        element: Box<SomeIPType>,
+        sentinel: Value,
+    },
+    Enum {
+        /// With a signed `base`, discriminants are sign extended to 64 bits,
+        /// e.g. `-1i64 as u64` for -1.
+        variants: Vec<(u64, String)>,
+        /// Decode discriminants without a variant into `Value::EnumRaw`
+        /// instead of failing.
+        #[cfg_attr(feature = "serde", serde(default))]
+        open_enum: bool,
+        /// The integer type of the discriminant, `UInt8` if not set.
+        #[cfg_attr(feature = "serde", serde(default))]
+        base: Option<Box<SomeIPType>>,
+    },
+    /// An enum followed by the struct mapped to its decoded variant name.
+    ///
+    /// Decodes to a struct holding the enum as `variant` and the selected
+    /// struct as `value`.
+    EnumSelectedStruct {
+        enum_def: Box<SomeIPType>,
+        variant_structs: HashMap<String, SomeIPType>,
+    },
+    /// A `count` followed by that many `element`s.
+    ///
+    /// Decodes to a struct holding the count as `count` and the elements as
+    /// the array `items`. `count` must decode to an unsigned integer.
+    HeaderCountBody {
+        count: Box<SomeIPType>,
+        element: Box<SomeIPType>,
+    },
+    /// A length field, a type field selecting one of `variants` and the
+    /// selected member, padded up to the length.
+    ///
+    /// Decodes to a struct holding the type field as `type` and the member as
+    /// `value`.
+    Union {
+        length_width: u8,
+        type_width: u8,
+        variants: Vec<(u64, SomeIPType)>,
+    },
+    /// An unsigned integer `storage` read once and split into named bit
+    /// ranges of the given widths, the first field taking the most
+    /// significant bits. Bits left over at the low end are ignored.
+    ///
+    /// Decodes to a struct holding every field as an unsigned integer.
+    Bitfield {
+        storage: Box<SomeIPType>,
+        fields: Vec<(String, u8)>,
+    },
+    /// Scaled signals packed into an unsigned integer `storage`, which is
+    /// read once.
+    ///
+    /// Decodes to a struct holding every signal as `Value::Float`.
+    SignalGroup {
+        storage: Box<SomeIPType>,
+        signals: Vec<Signal>,
+    },
+    /// A string occupying a fixed number of bytes (not characters), so a
+    /// UTF-16 string must have an even `length`.
+    StaticString {
+        length: u32,
+        coding: Option<StringCoding>,
+    },
+    /// A string without length field, ended by a NUL character: a 0x00 byte
+    /// for UTF-8, a 0x0000 code unit for UTF-16. The terminator is consumed
+    /// but not part of the string.
+    CString {
+        coding: Option<StringCoding>,
+    },
+    /// A string preceded by its length in bytes. A length of 0 decodes to
+    /// an empty string in either coding; there is no BOM or terminator to
+    /// read then.
+    DynamicString {
+        length_width: u8,
+        coding: Option<StringCoding>,
+    },
+    /// Bytes kept undecoded, e.g. fields of a newer interface version.
+    ///
+    /// With `length: None` the rest of the input is consumed, so it should
+    /// only be used for the tail of a length-bounded payload.
+    Opaque {
+        length: Option<u32>,
+    },
+    /// A type registered under this name in a [`TypeRegistry`].
+    Ref(String),
+}
+
+impl SomeIPType {
+    /// The number of bytes every value of this type takes, or `None` if it
+    /// depends on the value or on where the value starts, e.g. to allocate a
+    /// buffer or check a header `length` before decoding.
+    pub fn fixed_size(&self) -> Option<usize> {
+        match self {
+            SomeIPType::UInt8 | SomeIPType::SInt8 | SomeIPType::Bool => Some(1),
+            SomeIPType::Enum { base, .. } => base.as_deref().map_or(Some(1), Self::fixed_size),
+            SomeIPType::UInt16 | SomeIPType::SInt16 | SomeIPType::Float16 => Some(2),
+            SomeIPType::UInt32 | SomeIPType::SInt32 | SomeIPType::Float32 => Some(4),
+            SomeIPType::UInt64 | SomeIPType::SInt64 | SomeIPType::Float64 => Some(8),
+            SomeIPType::UInt128 | SomeIPType::SInt128 => Some(16),
+            SomeIPType::QFixed {
+                int_bits,
+                frac_bits,
+                ..
+            } => match u16::from(*int_bits) + u16::from(*frac_bits) {
+                width @ (8 | 16 | 32 | 64) => Some(usize::from(width / 8)),
+                _ => None,
+            },
+            // padding depends on the offset the struct starts at
+            SomeIPType::Struct {
+                fields,
+                alignment,
+                length_width: None,
+                layout: StructLayout::Sequential,
+            } if *alignment <= 1 => fields
+                .iter()
+                .try_fold(0usize, |size, (_, def)| size.checked_add(def.fixed_size()?)),
+            SomeIPType::StaticArray { length, element } => {
+                element.fixed_size()?.checked_mul(*length as usize)
+            }
+            SomeIPType::Bitfield { storage, .. } | SomeIPType::SignalGroup { storage, .. } => {
+                storage.fixed_size()
+            }
+            SomeIPType::Scaled { base, .. } | SomeIPType::Constrained { base, .. } => {
+                base.fixed_size()
+            }
+            SomeIPType::StaticString { length, .. }
+            | SomeIPType::Opaque {
+                length: Some(length),
+            } => Some(*length as usize),
+            _ => None,
+        }
+    }
+}
+
+/// A signal of a `SomeIPType::SignalGroup`, holding the physical value
+/// `raw * factor + offset`.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Signal {
+    pub name: String,
+    /// Bits preceding the signal, counted from the most significant bit of
+    /// the storage.
+    pub offset_bits: u8,
+    pub width: u8,
+    /// The raw value is two's complement.
+    pub signed: bool,
+    pub factor: f64,
+    pub offset: f64,
+    /// Unit of the physical value, e.g. `"km/h"`.
+    pub unit: Option<String>,
+}
+
+/// A decoded value.
+///
+/// `PartialEq` compares floats by IEEE 754 rules, so a value containing
+/// `Float(NaN)` is never equal to itself; use [`Value::approx_eq`] to compare
+/// decoded floats.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Value {
+    Bool(bool),
+    Float(f64),
+    UInt(u64),
+    Int(i64),
+    /// Only decoded from `SomeIPType::UInt128`.
+    UInt128(u128),
+    /// Only decoded from `SomeIPType::SInt128`.
+    Int128(i128),
+    Struct {
+        #[cfg_attr(feature = "serde", serde(with = "serde_fields"))]
+        fields: Vec<(String, Value)>,
+    },
+    Array(Vec<Value>),
+    Enum(String),
+    /// A discriminant of an open enum that names none of its variants.
+    EnumRaw(u64),
+    String(String),
+    Bytes(Vec<u8>),
+    /// Struct fields keyed by name, for random access. Never produced by
+    /// decoding, which keeps wire order in `Struct`; see [`Value::to_map`].
+    Map(BTreeMap<String, Value>),
+}
+
+impl Value {
+    /// Compares like `==`, except that floats are equal when they differ by
+    /// at most `epsilon` and `NaN` is equal to `NaN`.
+    pub fn approx_eq(&self, other: &Value, epsilon: f64) -> bool {
+        match (self, other) {
+            (Value::Float(a), Value::Float(b)) => {
+                (a.is_nan() && b.is_nan()) || a == b || (a - b).abs() <= epsilon
+            }
+            (Value::Struct { fields: a }, Value::Struct { fields: b }) => {
+                a.len() == b.len()
+                    && a.iter().zip(b).all(|((a_name, a), (b_name, b))| {
+                        a_name == b_name && a.approx_eq(b, epsilon)
+                    })
+            }
+            (Value::Array(a), Value::Array(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.approx_eq(b, epsilon))
+            }
+            (a, b) => a == b,
+        }
+    }
+
+    /// The field `name` of a struct or map.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::Struct { fields } => fields
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value),
+            Value::Map(map) => map.get(name),
+            _ => None,
+        }
+    }
+
+    /// The fields of a struct keyed by name, for looking up fields in large
+    /// structs, e.g. to build a `Value::Map`. The map orders fields by name
+    /// instead of wire order, and of fields sharing a name only the last is
+    /// kept.
+    pub fn to_map(&self) -> Option<BTreeMap<String, Value>> {
+        match self {
+            Value::Struct { fields } => Some(fields.iter().cloned().collect()),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    /// The elements of an array, or nothing if this is not an array.
+    pub fn iter_array(&self) -> impl Iterator<Item = &Value> {
+        self.as_array().unwrap_or_default().iter()
+    }
+
+    pub fn as_struct_fields(&self) -> Option<&[(String, Value)]> {
+        match self {
+            Value::Struct { fields } => Some(fields),
+            _ => None,
+        }
+    }
+
+    /// Like [`Self::to_map`], consuming the struct.
+    pub fn into_map(self) -> Option<BTreeMap<String, Value>> {
+        match self {
+            Value::Struct { fields } => Some(fields.into_iter().collect()),
+            _ => None,
+        }
+    }
+
+    /// The value at `path`, made of field names separated by `.` and array
+    /// indices in brackets, e.g. `"position.samples[2].x"`.
+    pub fn get_path(&self, path: &str) -> Option<&Value> {
+        path.split('.').try_fold(self, |value, segment| {
+            let (name, mut indices) = segment.split_once('[').unwrap_or((segment, ""));
+            let mut value = if name.is_empty() {
+                value
+            } else {
+                value.get(name)?
+            };
+            while !indices.is_empty() {
+                let (index, rest) = indices.split_once(']')?;
+                let Value::Array(elements) = value else {
+                    return None;
+                };
+                value = elements.get(index.parse::<usize>().ok()?)?;
+                indices = rest.strip_prefix('[').unwrap_or(rest);
+            }
+            Some(value)
+        })
+    }
+
+    /// Renders the value as a tree, with every struct field and array element
+    /// on its own line, indented by `indent` spaces per nesting level.
+    pub fn to_pretty_string(&self, indent: usize) -> String {
+        let mut out = String::new();
+        self.write_pretty(&mut out, indent, 0);
+        out
+    }
+
+    fn write_pretty(&self, out: &mut String, indent: usize, level: usize) {
+        use fmt::Write;
+
+        let pad = |out: &mut String, level: usize| out.push_str(&" ".repeat(indent * level));
+        match self {
+            Value::Bool(v) => write!(out, "{v}").unwrap(),
+            Value::Float(v) => write!(out, "{v}").unwrap(),
+            Value::UInt(v) => write!(out, "{v}").unwrap(),
+            Value::Int(v) => write!(out, "{v}").unwrap(),
+            Value::UInt128(v) => write!(out, "{v}").unwrap(),
+            Value::Int128(v) => write!(out, "{v}").unwrap(),
+            Value::Enum(name) => out.push_str(name),
+            Value::EnumRaw(v) => write!(out, "<{v:#04x}>").unwrap(),
+            Value::String(string) => write!(out, "{string:?}").unwrap(),
+            Value::Bytes(bytes) => {
+                out.push_str("0x");
+                for byte in bytes {
+                    write!(out, "{byte:02x}").unwrap();
+                }
+            }
+            Value::Struct { fields } => {
+                let fields = fields.iter().map(|(name, value)| (name, value));
+                Self::write_pretty_fields(fields, out, indent, level);
+            }
+            Value::Map(map) => Self::write_pretty_fields(map.iter(), out, indent, level),
+            Value::Array(elements) if elements.is_empty() => out.push_str("[]"),
+            Value::Array(elements) => {
+                out.push_str("[\n");
+                for (i, value) in elements.iter().enumerate() {
+                    pad(out, level + 1);
+                    value.write_pretty(out, indent, level + 1);
+                    out.push_str(if i + 1 < elements.len() { ",\n" } else { "\n" });
+                }
+                pad(out, level);
+                out.push(']');
+            }
+        }
+    }
+}
+
+impl Value {
+    fn write_pretty_fields<'v>(
+        fields: impl ExactSizeIterator<Item = (&'v String, &'v Value)>,
+        out: &mut String,
+        indent: usize,
+        level: usize,
+    ) {
+        use fmt::Write;
+
+        if fields.len() == 0 {
+            out.push_str("{}");
+            return;
+        }
+        let len = fields.len();
+        out.push_str("{\n");
+        for (i, (name, value)) in fields.enumerate() {
+            out.push_str(&" ".repeat(indent * (level + 1)));
+            write!(out, "{name}: ").unwrap();
+            value.write_pretty(out, indent, level + 1);
+            out.push_str(if i + 1 < len { ",\n" } else { "\n" });
+        }
+        out.push_str(&" ".repeat(indent * level));
+        out.push('}');
+    }
+}
+
+/// Integers above this magnitude lose precision as a JSON (double) number.
+#[cfg(feature = "serde")]
+const MAX_SAFE_JSON_INTEGER: u64 = 1 << 53;
+
+/// Converts into plain JSON, without the variant names the derived
+/// `Serialize` adds.
+///
+/// Structs become objects, arrays arrays, enums and strings strings. Bytes
+/// become a hex string. Integers, including raw enum discriminants, become
+/// numbers unless their magnitude exceeds 2^53, above which JSON consumers
+/// parsing numbers as doubles would round them: those become decimal
+/// strings. Non finite floats become `null`.
+#[cfg(feature = "serde")]
+impl From<&Value> for serde_json::Value {
+    fn from(value: &Value) -> Self {
+        use serde_json::Value as Json;
+
+        match value {
+            Value::Bool(v) => Json::Bool(*v),
+            Value::Float(v) => serde_json::Number::from_f64(*v).map_or(Json::Null, Json::Number),
+            Value::UInt(v) | Value::EnumRaw(v) if *v > MAX_SAFE_JSON_INTEGER => {
+                Json::String(v.to_string())
+            }
+            Value::UInt(v) | Value::EnumRaw(v) => Json::from(*v),
+            Value::Int(v) if v.unsigned_abs() > MAX_SAFE_JSON_INTEGER => {
+                Json::String(v.to_string())
+            }
+            Value::Int(v) => Json::from(*v),
+            Value::UInt128(v) if *v > u128::from(MAX_SAFE_JSON_INTEGER) => {
+                Json::String(v.to_string())
+            }
+            Value::UInt128(v) => Json::from(*v as u64),
+            Value::Int128(v) if v.unsigned_abs() > u128::from(MAX_SAFE_JSON_INTEGER) => {
+                Json::String(v.to_string())
+            }
+            Value::Int128(v) => Json::from(*v as i64),
+            Value::Struct { fields } => Json::Object(
+                fields
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.into()))
+                    .collect(),
+            ),
+            Value::Map(map) => Json::Object(
+                map.iter()
+                    .map(|(name, value)| (name.clone(), value.into()))
+                    .collect(),
+            ),
+            Value::Array(elements) => Json::Array(elements.iter().map(Json::from).collect()),
+            Value::Enum(name) => Json::String(name.clone()),
+            Value::String(string) => Json::String(string.clone()),
+            Value::Bytes(bytes) => {
+                Json::String(bytes.iter().map(|byte| format!("{byte:02x}")).collect())
+            }
+        }
+    }
+}
+
+/// (De)serializes struct fields as a map keyed by field name, keeping wire order.
+#[cfg(feature = "serde")]
+mod serde_fields {
+    use super::Value;
+    use serde::{
+        Deserializer, Serializer,
+        de::{MapAccess, Visitor},
+        ser::SerializeMap,
+    };
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(
+        fields: &[(String, Value)],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(fields.len()))?;
+        for (name, value) in fields {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<(String, Value)>, D::Error> {
+        struct FieldsVisitor;
+
+        impl<'de> Visitor<'de> for FieldsVisitor {
+            type Value = Vec<(String, Value)>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map of field names to values")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+                let mut fields = Vec::with_capacity(access.size_hint().unwrap_or(0));
+                while let Some(entry) = access.next_entry()? {
+                    fields.push(entry);
+                }
+                Ok(fields)
+            }
+        }
+
+        deserializer.deserialize_map(FieldsVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_some_ip_header() {
+        let bytes: Vec<u8> = vec![
+            0xff, 0xff, 0x81, 0x0, 0x0, 0x0, 0x0, 0x30, 0x0, 0x0, 0x0, 0x3, 0x1, 0x1, 0x2, 0x0,
+            0xc0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x10, 0x1, 0x0, 0x0, 0x10, 0x0, 0xeb, 0x0, 0x0,
+            0x1, 0x0, 0x0, 0x1e, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xc, 0x0, 0x9, 0x4, 0x0, 0xc0,
+            0xa8, 0x58, 0x49, 0x0, 0x11, 0xc3, 0x50,
+        ];
+        let slice = bytes.as_slice();
+        let (_payload, header) = some_ip_header(slice).unwrap();
+
+        assert_eq!(header.service_id, 0xffff);
+        assert_eq!(header.method_id, 0x8100);
+        assert_eq!(header.length, 0x30);
+        assert_eq!(header.client_id, 0x0);
+        assert_eq!(header.session_id, 0x3);
+        assert_eq!(header.protocol_version, 0x1);
+        assert_eq!(header.interface_version, 0x1);
+        assert_eq!(header.message_type, SomeIPMessageType::Notification());
+        assert_eq!(header.return_code, ReturnCode::Ok);
+    }
+
+    #[test]
+    fn test_some_ip_uint32_value() {
+        let bytes: Vec<u8> = vec![0x12, 0x34, 0x56, 0x78];
+        let slice = bytes.as_slice();
+        let def = SomeIPType::UInt32;
+        let (remaining, value) = some_ip_value(slice, &def).unwrap();
+
+        assert_eq!(remaining.len(), 0);
+        assert_eq!(value, Value::UInt(0x12345678));
+    }
+
+    #[test]
+    fn test_some_ip_value_schema_outlives_input() {
+        static DEF: SomeIPType = SomeIPType::UInt16;
+        fn decode_local_def(input: &[u8]) -> IResult<&[u8], Value, Error<'_>> {
+            let def = SomeIPType::UInt16;
+            some_ip_value(input, &def)
+        }
+
+        let value = {
+            let bytes: Vec<u8> = vec![0x12, 0x34];
+            some_ip_value(&bytes, &DEF).unwrap().1
+        };
+        assert_eq!(value, Value::UInt(0x1234));
+        assert_eq!(
+            decode_local_def(&[0xab, 0xcd]),
+            Ok((&[][..], Value::UInt(0xabcd)))
+        );
+    }
+
+    #[test]
+    fn test_decode_first_field() {
+        // the data array claims more elements than there are bytes
+        let bytes: Vec<u8> = vec![0x0, 0x7, 0xff, 0xff, 0x1];
+        let def = SomeIPType::Struct {
+            fields: vec![
+                ("command".to_string(), SomeIPType::UInt16),
+                (
+                    "data".to_string(),
+                    SomeIPType::DynamicArray {
+                        length_width: 16,
+                        element: Box::new(SomeIPType::UInt32),
+                        length_unit: LengthUnit::Elements,
+                        min_length: None,
+                        max_length: None,
+                    },
+                ),
+            ],
+            alignment: 1,
+            length_width: None,
+            layout: StructLayout::Sequential,
+        };
+
+        assert_eq!(decode_first_field(&bytes, &def), Ok(Value::UInt(0x7)));
+        assert_eq!(
+            decode_first_field(&bytes, &SomeIPType::UInt8),
+            Ok(Value::UInt(0x0))
+        );
+        assert!(some_ip_value(&bytes, &def).is_err());
+    }
+
+    #[test]
+    fn test_some_ip_struct_value() {
+        let bytes: Vec<u8> = vec![0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc];
+        let slice = bytes.as_slice();
+        let def = SomeIPType::Struct {
+            fields: vec![
+                ("field1".to_string(), SomeIPType::UInt32),
+                ("field2".to_string(), SomeIPType::UInt16),
+            ],
+            alignment: 1,
+            length_width: None,
+            layout: StructLayout::Sequential,
+        };
+        let (remaining, value) = some_ip_value(slice, &def).unwrap();
+
+        assert_eq!(remaining.len(), 0);
+        assert_eq!(
+            value,
+            Value::Struct {
+                fields: vec![
+                    ("field1".to_string(), Value::UInt(0x12345678)),
+                    ("field2".to_string(), Value::UInt(0x9abc))
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn test_some_ip_struct_layouts() {
+        let bytes: Vec<u8> = vec![0b101, 0x12, 0x34, 0x56, 0x78];
+        let def = |layout| SomeIPType::Struct {
+            fields: vec![
+                ("mask".to_string(), SomeIPType::UInt8),
+                ("speed".to_string(), SomeIPType::UInt16),
+                ("gear".to_string(), SomeIPType::UInt8),
+            ],
+            alignment: 1,
+            length_width: None,
+            layout,
+        };
+
+        assert_eq!(
+            some_ip_value(&bytes, &def(StructLayout::Sequential)),
+            Ok((
+                &[0x78][..],
+                Value::Struct {
+                    fields: vec![
+                        ("mask".to_string(), Value::UInt(0b101)),
+                        ("speed".to_string(), Value::UInt(0x1234)),
+                        ("gear".to_string(), Value::UInt(0x56)),
+                    ]
+                }
+            ))
+        );
+        assert_eq!(
+            some_ip_value(
+                &bytes,
+                &def(StructLayout::BitmaskOptional { mask_width: 8 })
+            ),
+            Ok((
+                &[0x56, 0x78][..],
+                Value::Struct {
+                    fields: vec![
+                        ("mask".to_string(), Value::UInt(0x12)),
+                        ("gear".to_string(), Value::UInt(0x34)),
+                    ]
+                }
+            ))
+        );
+        assert_eq!(
+            some_ip_value(&bytes, &def(StructLayout::Tlv)),
+            Err(nom::Err::Error(Error::new(
+                &bytes,
+                InnerError::UnsupportedType
+            )))
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_value_serde_roundtrip() {
+        let value = Value::Struct {
+            fields: vec![
+                ("field1".to_string(), Value::UInt(0x12345678)),
+                ("field2".to_string(), Value::UInt(0x9abc)),
+            ],
+        };
+
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(
+            json,
+            r#"{"Struct":{"fields":{"field1":{"UInt":305419896},"field2":{"UInt":39612}}}}"#
+        );
+
+        let parsed: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, value);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_value_to_json() {
+        let value = Value::Struct {
+            fields: vec![
+                ("field1".to_string(), Value::UInt(0x12345678)),
+                ("field2".to_string(), Value::UInt(0x9abc)),
+                ("mode".to_string(), Value::Enum("ON".to_string())),
+                ("big".to_string(), Value::UInt(u64::MAX)),
+                ("samples".to_string(), Value::Array(vec![Value::Int(-1)])),
+            ],
+        };
+
+        assert_eq!(
+            serde_json::Value::from(&value),
+            serde_json::json!({
+                "field1": 305419896,
+                "field2": 39612,
+                "mode": "ON",
+                "big": "18446744073709551615",
+                "samples": [-1],
+            })
+        );
+    }
+
+    #[test]
+    fn test_some_ip_length_prefixed_struct_value() {
+        // the sender's struct has a third field, unknown to the receiver
+        let bytes: Vec<u8> = vec![
+            0x0, 0x8, 0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0, 0xff,
+        ];
+        let def = SomeIPType::Struct {
+            fields: vec![
+                ("field1".to_string(), SomeIPType::UInt32),
+                ("field2".to_string(), SomeIPType::UInt16),
+            ],
+            alignment: 1,
+            length_width: Some(16),
+            layout: StructLayout::Sequential,
+        };
+        let (remaining, value) = some_ip_value(&bytes, &def).unwrap();
+
+        assert_eq!(remaining, &[0xff]);
+        assert_eq!(
+            value,
+            Value::Struct {
+                fields: vec![
+                    ("field1".to_string(), Value::UInt(0x12345678)),
+                    ("field2".to_string(), Value::UInt(0x9abc)),
+                ]
+            }
+        );
+
+        // field2 runs past the declared length, and the input ends after it
+        let overrun: Vec<u8> = vec![0x0, 0x4, 0x12, 0x34, 0x56, 0x78, 0x9a];
+        assert_eq!(
+            some_ip_value(&overrun, &def),
+            Err(nom::Err::Error(Error::new(
+                &overrun[6..6],
+                InnerError::Nom(ErrorKind::Eof)
+            )))
+        );
+
+        let tail = SomeIPType::Struct {
+            fields: vec![
+                ("id".to_string(), SomeIPType::UInt8),
+                ("extension".to_string(), SomeIPType::Opaque { length: None }),
+            ],
+            alignment: 1,
+            length_width: Some(8),
+            layout: StructLayout::Sequential,
+        };
+        let bytes: Vec<u8> = vec![0x3, 0x1, 0xaa, 0xbb, 0xff];
+        assert_eq!(
+            some_ip_value(&bytes, &tail),
+            Ok((
+                &[0xff][..],
+                Value::Struct {
+                    fields: vec![
+                        ("id".to_string(), Value::UInt(0x1)),
+                        ("extension".to_string(), Value::Bytes(vec![0xaa, 0xbb])),
+                    ]
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_some_ip_enum_selected_struct_value() {
+        let bytes: Vec<u8> = vec![0x02, 0x12, 0x34, 0x56];
+        let slice = bytes.as_slice();
+        let def = SomeIPType::EnumSelectedStruct {
+            enum_def: Box::new(SomeIPType::Enum {
+                variants: vec![(1, "RESET".to_string()), (2, "STATUS".to_string())],
+                open_enum: false,
+                base: None,
+            }),
+            variant_structs: HashMap::from([
+                (
+                    "RESET".to_string(),
+                    SomeIPType::Struct {
+                        fields: vec![],
+                        alignment: 1,
+                        length_width: None,
+                        layout: StructLayout::Sequential,
+                    },
+                ),
+                (
+                    "STATUS".to_string(),
+                    SomeIPType::Struct {
+                        fields: vec![
+                            ("code".to_string(), SomeIPType::UInt8),
+                            ("detail".to_string(), SomeIPType::UInt16),
+                        ],
+                        alignment: 1,
+                        length_width: None,
+                        layout: StructLayout::Sequential,
+                    },
+                ),
+            ]),
+        };
+        let (remaining, value) = some_ip_value(slice, &def).unwrap();
+
+        assert_eq!(remaining.len(), 0);
+        assert_eq!(
+            value,
+            Value::Struct {
+                fields: vec![
+                    ("variant".to_string(), Value::Enum("STATUS".to_string())),
+                    (
+                        "value".to_string(),
+                        Value::Struct {
+                            fields: vec![
+                                ("code".to_string(), Value::UInt(0x12)),
+                                ("detail".to_string(), Value::UInt(0x3456)),
+                            ]
+                        }
+                    ),
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn test_some_ip_bitfield_value() {
+        // valid 1, mode 011, counter 0110
+        let bytes: Vec<u8> = vec![0b1011_0110];
+        let def = SomeIPType::Bitfield {
+            storage: Box::new(SomeIPType::UInt8),
+            fields: vec![
+                ("valid".to_string(), 1),
+                ("mode".to_string(), 3),
+                ("counter".to_string(), 4),
+            ],
+        };
+        let (remaining, value) = some_ip_value(&bytes, &def).unwrap();
+
+        assert_eq!(remaining.len(), 0);
+        assert_eq!(
+            value,
+            Value::Struct {
+                fields: vec![
+                    ("valid".to_string(), Value::UInt(0b1)),
+                    ("mode".to_string(), Value::UInt(0b011)),
+                    ("counter".to_string(), Value::UInt(0b0110)),
+                ]
+            }
+        );
+
+        let mut bytes_out = Vec::new();
+        encode::encode_value(&value, &def, &mut bytes_out).unwrap();
+        assert_eq!(bytes_out, bytes);
+    }
+
+    #[test]
+    fn test_some_ip_signal_group_value() {
+        // speed 0x2a3 in 0.5 km/h steps, then temperature -3 in °C from +20 °C
+        let bytes: Vec<u8> = vec![0b1010_1000, 0b1111_1101];
+        let def = SomeIPType::SignalGroup {
+            storage: Box::new(SomeIPType::UInt16),
+            signals: vec![
+                Signal {
+                    name: "speed".to_string(),
+                    offset_bits: 0,
+                    width: 10,
+                    signed: false,
+                    factor: 0.5,
+                    offset: 0.0,
+                    unit: Some("km/h".to_string()),
+                },
+                Signal {
+                    name: "temperature".to_string(),
+                    offset_bits: 12,
+                    width: 4,
+                    signed: true,
+                    factor: 1.0,
+                    offset: 20.0,
+                    unit: Some("°C".to_string()),
+                },
+            ],
+        };
+        let (remaining, value) = some_ip_value(&bytes, &def).unwrap();
+
+        assert_eq!(remaining.len(), 0);
+        assert_eq!(
+            value,
+            Value::Struct {
+                fields: vec![
+                    ("speed".to_string(), Value::Float(337.5)),
+                    ("temperature".to_string(), Value::Float(17.0)),
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn test_some_ip_enum_unknown_discriminant() {
+        let bytes: Vec<u8> = vec![0x05];
+        let def = |open_enum| SomeIPType::Enum {
+            variants: vec![(1, "RESET".to_string()), (2, "STATUS".to_string())],
+            open_enum,
+            base: None,
+        };
+
+        assert_eq!(
+            some_ip_value(&bytes, &def(true)),
+            Ok((&[][..], Value::EnumRaw(0x5)))
+        );
+        assert_eq!(
+            some_ip_value(&bytes, &def(false)),
+            Err(nom::Err::Error(Error::new(
+                &bytes,
+                InnerError::UnknownDiscriminant(0x5)
+            )))
+        );
+    }
+
+    #[test]
+    fn test_some_ip_signed_enum() {
+        let bytes: Vec<u8> = vec![0xff, 0x1];
+        let def = SomeIPType::Enum {
+            variants: vec![(-1i64 as u64, "INVALID".to_string()), (1, "OK".to_string())],
+            open_enum: false,
+            base: Some(Box::new(SomeIPType::SInt8)),
+        };
+        let array = SomeIPType::StaticArray {
+            length: 2,
+            element: Box::new(def),
+        };
+        let value = Value::Array(vec![
+            Value::Enum("INVALID".to_string()),
+            Value::Enum("OK".to_string()),
+        ]);
+
+        assert_eq!(some_ip_value(&bytes, &array), Ok((&[][..], value.clone())));
+        let mut out = Vec::new();
+        encode::encode_value(&value, &array, &mut out).unwrap();
+        assert_eq!(out, bytes);
+    }
+
+    #[test]
+    fn test_some_ip_constrained_value() {
+        let def = SomeIPType::Constrained {
+            base: Box::new(SomeIPType::UInt8),
+            min: 0,
+            max: 100,
+        };
+
+        assert_eq!(some_ip_value(&[100], &def), Ok((&[][..], Value::UInt(100))));
+        assert_eq!(
+            some_ip_value(&[200], &def),
+            Err(nom::Err::Error(Error::new(
+                &[200][..],
+                InnerError::ValueOutOfRange(200)
+            )))
+        );
+
+        // above i128::MAX, which no max can allow
+        let def = SomeIPType::Constrained {
+            base: Box::new(SomeIPType::UInt128),
+            min: 0,
+            max: i128::MAX,
+        };
+        let bytes = [0xff; 16];
+        assert_eq!(
+            some_ip_value(&bytes, &def),
+            Err(nom::Err::Error(Error::new(
+                &bytes[..],
+                InnerError::UInt128OutOfRange(u128::MAX)
+            )))
+        );
+    }
+
+    #[test]
+    fn test_some_ip_optional_group_value() {
+        let bytes: Vec<u8> = vec![0b101, 0x12, 0x34, 0x56, 0x78];
+        let def = SomeIPType::OptionalGroup {
+            mask_width: 8,
+            members: vec![
+                ("speed".to_string(), SomeIPType::UInt16),
+                ("heading".to_string(), SomeIPType::UInt32),
+                ("gear".to_string(), SomeIPType::UInt8),
+            ],
+        };
+        let value = Value::Struct {
+            fields: vec![
+                ("speed".to_string(), Value::UInt(0x1234)),
+                ("gear".to_string(), Value::UInt(0x56)),
+            ],
+        };
+
+        assert_eq!(
+            some_ip_value(&bytes, &def),
+            Ok((&[0x78][..], value.clone()))
+        );
+        let mut out = Vec::new();
+        encode::encode_value(&value, &def, &mut out).unwrap();
+        assert_eq!(out, bytes[..4]);
+    }
+
+    #[test]
+    fn test_some_ip_cstring_value() {
+        let def = SomeIPType::CString { coding: None };
+
+        assert_eq!(
+            some_ip_value(b"hi\0rest", &def),
+            Ok((&b"rest"[..], Value::String("hi".to_string())))
+        );
+        assert_eq!(
+            complete::some_ip_value(b"hi", &def),
+            Err(nom::Err::Error(Error::new(
+                &b"hi"[..],
+                InnerError::MissingTerminator
+            )))
+        );
+
+        let utf16 = SomeIPType::CString {
+            coding: Some(StringCoding::Utf16),
+        };
+        assert_eq!(
+            some_ip_value(&[0x0, 0x68, 0x1, 0x0, 0x0, 0x0, 0xff], &utf16),
+            Ok((&[0xff][..], Value::String("h\u{100}".to_string())))
+        );
+    }
+
+    #[test]
+    fn test_some_ip_128_bit_values() {
+        let bytes: Vec<u8> = (0x1..=0x10).collect();
+
+        assert_eq!(
+            some_ip_value(&bytes, &SomeIPType::UInt128),
+            Ok((
+                &[][..],
+                Value::UInt128(0x0102_0304_0506_0708_090a_0b0c_0d0e_0f10)
+            ))
+        );
+        assert_eq!(
+            some_ip_value(&[0xff; 16], &SomeIPType::SInt128),
+            Ok((&[][..], Value::Int128(-1)))
+        );
+        assert_eq!(
+            some_ip_value(&bytes, &SomeIPType::UInt64),
+            Ok((&bytes[8..], Value::UInt(0x0102_0304_0506_0708)))
+        );
+    }
+
+    #[test]
+    fn test_some_ip_value_lenient() {
+        let bytes: Vec<u8> = vec![0x2, 0x2, 0x0, 0x41, 0xff];
+        let def = SomeIPType::Struct {
+            fields: vec![
+                ("enabled".to_string(), SomeIPType::Bool),
+                (
+                    "label".to_string(),
+                    SomeIPType::DynamicString {
+                        length_width: 8,
+                        coding: Some(StringCoding::Utf16),
+                    },
+                ),
+            ],
+            alignment: 1,
+            length_width: None,
+            layout: StructLayout::Sequential,
+        };
+        let (result, warnings) = some_ip_value_lenient(&bytes, &def);
+
+        assert_eq!(
+            result,
+            Ok((
+                &[0xff][..],
+                Value::Struct {
+                    fields: vec![
+                        ("enabled".to_string(), Value::Bool(true)),
+                        ("label".to_string(), Value::String("A".to_string())),
+                    ]
+                }
+            ))
+        );
+        assert_eq!(
+            warnings,
+            [
+                ParseWarning::NonCanonicalBool {
+                    offset: 0,
+                    value: 0x2
+                },
+                ParseWarning::MissingBom { offset: 2 },
+                ParseWarning::TrailingBytes(1),
+            ]
+        );
+
+        let (result, warnings) = some_ip_value_lenient(&[0x2], &SomeIPType::Bool);
+        assert_eq!(result, Ok((&[][..], Value::Bool(true))));
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            some_ip_value(&[0x2], &SomeIPType::Bool),
+            Err(nom::Err::Error(Error::new(
+                &[0x2],
+                InnerError::InvalidBool(0x2)
+            )))
+        );
+    }
+
+    #[test]
+    fn test_some_ip_message() {
+        let bytes: Vec<u8> = vec![
+            0xff, 0xff, 0x81, 0x0, 0x0, 0x0, 0x0, 0x30, 0x0, 0x0, 0x0, 0x3, 0x1, 0x1, 0x2, 0x0,
+            0xc0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x10, 0x1, 0x0, 0x0, 0x10, 0x0, 0xeb, 0x0, 0x0,
+            0x1, 0x0, 0x0, 0x1e, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xc, 0x0, 0x9, 0x4, 0x0, 0xc0,
+            0xa8, 0x58, 0x49, 0x0, 0x11, 0xc3, 0x50,
+        ];
+        let slice = bytes.as_slice();
+        let def = SomeIPType::Struct {
+            fields: vec![
+                ("flags".to_string(), SomeIPType::UInt8),
+                (
+                    "reserved".to_string(),
+                    SomeIPType::StaticArray {
+                        length: 3,
+                        element: Box::new(SomeIPType::UInt8),
+                    },
+                ),
+                (
+                    "entries".to_string(),
+                    SomeIPType::DynamicArray {
+                        length_width: 32,
+                        element: Box::new(SomeIPType::UInt8),
+                        length_unit: LengthUnit::Elements,
+                        min_length: None,
+                        max_length: None,
+                    },
+                ),
+                (
+                    "options".to_string(),
+                    SomeIPType::DynamicArray {
+                        length_width: 32,
+                        element: Box::new(SomeIPType::UInt8),
+                        length_unit: LengthUnit::Elements,
+                        min_length: None,
+                        max_length: None,
+                    },
+                ),
+            ],
+            alignment: 1,
+            length_width: None,
+            layout: StructLayout::Sequential,
+        };
+        let bytes_value =
+            |bytes: &[u8]| Value::Array(bytes.iter().map(|b| Value::UInt((*b).into())).collect());
+        let (remaining, (header, value)) = some_ip_message(slice, &def).unwrap();
+
+        assert_eq!(remaining.len(), 0);
+        assert_eq!(header.message_type, SomeIPMessageType::Notification());
+        assert_eq!(
+            value,
+            Value::Struct {
+                fields: vec![
+                    ("flags".to_string(), Value::UInt(0xc0)),
+                    ("reserved".to_string(), bytes_value(&[0x0, 0x0, 0x0])),
+                    ("entries".to_string(), bytes_value(&bytes[24..40])),
+                    ("options".to_string(), bytes_value(&bytes[44..56])),
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn test_some_ip_frame() {
+        let bytes: Vec<u8> = vec![
+            0x12, 0x34, 0x00, 0x01, 0x0, 0x0, 0x0, 0x0c, 0x0, 0x1, 0x0, 0x1, 0x1, 0x1, 0x0, 0x0,
+            0xde, 0xad, 0xbe, 0xef, 0xca, 0xfe,
+        ];
+        let (remaining, message) = some_ip_frame(&bytes).unwrap();
+
+        assert_eq!(message.payload.len(), message.header.length as usize - 8);
+        assert_eq!(message.payload, &[0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(remaining, &[0xca, 0xfe]);
+        assert!(matches!(
+            some_ip_frame(&bytes[..18]),
+            Err(nom::Err::Incomplete(_))
+        ));
+    }
+
+    #[test]
+    fn test_some_ip_message_payload_bounded_by_length() {
+        // declared length 12 leaves a 4 byte payload, followed by 2 bytes of the next message
+        let bytes: Vec<u8> = vec![
+            0x12, 0x34, 0x00, 0x01, 0x0, 0x0, 0x0, 0x0c, 0x0, 0x1, 0x0, 0x1, 0x1, 0x1, 0x0, 0x0,
+            0xde, 0xad, 0xbe, 0xef, 0xca, 0xfe,
+        ];
+        let slice = bytes.as_slice();
+        let def = SomeIPType::UInt64;
+        let result = some_ip_message(slice, &def);
+
+        assert!(matches!(
+            result,
+            Err(nom::Err::Error(Error {
+                error: InnerError::Nom(ErrorKind::Eof),
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_skip_payload() {
+        let bytes: Vec<u8> = vec![
+            0x43, 0x21, 0x0, 0x1, 0x0, 0x0, 0x0, 0xb, 0x0, 0x1, 0x0, 0x1, 0x1, 0x1, 0x0, 0x0, 0xaa,
+            0xbb, 0xcc, 0x12, 0x34, 0x0, 0x2, 0x0, 0x0, 0x0, 0x8, 0x0, 0x1, 0x0, 0x2, 0x1, 0x1,
+            0x0, 0x0,
+        ];
+        let (i1, header) = some_ip_header(&bytes).unwrap();
+        let (i2, payload) = skip_payload(i1, &header).unwrap();
+
+        assert_eq!(payload, [0xaa, 0xbb, 0xcc]);
+        assert_eq!(i2, &bytes[19..]);
+        let (_, next) = some_ip_header(i2).unwrap();
+        assert_eq!(next.key().service_id, ServiceId(0x1234));
+        assert!(matches!(
+            skip_payload(&i1[..2], &header),
+            Err(nom::Err::Incomplete(_))
+        ));
+    }
+
+    #[test]
+    fn test_some_ip_message_trailing() {
+        let mut bytes: Vec<u8> = vec![
+            0x12, 0x34, 0x00, 0x01, 0x0, 0x0, 0x0, 0xa, 0x0, 0x1, 0x0, 0x1, 0x1, 0x1, 0x2, 0x0,
+            0xab, 0xcd,
+        ];
+        let options = MessageOptions::default();
+        let (_, message) =
+            some_ip_message_with_options(&bytes, &SomeIPType::UInt16, &options).unwrap();
+        assert_eq!(message.value, Value::UInt(0xabcd));
+        assert!(message.trailing.is_empty());
+
+        bytes[7] = 0xd;
+        bytes.extend([0x0, 0x0, 0x0]);
+        let (_, message) =
+            some_ip_message_with_options(&bytes, &SomeIPType::UInt16, &options).unwrap();
+        assert_eq!(message.value, Value::UInt(0xabcd));
+        assert_eq!(message.trailing, [0x0, 0x0, 0x0]);
+
+        let strict = MessageOptions {
+            strict_trailing: true,
+        };
+        let Err(nom::Err::Error(error)) =
+            some_ip_message_with_options(&bytes, &SomeIPType::UInt16, &strict)
+        else {
+            panic!("trailing bytes accepted");
+        };
+        assert_eq!(error.error, InnerError::TrailingBytes(3));
+        assert_eq!(error.offset(&bytes), Some(18));
+    }
+
+    #[test]
+    fn test_some_ip_message_validated() {
+        // declares a 6 byte payload for a 4 byte schema
+        let mut bytes: Vec<u8> = vec![
+            0x12, 0x34, 0x00, 0x01, 0x0, 0x0, 0x0, 0x0e, 0x0, 0x1, 0x0, 0x1, 0x1, 0x1, 0x0, 0x0,
+            0xde, 0xad, 0xbe, 0xef, 0xca, 0xfe,
+        ];
+        let def = SomeIPType::UInt32;
+        let Err(nom::Err::Error(error)) = some_ip_message_validated(&bytes, &def) else {
+            panic!("length mismatch accepted");
+        };
+        assert_eq!(
+            error.error,
+            InnerError::SchemaLengthMismatch {
+                payload_length: 6,
+                schema_size: 4
+            }
+        );
+        assert_eq!(error.offset(&bytes), Some(4));
+        assert!(some_ip_message(&bytes, &def).is_ok());
+
+        bytes[7] = 0x0c;
+        let (_, (_, value)) = some_ip_message_validated(&bytes, &def).unwrap();
+        assert_eq!(value, Value::UInt(0xdeadbeef));
+    }
+
+    #[test]
+    fn test_some_ip_value_node_limit() {
+        let bytes: Vec<u8> = vec![0x0; 64];
+        let slice = bytes.as_slice();
+        let def = SomeIPType::StaticArray {
+            length: 8,
+            element: Box::new(SomeIPType::Struct {
+                fields: vec![
+                    ("a".to_string(), SomeIPType::UInt32),
+                    ("b".to_string(), SomeIPType::UInt32),
+                ],
+                alignment: 1,
+                length_width: None,
+                layout: StructLayout::Sequential,
+            }),
+        };
+        let options = DecodeOptions {
+            max_nodes: Some(16),
+            ..Default::default()
+        };
+        let result = some_ip_value_with_options(slice, &def, &options);
+
+        assert!(matches!(
+            result,
+            Err(nom::Err::Failure(Error {
+                error: InnerError::NodeLimitExceeded,
+                ..
+            }))
+        ));
+
+        // 1 array + 8 structs + 16 fields
+        let options = DecodeOptions {
+            max_nodes: Some(25),
+            ..Default::default()
+        };
+        assert!(some_ip_value_with_options(slice, &def, &options).is_ok());
+    }
+
+    #[test]
+    fn test_some_ip_registry_ref_value() {
+        let bytes: Vec<u8> = vec![0x0, 0x1, 0x0, 0x2, 0x0, 0x3, 0x0, 0x4];
+        let mut registry = TypeRegistry::new();
+        registry.register(
+            "Point",
+            SomeIPType::Struct {
+                fields: vec![
+                    ("x".to_string(), SomeIPType::UInt16),
+                    ("y".to_string(), SomeIPType::UInt16),
+                ],
+                alignment: 1,
+                length_width: None,
+                layout: StructLayout::Sequential,
+            },
+        );
+        let def = SomeIPType::Struct {
+            fields: vec![
+                ("from".to_string(), SomeIPType::Ref("Point".to_string())),
+                ("to".to_string(), SomeIPType::Ref("Point".to_string())),
+            ],
+            alignment: 1,
+            length_width: None,
+            layout: StructLayout::Sequential,
+        };
+        let point = |x, y| Value::Struct {
+            fields: vec![
+                ("x".to_string(), Value::UInt(x)),
+                ("y".to_string(), Value::UInt(y)),
+            ],
+        };
+
+        assert_eq!(
+            some_ip_value_with_registry(&bytes, &def, &registry, &DecodeOptions::default()),
+            Ok((
+                &[][..],
+                Value::Struct {
+                    fields: vec![
+                        ("from".to_string(), point(1, 2)),
+                        ("to".to_string(), point(3, 4)),
+                    ]
+                }
+            ))
+        );
+        assert!(matches!(
+            some_ip_value(&bytes, &def),
+            Err(nom::Err::Error(Error {
+                error: InnerError::UnknownType(_),
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_some_ip_registry_ref_cycle() {
+        let mut registry = TypeRegistry::new();
+        registry.register(
+            "Node",
+            SomeIPType::Struct {
+                fields: vec![("next".to_string(), SomeIPType::Ref("Node".to_string()))],
+                alignment: 1,
+                length_width: None,
+                layout: StructLayout::Sequential,
+            },
+        );
+        let def = SomeIPType::Ref("Node".to_string());
+
+        assert!(matches!(
+            some_ip_value_with_registry(&[], &def, &registry, &DecodeOptions::default()),
+            Err(nom::Err::Failure(Error {
+                error: InnerError::DepthLimitExceeded,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_some_ip_unify_integers() {
+        let bytes: Vec<u8> = vec![0x7, 0xff, 0xfe];
+        let def = SomeIPType::Struct {
+            fields: vec![
+                ("a".to_string(), SomeIPType::UInt8),
+                ("b".to_string(), SomeIPType::SInt16),
+            ],
+            alignment: 1,
+            length_width: None,
+            layout: StructLayout::Sequential,
+        };
+        let options = DecodeOptions {
+            unify_integers: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            some_ip_value_with_options(bytes.as_slice(), &def, &options),
+            Ok((
+                &[][..],
+                Value::Struct {
+                    fields: vec![
+                        ("a".to_string(), Value::Int(7)),
+                        ("b".to_string(), Value::Int(-2)),
+                    ]
+                }
+            ))
+        );
+
+        let bytes = u64::MAX.to_be_bytes();
+        assert!(matches!(
+            some_ip_value_with_options(&bytes, &SomeIPType::UInt64, &options),
+            Err(nom::Err::Error(Error {
+                error: InnerError::IntegerOverflow(u64::MAX),
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_some_ip_aligned_struct_value() {
+        let bytes: Vec<u8> = vec![0x12, 0x0, 0x0, 0x0, 0x34, 0x56, 0x78, 0x9a];
+        let slice = bytes.as_slice();
+        let def = SomeIPType::Struct {
+            fields: vec![
+                ("field1".to_string(), SomeIPType::UInt8),
+                ("field2".to_string(), SomeIPType::UInt32),
+            ],
+            alignment: 4,
+            length_width: None,
+            layout: StructLayout::Sequential,
+        };
+        let (remaining, value) = some_ip_value(slice, &def).unwrap();
+
+        assert_eq!(remaining.len(), 0);
+        assert_eq!(
+            value,
+            Value::Struct {
+                fields: vec![
+                    ("field1".to_string(), Value::UInt(0x12)),
+                    ("field2".to_string(), Value::UInt(0x3456789a))
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn test_some_ip_dynamic_array_length_units() {
+        let strings = |length_unit| SomeIPType::DynamicArray {
+            length_width: 8,
+            element: Box::new(SomeIPType::DynamicString {
+                length_width: 8,
+                coding: None,
+            }),
+            length_unit,
+            min_length: None,
+            max_length: None,
+        };
+        let expected = Value::Array(vec![
+            Value::String("hi".to_string()),
+            Value::String("abcd".to_string()),
+        ]);
+
+        let bytes: Vec<u8> = vec![0x2, 0x2, b'h', b'i', 0x4, b'a', b'b', b'c', b'd'];
+        let def = strings(LengthUnit::Elements);
+        let (remaining, value) = some_ip_value(bytes.as_slice(), &def).unwrap();
+        assert_eq!(remaining.len(), 0);
+        assert_eq!(value, expected);
+
+        let bytes: Vec<u8> = vec![0x8, 0x2, b'h', b'i', 0x4, b'a', b'b', b'c', b'd'];
+        let def = strings(LengthUnit::Bytes);
+        let (remaining, value) = some_ip_value(bytes.as_slice(), &def).unwrap();
+        assert_eq!(remaining.len(), 0);
+        assert_eq!(value, expected);
+
+        // the second string ends two bytes after the declared 5 bytes
+        let bytes: Vec<u8> = vec![0x5, 0x2, b'h', b'i', 0x4, b'a', b'b', b'c', b'd'];
+        let result = some_ip_value(bytes.as_slice(), &def);
+        assert!(matches!(
+            result,
+            Err(nom::Err::Error(Error {
+                error: InnerError::ArrayBoundaryCrossed,
+                ..
+            }))
+        ));
+
+        let bytes = [0xff; 9];
+        let def = SomeIPType::DynamicArray {
+            length_width: 64,
+            element: Box::new(SomeIPType::UInt8),
+            length_unit: LengthUnit::Bytes,
+            min_length: None,
+            max_length: None,
+        };
+        assert!(matches!(
+            some_ip_value(&bytes, &def),
+            Err(nom::Err::Incomplete(_))
+        ));
+        assert!(matches!(
+            complete::some_ip_value(&bytes, &def),
+            Err(nom::Err::Error(Error {
+                error: InnerError::Nom(ErrorKind::Eof),
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_some_ip_dynamic_array_length_bounds() {
+        let bounded = |length_unit| SomeIPType::DynamicArray {
+            length_width: 8,
+            element: Box::new(SomeIPType::UInt16),
+            length_unit,
+            min_length: Some(2),
+            max_length: Some(3),
+        };
+        let def = bounded(LengthUnit::Elements);
+
+        let bytes: Vec<u8> = vec![0x2, 0x0, 0xa, 0x0, 0xb];
+        assert_eq!(
+            some_ip_value(&bytes, &def),
+            Ok((
+                &[][..],
+                Value::Array(vec![Value::UInt(0xa), Value::UInt(0xb)])
+            ))
+        );
+
+        let bytes: Vec<u8> = vec![0x1, 0x0, 0xa];
+        assert_eq!(
+            some_ip_value(&bytes, &def),
+            Err(nom::Err::Error(Error::new(
+                &bytes,
+                InnerError::ArrayLengthConstraint(1)
+            )))
+        );
+
+        // rejected before the elements, which are missing, are decoded
+        let bytes: Vec<u8> = vec![0x4, 0x0, 0xa];
+        assert_eq!(
+            some_ip_value(&bytes, &def),
+            Err(nom::Err::Error(Error::new(
+                &bytes,
+                InnerError::ArrayLengthConstraint(4)
+            )))
+        );
+
+        let def = bounded(LengthUnit::Bytes);
+        let bytes: Vec<u8> = vec![0x8, 0x0, 0xa, 0x0, 0xb, 0x0, 0xc, 0x0, 0xd];
+        assert_eq!(
+            some_ip_value(&bytes, &def),
+            Err(nom::Err::Error(Error::new(
+                &bytes,
+                InnerError::ArrayLengthConstraint(4)
+            )))
+        );
+    }
+
+    #[test]
+    fn test_some_ip_invalid_length_width() {
+        let bytes: Vec<u8> = vec![0x0, 0x0, 0x2, 0xa, 0xb];
+        let def = SomeIPType::DynamicArray {
+            length_width: 24,
+            element: Box::new(SomeIPType::UInt8),
+            length_unit: LengthUnit::Elements,
+            min_length: None,
+            max_length: None,
+        };
+
+        assert_eq!(
+            some_ip_value(&bytes, &def),
+            Err(nom::Err::Error(Error::new(
+                &bytes,
+                InnerError::InvalidLengthWidth(24)
+            )))
+        );
+        assert_eq!(
+            schema::validate_schema(&def),
+            Err(schema::SchemaError::InvalidLengthWidth(24))
+        );
+    }
+
+    #[test]
+    fn test_some_ip_nested_dynamic_array_value() {
+        // each row carries its own length after the outer length
+        let bytes: Vec<u8> = vec![0x2, 0x3, 0x1, 0x2, 0x3, 0x2, 0x4, 0x5];
+        let def = SomeIPType::DynamicArray {
+            length_width: 8,
+            element: Box::new(SomeIPType::DynamicArray {
+                length_width: 8,
+                element: Box::new(SomeIPType::UInt8),
+                length_unit: LengthUnit::Elements,
+                min_length: None,
+                max_length: None,
+            }),
+            length_unit: LengthUnit::Elements,
+            min_length: None,
+            max_length: None,
+        };
+        let row = |values: &[u64]| Value::Array(values.iter().map(|v| Value::UInt(*v)).collect());
+
+        assert_eq!(
+            some_ip_value(bytes.as_slice(), &def),
+            Ok((&[][..], Value::Array(vec![row(&[1, 2, 3]), row(&[4, 5])])))
+        );
+    }
+
+    #[test]
+    fn test_some_ip_zero_length_dynamic_values() {
+        let bytes: Vec<u8> = vec![0x0, 0x0, 0xab];
+        for length_unit in [LengthUnit::Elements, LengthUnit::Bytes] {
+            let def = SomeIPType::DynamicArray {
+                length_width: 16,
+                element: Box::new(SomeIPType::UInt32),
+                length_unit,
+                min_length: None,
+                max_length: None,
+            };
+            assert_eq!(
+                some_ip_value(&bytes, &def),
+                Ok((&[0xab][..], Value::Array(vec![])))
+            );
+        }
+        for coding in [None, Some(StringCoding::Utf8), Some(StringCoding::Utf16)] {
+            let def = SomeIPType::DynamicString {
+                length_width: 16,
+                coding,
+            };
+            assert_eq!(
+                some_ip_value(&bytes, &def),
+                Ok((&[0xab][..], Value::String(String::new())))
+            );
+            // nothing to wait for after the length field
+            assert_eq!(
+                some_ip_value(&bytes[..2], &def),
+                Ok((&[][..], Value::String(String::new())))
+            );
+        }
+    }
+
+    #[test]
+    fn test_some_ip_arrays_of_length_prefixed_elements() {
+        let bytes: Vec<u8> = vec![
+            0x3, 0x1, b'a', 0x3, b'b', b'c', b'd', 0x0, 0x2, 0x1, 0xa, 0x2, 0xb, 0xc, 0xff,
+        ];
+        let string = || SomeIPType::DynamicString {
+            length_width: 8,
+            coding: None,
+        };
+        let strings = Value::Array(vec![
+            Value::String("a".to_string()),
+            Value::String("bcd".to_string()),
+            Value::String(String::new()),
+        ]);
+        let counted = SomeIPType::DynamicArray {
+            length_width: 8,
+            element: Box::new(string()),
+            length_unit: LengthUnit::Elements,
+            min_length: None,
+            max_length: None,
+        };
+        assert_eq!(
+            some_ip_value(&bytes, &counted),
+            Ok((&bytes[8..], strings.clone()))
+        );
+        let mut sized = bytes.clone();
+        sized[0] = 0x7;
+        let sized_def = SomeIPType::DynamicArray {
+            length_width: 8,
+            element: Box::new(string()),
+            length_unit: LengthUnit::Bytes,
+            min_length: None,
+            max_length: None,
+        };
+        assert_eq!(
+            some_ip_value(&sized, &sized_def),
+            Ok((&sized[8..], strings))
+        );
+
+        let rows = SomeIPType::StaticArray {
+            length: 2,
+            element: Box::new(SomeIPType::DynamicArray {
+                length_width: 8,
+                element: Box::new(SomeIPType::UInt8),
+                length_unit: LengthUnit::Elements,
+                min_length: None,
+                max_length: None,
+            }),
+        };
+        let row = |values: &[u64]| Value::Array(values.iter().map(|v| Value::UInt(*v)).collect());
+        assert_eq!(
+            some_ip_value(&bytes[9..], &rows),
+            Ok((
+                &[0xff][..],
+                Value::Array(vec![row(&[0xa]), row(&[0xb, 0xc])])
+            ))
+        );
+
+        // elements taking no bytes must not spin forever
+        let empty = SomeIPType::DynamicArray {
+            length_width: 8,
+            element: Box::new(SomeIPType::Struct {
+                fields: vec![],
+                alignment: 1,
+                length_width: None,
+                layout: StructLayout::Sequential,
+            }),
+            length_unit: LengthUnit::Bytes,
+            min_length: None,
+            max_length: None,
+        };
+        assert!(some_ip_value(&[0x1, 0x0], &empty).is_err());
+    }
+
+    #[test]
+    fn test_someip_length_framed() {
+        let bytes: Vec<u8> = vec![
+            0x0, 0x0, 0x0, 0x14, 0x12, 0x34, 0x00, 0x01, 0x0, 0x0, 0x0, 0x0c, 0x0, 0x1, 0x0, 0x1,
+            0x1, 0x1, 0x0, 0x0, 0xde, 0xad, 0xbe, 0xef, 0xca, 0xfe,
+        ];
+        let slice = bytes.as_slice();
+        let (remaining, message) = someip_length_framed(slice).unwrap();
+
+        assert_eq!(remaining, &[0xca, 0xfe]);
+        assert_eq!(message.header.service_id, 0x1234);
+        assert_eq!(message.header.method_id, 0x0001);
+        assert_eq!(message.header.message_type, SomeIPMessageType::Request());
+        assert_eq!(message.payload, &[0xde, 0xad, 0xbe, 0xef]);
+
+        let mut bytes = bytes;
+        bytes[3] = 0x16;
+        let result = someip_length_framed(bytes.as_slice());
+        assert!(matches!(
+            result,
+            Err(nom::Err::Error(Error {
+                error: InnerError::LengthMismatch {
+                    declared: 0x16,
+                    actual: 0x14
+                },
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_someip_with_timestamp() {
+        let bytes: Vec<u8> = vec![
+            0x0, 0x0, 0x1, 0x8c, 0x3f, 0x2a, 0x10, 0x0, 0x12, 0x34, 0x00, 0x01, 0x0, 0x0, 0x0,
+            0x0a, 0x0, 0x1, 0x0, 0x1, 0x1, 0x1, 0x2, 0x0, 0xca, 0xfe,
+        ];
+        let (remaining, (timestamp_ns, message)) = someip_with_timestamp(&bytes).unwrap();
+
+        assert_eq!(remaining.len(), 0);
+        assert_eq!(timestamp_ns, 0x18c_3f2a_1000);
+        assert_eq!(message.header.service_id, 0x1234);
+        assert_eq!(
+            message.header.message_type,
+            SomeIPMessageType::Notification()
+        );
+        assert_eq!(message.payload, &[0xca, 0xfe]);
+    }
+
+    #[test]
+    fn test_some_ip_utf16_static_string_value() {
+        let bytes: Vec<u8> = vec![0xfe, 0xff, 0x0, b'h', 0x0, b'i'];
+        let def = SomeIPType::StaticString {
+            length: 6,
+            coding: Some(StringCoding::Utf16),
+        };
+        let (remaining, value) = some_ip_value(bytes.as_slice(), &def).unwrap();
+
+        assert_eq!(remaining.len(), 0);
+        assert_eq!(value, Value::String("hi".to_string()));
+
+        let def = SomeIPType::StaticString {
+            length: 5,
+            coding: Some(StringCoding::Utf16),
+        };
+        let result = some_ip_value(bytes.as_slice(), &def);
+
+        assert!(matches!(
+            result,
+            Err(nom::Err::Error(Error {
+                error: InnerError::InvalidStringLength(5),
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_some_ip_string_default_coding() {
+        let bytes: Vec<u8> = vec![0x0, 0x4, 0x0, 0x41, 0x0, 0x42];
+        let def = SomeIPType::DynamicString {
+            length_width: 16,
+            coding: None,
+        };
+
+        assert_eq!(
+            some_ip_value(&bytes, &def),
+            Ok((&[][..], Value::String("\0A\0B".to_string())))
+        );
+        let options = DecodeOptions {
+            default_coding: StringCoding::Utf16,
+            ..Default::default()
+        };
+        assert_eq!(
+            some_ip_value_with_options(&bytes, &def, &options),
+            Ok((&[][..], Value::String("AB".to_string())))
+        );
+        // an explicit coding wins over the default
+        let utf8 = SomeIPType::DynamicString {
+            length_width: 16,
+            coding: Some(StringCoding::Utf8),
+        };
+        assert_eq!(
+            some_ip_value_with_options(&bytes, &utf8, &options),
+            Ok((&[][..], Value::String("\0A\0B".to_string())))
+        );
+    }
+
+    #[test]
+    fn test_some_ip_string_borrowed() {
+        let bytes: Vec<u8> = vec![0x0, 0x5, b'h', b'e', b'l', b'l', b'o', 0xff];
+        let def = SomeIPType::DynamicString {
+            length_width: 16,
+            coding: None,
+        };
+        let (remaining, str) = some_ip_string(bytes.as_slice(), &def).unwrap();
+
+        assert_eq!(remaining, &[0xff]);
+        assert_eq!(str, "hello");
+        let Cow::Borrowed(borrowed) = str else {
+            panic!("ASCII string was copied");
+        };
+        assert!(std::ptr::eq(borrowed.as_ptr(), &bytes[2]));
+    }
+
+    #[test]
+    fn test_value_approx_eq() {
+        let nan = Value::Float(f64::NAN);
+        assert_ne!(nan, Value::Float(f64::NAN));
+        assert!(nan.approx_eq(&Value::Float(f64::NAN), 0.0));
+
+        let a = Value::Struct {
+            fields: vec![
+                ("speed".to_string(), Value::Float(12.5)),
+                ("ids".to_string(), Value::Array(vec![Value::UInt(1)])),
+            ],
+        };
+        let b = Value::Struct {
+            fields: vec![
+                ("speed".to_string(), Value::Float(12.5000001)),
+                ("ids".to_string(), Value::Array(vec![Value::UInt(1)])),
+            ],
+        };
+        assert_ne!(a, b);
+        assert!(a.approx_eq(&b, 1e-6));
+        assert!(!a.approx_eq(&b, 1e-9));
+        assert!(!Value::Float(1.0).approx_eq(&Value::Float(f64::INFINITY), 1e-6));
+    }
+
+    #[test]
+    fn test_fixed_size() {
+        let header = SomeIPType::Struct {
+            fields: vec![
+                ("id".to_string(), SomeIPType::UInt16),
+                (
+                    "name".to_string(),
+                    SomeIPType::StaticString {
+                        length: 8,
+                        coding: None,
+                    },
+                ),
+                (
+                    "samples".to_string(),
+                    SomeIPType::StaticArray {
+                        length: 3,
+                        element: Box::new(SomeIPType::Float32),
+                    },
+                ),
+            ],
+            alignment: 1,
+            length_width: None,
+            layout: StructLayout::Sequential,
+        };
+        let dynamic = SomeIPType::Struct {
+            fields: vec![
+                ("id".to_string(), SomeIPType::UInt16),
+                (
+                    "data".to_string(),
+                    SomeIPType::DynamicArray {
+                        length_width: 32,
+                        element: Box::new(SomeIPType::UInt8),
+                        length_unit: LengthUnit::Elements,
+                        min_length: None,
+                        max_length: None,
+                    },
+                ),
+            ],
+            alignment: 1,
+            length_width: None,
+            layout: StructLayout::Sequential,
+        };
+
+        assert_eq!(SomeIPType::UInt32.fixed_size(), Some(4));
+        assert_eq!(header.fixed_size(), Some(22));
+        assert_eq!(dynamic.fixed_size(), None);
+    }
 
     #[test]
-    fn test_some_ip_header() {
-        let bytes: Vec<u8> = vec![
-            0xff, 0xff, 0x81, 0x0, 0x0, 0x0, 0x0, 0x30, 0x0, 0x0, 0x0, 0x3, 0x1, 0x1, 0x2, 0x0, 0xc0,
-            0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x10, 0x1, 0x0, 0x0, 0x10, 0x0, 0xeb, 0x0, 0x0, 0x1, 0x0,
-            0x0, 0x1e, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xc, 0x0, 0x9, 0x4, 0x0, 0xc0, 0xa8, 0x58,
-            0x49, 0x0, 0x11, 0xc3, 0x50,
-        ];
-        let slice = bytes.as_slice();
-        let (_payload, header) = some_ip_header(slice).unwrap();
+    fn test_some_ip_static_array_fixed_stride() {
+        let bytes: Vec<u8> = (0..600).map(|i| i as u8).collect();
+        let element = SomeIPType::Struct {
+            fields: vec![
+                ("id".to_string(), SomeIPType::UInt16),
+                ("value".to_string(), SomeIPType::SInt32),
+            ],
+            alignment: 1,
+            length_width: None,
+            layout: StructLayout::Sequential,
+        };
+        assert_eq!(element.fixed_size(), Some(6));
+        let def = SomeIPType::StaticArray {
+            length: 100,
+            element: Box::new(element),
+        };
+        let SomeIPType::StaticArray { element, .. } = &def else {
+            unreachable!()
+        };
 
-        assert_eq!(header.service_id, 0xffff);
-        assert_eq!(header.method_id, 0x8100);
-        assert_eq!(header.length, 0x30);
-        assert_eq!(header.client_id, 0x0);
-        assert_eq!(header.session_id, 0x3);
-        assert_eq!(header.protocol_version, 0x1);
-        assert_eq!(header.interface_version, 0x1);
-        assert_eq!(header.message_type, SomeIPMessageType::Notification());
-        assert_eq!(header.return_code, 0x0);
+        let options = DecodeOptions::default();
+        let naive = someip_array(
+            &bytes,
+            element,
+            100,
+            &mut DecodeState::new(&options, false, &bytes),
+        );
+        let (remaining, value) = some_ip_value(&bytes, &def).unwrap();
+
+        assert_eq!(remaining.len(), 0);
+        assert_eq!(Ok((remaining, value)), naive);
+        assert!(matches!(
+            some_ip_value(&bytes[..599], &def),
+            Err(nom::Err::Incomplete(_))
+        ));
+
+        // warnings and errors point into the input, as for any other array
+        let bools = [0x2, 0x1, 0x1];
+        let def = SomeIPType::StaticArray {
+            length: 3,
+            element: Box::new(SomeIPType::Bool),
+        };
+        let mut state = DecodeState::new(&options, false, &bools);
+        state.warnings = Some(Vec::new());
+        let naive = someip_array(&bools, &SomeIPType::Bool, 3, &mut state);
+        let (result, warnings) = some_ip_value_lenient(&bools, &def);
+        assert_eq!(result, naive);
+        assert_eq!(warnings, state.warnings.unwrap());
+        assert_eq!(
+            warnings,
+            [ParseWarning::NonCanonicalBool {
+                offset: 0,
+                value: 0x2
+            }]
+        );
+
+        let discriminants = [0x1, 0x7];
+        let enum_def = || SomeIPType::Enum {
+            variants: vec![(1, "on".to_string())],
+            open_enum: false,
+            base: None,
+        };
+        let def = SomeIPType::StaticArray {
+            length: 2,
+            element: Box::new(enum_def()),
+        };
+        let naive = someip_array(
+            &discriminants,
+            &enum_def(),
+            2,
+            &mut DecodeState::new(&options, false, &discriminants),
+        );
+        let result = some_ip_value(&discriminants, &def);
+        assert_eq!(result, naive);
+        let Err(nom::Err::Error(error)) = result else {
+            panic!("unknown discriminant parsed");
+        };
+        assert_eq!(error.offset(&discriminants), Some(1));
     }
 
     #[test]
-    fn test_some_ip_uint32_value() {
-        let bytes: Vec<u8> = vec![0x12, 0x34, 0x56, 0x78];
-        let slice = bytes.as_slice();
-        let def = SomeIPType::UInt32;
-        let (remaining, value) = some_ip_value(slice, &def).unwrap();
+    fn test_some_ip_sentinel_array_value() {
+        let bytes: Vec<u8> = vec![0x0, 0x1, 0x12, 0x34, 0x0, 0x0, 0xff];
+        let def = SomeIPType::SentinelArray {
+            element: Box::new(SomeIPType::UInt16),
+            sentinel: Value::UInt(0),
+        };
+        let (remaining, value) = some_ip_value(bytes.as_slice(), &def).unwrap();
 
-        assert_eq!(remaining.len(), 0);
-        assert_eq!(value, Value::UInt(0x12345678));
+        assert_eq!(remaining, &[0xff]);
+        assert_eq!(
+            value,
+            Value::Array(vec![Value::UInt(0x1), Value::UInt(0x1234)])
+        );
+
+        let def = SomeIPType::SentinelArray {
+            element: Box::new(SomeIPType::Opaque { length: Some(0) }),
+            sentinel: Value::Bytes(vec![0x0]),
+        };
+        assert_eq!(
+            some_ip_value(&bytes, &def),
+            Err(nom::Err::Error(Error::new(
+                &bytes,
+                InnerError::Nom(ErrorKind::Many0)
+            )))
+        );
     }
 
     #[test]
-    fn test_some_ip_struct_value() {
-        let bytes: Vec<u8> = vec![0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc];
+    fn test_error_offset() {
+        // the payload ends one byte into field2
+        let bytes: Vec<u8> = vec![
+            0x12, 0x34, 0x00, 0x01, 0x0, 0x0, 0x0, 0x0d, 0x0, 0x1, 0x0, 0x1, 0x1, 0x1, 0x0, 0x0,
+            0xde, 0xad, 0xbe, 0xef, 0xca,
+        ];
         let slice = bytes.as_slice();
         let def = SomeIPType::Struct {
             fields: vec![
                 ("field1".to_string(), SomeIPType::UInt32),
                 ("field2".to_string(), SomeIPType::UInt16),
             ],
+            alignment: 1,
+            length_width: None,
+            layout: StructLayout::Sequential,
         };
-        let (remaining, value) = some_ip_value(slice, &def).unwrap();
+        let Err(nom::Err::Error(error)) = some_ip_message(slice, &def) else {
+            panic!("truncated payload parsed");
+        };
+
+        assert_eq!(error.offset(slice), Some(20));
+        assert_eq!(error.offset(&bytes[..4]), None);
+    }
+
+    #[test]
+    fn test_error_annotated_dump() {
+        let bytes: Vec<u8> = vec![
+            0x12, 0x34, 0x00, 0x01, 0x0, 0x0, 0x0, 0x8, 0x0, 0x1, 0x0, 0x1, 0x2, 0x1, 0x0, 0x0,
+        ];
+        let Err(nom::Err::Error(error)) = some_ip_header_strict(&bytes) else {
+            panic!("protocol version 2 accepted");
+        };
+
+        let dump = error.annotated_dump(&bytes);
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(
+            lines[0],
+            "00000000  12 34 00 01 00 00 00 08 00 01 00 01 02 01 00 00"
+        );
+        assert_eq!(lines[1].find('^'), lines[0].find("02 01"));
+        assert!(lines[1].ends_with("^^ offset 0xc (byte 0x02): unsupported protocol version 0x02"));
+    }
+
+    #[test]
+    fn test_is_response_to_session_handling() {
+        let request = SomeIPHeader::builder()
+            .service_id(0x1234)
+            .method_id(0x1)
+            .client_id(0x10)
+            .session_id(0x5)
+            .build();
+        let response = |session_id| {
+            SomeIPHeader::builder()
+                .service_id(0x1234)
+                .method_id(0x1)
+                .client_id(0x10)
+                .session_id(session_id)
+                .response()
+                .build()
+        };
+
+        assert!(request.session_handling_active());
+        assert!(response(0x5).is_response_to(&request));
+        assert!(!response(0x6).is_response_to(&request));
+
+        let request = SomeIPHeader {
+            session_id: 0,
+            ..request
+        };
+        assert!(!request.session_handling_active());
+        assert!(response(0x6).is_response_to(&request));
+        assert!(!request.is_response_to(&response(0x6)));
+    }
+
+    #[test]
+    fn test_header_event_id() {
+        let method = SomeIPHeader::builder().method_id(0x0123).build();
+        assert!(!method.is_event());
+        assert_eq!(method.event_id(), None);
+
+        let event = SomeIPHeader::builder().method_id(0x8100).build();
+        assert!(event.is_event());
+        assert_eq!(event.event_id(), Some(0x0100));
+    }
+
+    #[test]
+    fn test_header_magic_cookie() {
+        let client = [
+            0xff, 0xff, 0x0, 0x0, 0x0, 0x0, 0x0, 0x8, 0xde, 0xad, 0xbe, 0xef, 0x1, 0x1, 0x1, 0x0,
+        ];
+        let server = [
+            0xff, 0xff, 0x80, 0x0, 0x0, 0x0, 0x0, 0x8, 0xde, 0xad, 0xbe, 0xef, 0x1, 0x1, 0x2, 0x0,
+        ];
+        assert!(some_ip_header(&client).unwrap().1.is_magic_cookie());
+        assert!(some_ip_header(&server).unwrap().1.is_magic_cookie());
+
+        // a client cookie must not be a notification
+        let mut wrong_type = client;
+        wrong_type[14] = 0x2;
+        assert!(!some_ip_header(&wrong_type).unwrap().1.is_magic_cookie());
+        let built = SomeIPHeader::builder()
+            .service_id(0xffff)
+            .client_id(0xdead)
+            .session_id(0xbeef)
+            .request_no_return()
+            .build();
+        assert!(built.is_magic_cookie());
+        assert!(!SomeIPHeader::builder().build().is_magic_cookie());
+    }
+
+    #[test]
+    fn test_header_payload_length() {
+        let header = |length| SomeIPHeader {
+            length,
+            ..SomeIPHeader::builder().build()
+        };
+
+        assert_eq!(header(8).payload_length(), Some(0));
+        assert_eq!(header(12).payload_length(), Some(4));
+        assert_eq!(header(4).payload_length(), None);
+    }
+
+    #[test]
+    fn test_service_and_method_ids() {
+        assert_eq!(ServiceId(0x1234).to_string(), "0x1234");
+        assert_eq!(MethodId(0x1).to_string(), "0x0001");
+        assert_eq!(u16::from(ServiceId::from(0xfffe)), 0xfffe);
+        assert_eq!(MethodId::from(0x8001), MethodId(0x8001));
+
+        let (_, header) = some_ip_header(&[
+            0x12, 0x34, 0x80, 0x01, 0x0, 0x0, 0x0, 0x8, 0x0, 0x1, 0x0, 0x2, 0x1, 0x1, 0x2, 0x0,
+        ])
+        .unwrap();
+        assert_eq!(header.service_id, ServiceId(0x1234));
+        assert_eq!(header.method_id, 0x8001);
+    }
+
+    #[test]
+    fn test_header_getters() {
+        let bytes: Vec<u8> = vec![
+            0x12, 0x34, 0x80, 0x01, 0x0, 0x0, 0x0, 0x8, 0x0, 0x5, 0x0, 0x6, 0x1, 0x3, 0x2, 0x1,
+        ];
+        let (_, header) = some_ip_header(&bytes).unwrap();
+
+        assert_eq!(header.service_id(), ServiceId(0x1234));
+        assert_eq!(header.method_id(), MethodId(0x8001));
+        assert_eq!(header.length(), 8);
+        assert_eq!(header.client_id(), 0x5);
+        assert_eq!(header.session_id(), 0x6);
+        assert_eq!(header.protocol_version(), PROTOCOL_VERSION);
+        assert_eq!(header.interface_version(), 0x3);
+        assert_eq!(header.message_type(), SomeIPMessageType::Notification());
+        assert_eq!(header.return_code(), ReturnCode::NotOk);
+    }
+
+    #[test]
+    fn test_header_key() {
+        let request = SomeIPHeader::builder()
+            .service_id(0x1234)
+            .method_id(0x1)
+            .session_id(0x7)
+            .build();
+        let mut handlers = HashMap::new();
+        handlers.insert(request.key(), "set_speed");
+
+        let response = request.reply(ReturnCode::Ok);
+        assert_eq!(handlers.get(&response.key()), Some(&"set_speed"));
+        let other = SomeIPHeader::builder()
+            .service_id(0x1234)
+            .method_id(0x2)
+            .build();
+        assert_eq!(handlers.get(&other.key()), None);
+    }
+
+    #[test]
+    fn test_header_reply() {
+        let bytes: Vec<u8> = vec![
+            0x12, 0x34, 0x00, 0x01, 0x0, 0x0, 0x0, 0x0c, 0x0, 0x1, 0x0, 0x2, 0x1, 0x3, 0x0, 0x0,
+        ];
+        let (_, request) = some_ip_header(&bytes).unwrap();
+
+        let reply = request.reply(ReturnCode::Ok);
+        assert_eq!(reply.message_type, SomeIPMessageType::Response());
+        assert_eq!(reply.length, 8);
+        assert_eq!(
+            (reply.service_id, reply.method_id, reply.client_id),
+            (ServiceId(0x1234), MethodId(0x0001), 0x1)
+        );
+        assert_eq!(reply.interface_version, 0x3);
+        assert!(reply.is_response_to(&request));
+
+        let error = request.reply(ReturnCode::NotReady);
+        assert_eq!(error.message_type, SomeIPMessageType::Error());
+        assert_eq!(error.return_code, ReturnCode::NotReady);
+        assert!(error.is_response_to(&request));
+    }
+
+    #[test]
+    fn test_error_into_boxed_error() {
+        fn parse(bytes: &[u8]) -> Result<Value, Box<dyn std::error::Error>> {
+            let def = SomeIPType::StaticString {
+                length: 3,
+                coding: Some(StringCoding::Utf16),
+            };
+            match some_ip_value(bytes, &def) {
+                Ok((_, value)) => Ok(value),
+                Err(nom::Err::Error(e) | nom::Err::Failure(e)) => Err(OwnedError::from(e).into()),
+                Err(nom::Err::Incomplete(_)) => Err("incomplete".into()),
+            }
+        }
+
+        let error = parse(&[0x0, b'a', 0x0, 0x0]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "string length 3 does not fit its coding (3 bytes left)"
+        );
+
+        let error = InnerError::LengthMismatch {
+            declared: 48,
+            actual: 40,
+        };
+        assert_eq!(error.to_string(), "length mismatch: declared 48, actual 40");
+    }
+
+    #[test]
+    fn test_some_ip_union_value() {
+        // length 4 covers the 2 byte member and 2 bytes of padding
+        let bytes: Vec<u8> = vec![0x0, 0x0, 0x0, 0x4, 0x0, 0x0, 0x0, 0x2, 0x12, 0x34, 0x0, 0x0];
+        let def = SomeIPType::Union {
+            length_width: 32,
+            type_width: 32,
+            variants: vec![(1, SomeIPType::UInt8), (2, SomeIPType::UInt16)],
+        };
+        let (remaining, value) = some_ip_value(bytes.as_slice(), &def).unwrap();
 
         assert_eq!(remaining.len(), 0);
         assert_eq!(
             value,
             Value::Struct {
                 fields: vec![
-                    ("field1".to_string(), Value::UInt(0x12345678)),
-                    ("field2".to_string(), Value::UInt(0x9abc))
+                    ("type".to_string(), Value::UInt(2)),
+                    ("value".to_string(), Value::UInt(0x1234)),
                 ]
             }
         );
+
+        let mut encoded = Vec::new();
+        encode::encode_value(&value, &def, &mut encoded).unwrap();
+        // encoded without padding
+        assert_eq!(
+            encoded,
+            &[0x0, 0x0, 0x0, 0x2, 0x0, 0x0, 0x0, 0x2, 0x12, 0x34]
+        );
     }
-}
 
+    #[test]
+    fn test_value_get_path() {
+        let value = Value::Struct {
+            fields: vec![
+                ("id".to_string(), Value::UInt(7)),
+                (
+                    "position".to_string(),
+                    Value::Struct {
+                        fields: vec![
+                            ("x".to_string(), Value::Int(-1)),
+                            (
+                                "samples".to_string(),
+                                Value::Array(vec![Value::UInt(1), Value::UInt(2)]),
+                            ),
+                        ],
+                    },
+                ),
+            ],
+        };
+
+        assert_eq!(value.get("id"), Some(&Value::UInt(7)));
+        assert_eq!(value.get("missing"), None);
+        assert_eq!(value.get_path("position.x"), Some(&Value::Int(-1)));
+        assert_eq!(value.get_path("position.samples[1]"), Some(&Value::UInt(2)));
+        assert_eq!(value.get_path("position.samples[2]"), None);
+        assert_eq!(value.get_path("position.y"), None);
+        assert_eq!(value.get_path("id.x"), None);
+    }
+
+    #[test]
+    fn test_value_iter_array() {
+        let bytes: Vec<u8> = vec![0x0, 0x1, 0x0, 0x2, 0x0, 0x3];
+        let def = SomeIPType::StaticArray {
+            length: 3,
+            element: Box::new(SomeIPType::UInt16),
+        };
+        let (_, value) = some_ip_value(&bytes, &def).unwrap();
+
+        let sum: u64 = value
+            .iter_array()
+            .map(|element| u64::try_from(element).unwrap())
+            .sum();
+        assert_eq!(sum, 6);
+        assert_eq!(value.as_array().map(<[Value]>::len), Some(3));
+        assert_eq!(value.as_struct_fields(), None);
+        assert_eq!(Value::UInt(1).iter_array().count(), 0);
+    }
+
+    #[test]
+    fn test_value_into_map() {
+        let value = Value::Struct {
+            fields: vec![
+                ("field1".to_string(), Value::UInt(0x12345678)),
+                ("field2".to_string(), Value::UInt(0x9abc)),
+                ("field1".to_string(), Value::UInt(0x1)),
+            ],
+        };
+        let map = value.into_map().unwrap();
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get("field2"), Some(&Value::UInt(0x9abc)));
+        assert_eq!(map.get("field1"), Some(&Value::UInt(0x1)));
+        assert_eq!(Value::UInt(1).into_map(), None);
+    }
+
+    #[test]
+    fn test_value_to_map() {
+        let value = Value::Struct {
+            fields: vec![
+                ("field2".to_string(), Value::UInt(0x9abc)),
+                ("field1".to_string(), Value::UInt(0x12345678)),
+            ],
+        };
+        let map = value.to_map().unwrap();
+
+        assert_eq!(map.get("field2"), Some(&Value::UInt(0x9abc)));
+        assert_eq!(
+            Value::Map(map).to_pretty_string(2),
+            "{\n  field1: 305419896,\n  field2: 39612\n}"
+        );
+        assert_eq!(Value::UInt(1).to_map(), None);
+    }
+
+    #[test]
+    fn test_value_to_pretty_string() {
+        let value = Value::Struct {
+            fields: vec![
+                ("id".to_string(), Value::UInt(7)),
+                (
+                    "position".to_string(),
+                    Value::Struct {
+                        fields: vec![
+                            ("x".to_string(), Value::Int(-1)),
+                            ("tags".to_string(), Value::Array(vec![])),
+                        ],
+                    },
+                ),
+                (
+                    "names".to_string(),
+                    Value::Array(vec![Value::String("a".to_string())]),
+                ),
+            ],
+        };
+
+        assert_eq!(
+            value.to_pretty_string(2),
+            "{\n  id: 7,\n  position: {\n    x: -1,\n    tags: []\n  },\n  names: [\n    \"a\"\n  ]\n}"
+        );
+    }
+
+    #[test]
+    fn test_message_type_copy() {
+        let header = SomeIPHeader::builder().notification().build();
+        let message_type = header.message_type;
+        let reply = SomeIPHeader::builder().message_type(message_type).build();
+
+        assert_eq!(reply.message_type, header.message_type);
+        assert_eq!(message_type.as_u8(), 0x2);
+    }
+
+    #[test]
+    fn test_message_type_byte_round_trip() {
+        for byte in 0..=u8::MAX {
+            assert_eq!(u8::from(SomeIPMessageType::from(byte)), byte);
+        }
+        assert_eq!(SomeIPMessageType::Error().as_u8(), 0x81);
+        assert_eq!(
+            SomeIPMessageType::from(0x40),
+            SomeIPMessageType::Unknown(0x40)
+        );
+    }
+
+    #[test]
+    fn test_message_type_and_return_code_names() {
+        assert_eq!(SomeIPMessageType::from(0x02).name(), "NOTIFICATION");
+        assert_eq!(SomeIPMessageType::from(0x81).name(), "ERROR");
+        assert_eq!(SomeIPMessageType::from(0x42).name(), "UNKNOWN");
+        assert_eq!(ReturnCode::from(0x00).description(), "E_OK");
+        assert_eq!(ReturnCode::from(0x01).description(), "E_NOT_OK");
+        assert_eq!(ReturnCode::from(0x0f).description(), "E_E2E_NO_NEW_DATA");
+        assert_eq!(
+            ReturnCode::from(0x42).description(),
+            "VENDOR_SPECIFIC(0x42)"
+        );
+        assert_eq!(ReturnCode::from(0x10).description(), "UNKNOWN(0x10)");
+        for byte in 0..=u8::MAX {
+            assert_eq!(u8::from(ReturnCode::from(byte)), byte);
+        }
+    }
+
+    #[test]
+    fn test_return_code_vendor_specific() {
+        assert_eq!(ReturnCode::from(0x30), ReturnCode::VendorSpecific(0x30));
+        assert!(ReturnCode::from(0x30).is_vendor_specific());
+        assert_eq!(ReturnCode::from(0x60), ReturnCode::Unknown(0x60));
+        assert!(!ReturnCode::from(0x60).is_vendor_specific());
+        assert!(!ReturnCode::Ok.is_vendor_specific());
+    }
+
+    #[test]
+    fn test_some_ip_header_count_body_value() {
+        let bytes: Vec<u8> = vec![0x2, 0x0, 0x1, 0xa, 0x0, 0x2, 0xb];
+        let def = SomeIPType::HeaderCountBody {
+            count: Box::new(SomeIPType::UInt8),
+            element: Box::new(SomeIPType::Struct {
+                fields: vec![
+                    ("id".to_string(), SomeIPType::UInt16),
+                    ("value".to_string(), SomeIPType::UInt8),
+                ],
+                alignment: 1,
+                length_width: None,
+                layout: StructLayout::Sequential,
+            }),
+        };
+        let item = |id, value| Value::Struct {
+            fields: vec![
+                ("id".to_string(), Value::UInt(id)),
+                ("value".to_string(), Value::UInt(value)),
+            ],
+        };
+
+        assert_eq!(
+            some_ip_value(bytes.as_slice(), &def),
+            Ok((
+                &[][..],
+                Value::Struct {
+                    fields: vec![
+                        ("count".to_string(), Value::UInt(2)),
+                        (
+                            "items".to_string(),
+                            Value::Array(vec![item(1, 0xa), item(2, 0xb)])
+                        ),
+                    ]
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_some_ip_header_strict() {
+        let mut bytes: Vec<u8> = vec![
+            0x12, 0x34, 0x00, 0x01, 0x0, 0x0, 0x0, 0x8, 0x0, 0x1, 0x0, 0x1, 0x1, 0x1, 0x0, 0x0,
+        ];
+        let (_, header) = some_ip_header_strict(bytes.as_slice()).unwrap();
+        assert_eq!(header.protocol_version, 0x1);
+
+        bytes[12] = 0x2;
+        assert!(some_ip_header(bytes.as_slice()).is_ok());
+        let Err(nom::Err::Error(error)) = some_ip_header_strict(bytes.as_slice()) else {
+            panic!("protocol version 2 accepted");
+        };
+        assert_eq!(error.error, InnerError::UnsupportedProtocolVersion(0x2));
+        assert_eq!(error.offset(&bytes), Some(12));
+    }
+
+    #[test]
+    fn test_some_ip_header_reserved_message_type_bits() {
+        let bytes: Vec<u8> = vec![
+            0x12, 0x34, 0x00, 0x01, 0x0, 0x0, 0x0, 0x8, 0x0, 0x1, 0x0, 0x1, 0x1, 0x1, 0x40, 0x0,
+        ];
+        let (_, header) =
+            some_ip_header_with_options(bytes.as_slice(), &HeaderOptions::default()).unwrap();
+        assert_eq!(header.message_type, SomeIPMessageType::Unknown(0x40));
+
+        let options = HeaderOptions {
+            reject_reserved_message_type_bits: true,
+        };
+        let Err(nom::Err::Error(error)) = some_ip_header_with_options(bytes.as_slice(), &options)
+        else {
+            panic!("reserved message type bits accepted");
+        };
+        assert_eq!(error.error, InnerError::ReservedMessageTypeBits(0x40));
+        assert_eq!(error.offset(&bytes), Some(14));
+
+        let notification = [&bytes[..14], &[0x02, 0x0]].concat();
+        assert!(some_ip_header_with_options(notification.as_slice(), &options).is_ok());
+    }
+
+    #[test]
+    fn test_some_ip_messages() {
+        let bytes: Vec<u8> = vec![
+            0x12, 0x34, 0x00, 0x01, 0x0, 0x0, 0x0, 0x0c, 0x0, 0x1, 0x0, 0x1, 0x1, 0x1, 0x0, 0x0,
+            0xde, 0xad, 0xbe, 0xef, 0x12, 0x34, 0x80, 0x02, 0x0, 0x0, 0x0, 0x0a, 0x0, 0x0, 0x0,
+            0x2, 0x1, 0x1, 0x2, 0x0, 0xca, 0xfe,
+        ];
+        let messages = some_ip_messages(bytes.as_slice())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].header.method_id, 0x0001);
+        assert_eq!(messages[0].payload, &[0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(messages[1].header.method_id, 0x8002);
+        assert_eq!(messages[1].payload, &[0xca, 0xfe]);
+
+        let mut messages = some_ip_messages(&bytes[..bytes.len() - 1]);
+        assert!(messages.next().unwrap().is_ok());
+        assert!(matches!(
+            messages.next(),
+            Some(Err(nom::Err::Incomplete(_)))
+        ));
+        assert_eq!(messages.next(), None);
+        assert_eq!(messages.remaining(), &bytes[20..bytes.len() - 1]);
+    }
+
+    #[test]
+    fn test_some_ip_messages_aligned() {
+        // the first message is 18 bytes long, followed by 2 bytes of padding
+        let bytes: Vec<u8> = vec![
+            0x12, 0x34, 0x00, 0x01, 0x0, 0x0, 0x0, 0x0a, 0x0, 0x1, 0x0, 0x1, 0x1, 0x1, 0x0, 0x0,
+            0xca, 0xfe, 0x0, 0x0, 0x12, 0x34, 0x80, 0x02, 0x0, 0x0, 0x0, 0x0a, 0x0, 0x0, 0x0, 0x2,
+            0x1, 0x1, 0x2, 0x0, 0xbe, 0xef,
+        ];
+        let messages = some_ip_messages(bytes.as_slice())
+            .aligned(4)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].payload, &[0xca, 0xfe]);
+        assert_eq!(messages[1].header.method_id, 0x8002);
+        assert_eq!(messages[1].payload, &[0xbe, 0xef]);
+    }
+
+    #[test]
+    fn test_some_ip_float16_value() {
+        let float16 = |bits: u16| {
+            let bytes = bits.to_be_bytes();
+            let (_, value) = some_ip_value(&bytes, &SomeIPType::Float16).unwrap();
+            let Value::Float(value) = value else {
+                panic!("not a float");
+            };
+            value
+        };
+
+        assert_eq!(float16(0x3c00), 1.0);
+        assert_eq!(float16(0xc000), -2.0);
+        assert_eq!(float16(0x7bff), 65504.0);
+        assert_eq!(float16(0x7c00), f64::INFINITY);
+        assert_eq!(float16(0xfc00), f64::NEG_INFINITY);
+        assert_eq!(float16(0x0001), 2f64.powi(-24));
+        assert_eq!(float16(0x8000), 0.0);
+        assert!(float16(0x7e00).is_nan());
+    }
+
+    #[test]
+    fn test_some_ip_scaled_value() {
+        // 65.0 °C in 0.1 °C steps from -40 °C
+        let bytes: Vec<u8> = vec![0x04, 0x1a];
+        let def = |keep_raw| SomeIPType::Scaled {
+            base: Box::new(SomeIPType::UInt16),
+            factor: 0.1,
+            offset: -40.0,
+            keep_raw,
+        };
+
+        let scaled = def(false);
+        let (remaining, value) = some_ip_value(&bytes, &scaled).unwrap();
+        assert_eq!(remaining.len(), 0);
+        assert!(value.approx_eq(&Value::Float(65.0), 1e-9));
+
+        let def = def(true);
+        let (_, value) = some_ip_value(&bytes, &def).unwrap();
+        assert!(value.approx_eq(
+            &Value::Struct {
+                fields: vec![
+                    ("value".to_string(), Value::Float(65.0)),
+                    ("raw".to_string(), Value::UInt(1050)),
+                ]
+            },
+            1e-9
+        ));
+        let mut out = Vec::new();
+        encode::encode_value(&value, &def, &mut out).unwrap();
+        assert_eq!(out, bytes);
+    }
+
+    #[test]
+    fn test_some_ip_float32_value() {
+        let bytes: Vec<u8> = vec![0x3f, 0xc0, 0x0, 0x0];
+        let (remaining, value) = some_ip_value(bytes.as_slice(), &SomeIPType::Float32).unwrap();
+
+        assert_eq!(remaining.len(), 0);
+        assert_eq!(value, Value::Float(1.5));
+    }
+
+    #[test]
+    fn test_some_ip_qfixed_value() {
+        let q8_8 = |signed| SomeIPType::QFixed {
+            int_bits: 8,
+            frac_bits: 8,
+            signed,
+        };
+
+        let bytes: Vec<u8> = vec![0x01, 0x80];
+        let def = q8_8(false);
+        let (remaining, value) = some_ip_value(bytes.as_slice(), &def).unwrap();
+        assert_eq!(remaining.len(), 0);
+        assert_eq!(value, Value::Float(1.5));
+
+        let bytes: Vec<u8> = vec![0xfe, 0x80];
+        let (_, value) = some_ip_value(bytes.as_slice(), &q8_8(true)).unwrap();
+        assert_eq!(value, Value::Float(-1.5));
+
+        let def = SomeIPType::QFixed {
+            int_bits: 4,
+            frac_bits: 8,
+            signed: false,
+        };
+        assert!(matches!(
+            some_ip_value(bytes.as_slice(), &def),
+            Err(nom::Err::Error(Error {
+                error: InnerError::InvalidBitWidth(12),
+                ..
+            }))
+        ));
+    }
+}
@@ -1,13 +1,112 @@
-use someip_nom::some_ip_header;
-
-fn main() {
-    let bytes: Vec<u8> = vec![
-        0xff, 0xff, 0x81, 0x0, 0x0, 0x0, 0x0, 0x30, 0x0, 0x0, 0x0, 0x3, 0x1, 0x1, 0x2, 0x0, 0xc0,
-        0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x10, 0x1, 0x0, 0x0, 0x10, 0x0, 0xeb, 0x0, 0x0, 0x1, 0x0,
-        0x0, 0x1e, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xc, 0x0, 0x9, 0x4, 0x0, 0xc0, 0xa8, 0x58,
-        0x49, 0x0, 0x11, 0xc3, 0x50,
-    ];
-    let slice = bytes.as_slice();
-    let (payload, header) = some_ip_header(slice).unwrap();
-    println!("{:?}", header);
-}
\ No newline at end of file
+//! Prints SOME/IP messages given as hex or read from a file.
+
+use std::{env, fs, path::Path, process::ExitCode};
+
+use someip_nom::{Error, SomeIPType, some_ip_header, some_ip_message};
+
+const USAGE: &str = "usage: someip-nom [--json] [--payload-type <type.json>] <hex | file>
+
+Prints the header of the SOME/IP message given as hex digits or stored in
+a file. With --payload-type the payload is decoded as the SomeIPType in the
+given JSON file and printed as well.";
+
+struct Args {
+    json: bool,
+    payload_type: Option<String>,
+    input: String,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut json = false;
+    let mut payload_type = None;
+    let mut input = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--payload-type" => {
+                payload_type = Some(args.next().ok_or("--payload-type needs a file")?);
+            }
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            flag if flag.starts_with("--") => return Err(format!("unknown flag {flag}")),
+            _ if input.is_some() => return Err("more than one input given".to_string()),
+            _ => input = Some(arg),
+        }
+    }
+    Ok(Args {
+        json,
+        payload_type,
+        input: input.ok_or(USAGE)?,
+    })
+}
+
+/// The bytes of the file at `input` or, if there is none, of the hex digits
+/// in `input`.
+fn read_input(input: &str) -> Result<Vec<u8>, String> {
+    if Path::new(input).is_file() {
+        return fs::read(input).map_err(|e| format!("cannot read {input}: {e}"));
+    }
+    let digits: String = input
+        .trim_start_matches("0x")
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    if !digits.len().is_multiple_of(2) || !digits.is_ascii() {
+        return Err(format!("{input} is neither a file nor hex bytes"));
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16))
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("{input} is neither a file nor hex bytes"))
+}
+
+fn describe(error: nom::Err<Error>) -> String {
+    match error {
+        nom::Err::Incomplete(_) => "message is truncated".to_string(),
+        nom::Err::Error(e) | nom::Err::Failure(e) => e.to_string(),
+    }
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let bytes = read_input(&args.input)?;
+    let Some(payload_type) = args.payload_type else {
+        let (_, header) = some_ip_header(&bytes).map_err(describe)?;
+        if args.json {
+            println!("{}", serde_json::to_string_pretty(&header).unwrap());
+        } else {
+            println!("{header:#?}");
+        }
+        return Ok(());
+    };
+
+    let def = fs::read_to_string(&payload_type)
+        .map_err(|e| format!("cannot read {payload_type}: {e}"))?;
+    let def: SomeIPType = serde_json::from_str(&def)
+        .map_err(|e| format!("invalid type definition in {payload_type}: {e}"))?;
+    let (_, (header, value)) = some_ip_message(&bytes, &def).map_err(describe)?;
+    if args.json {
+        let message = serde_json::json!({ "header": header, "payload": value });
+        println!("{}", serde_json::to_string_pretty(&message).unwrap());
+    } else {
+        println!("{header:#?}");
+        println!("{value:#?}");
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{message}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("error: {message}");
+            ExitCode::FAILURE
+        }
+    }
+}
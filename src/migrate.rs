@@ -0,0 +1,268 @@
+//! Upgrading values decoded with an older interface version to the shape of
+//! a newer one.
+
+use std::fmt;
+
+use crate::{SomeIPType, Value};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MigrateError {
+    /// The value does not have the shape of the old type, or the old and
+    /// new types are not versions of each other.
+    TypeMismatch,
+    /// An integer does not fit into the new type.
+    OutOfRange,
+    /// A field added by the new type has no default value.
+    NoDefault(String),
+}
+
+impl fmt::Display for MigrateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrateError::TypeMismatch => write!(f, "value does not match the types"),
+            MigrateError::OutOfRange => write!(f, "integer does not fit the new type"),
+            MigrateError::NoDefault(field) => write!(f, "no default value for field {field}"),
+        }
+    }
+}
+
+impl std::error::Error for MigrateError {}
+
+/// Converts `value`, decoded as `from`, into a value of type `to`.
+///
+/// Struct fields are matched by name: fields `to` adds are filled with
+/// their type's default, fields it drops are left out. Arrays migrated into
+/// a static array are truncated or padded with defaults to its length.
+/// Integers may change their width and signedness as long as the value
+/// fits.
+pub fn migrate(value: &Value, from: &SomeIPType, to: &SomeIPType) -> Result<Value, MigrateError> {
+    if from == to {
+        return Ok(value.clone());
+    }
+    match (from, to, value) {
+        (
+            SomeIPType::Struct {
+                fields: from_fields,
+                ..
+            },
+            SomeIPType::Struct {
+                fields: to_fields, ..
+            },
+            Value::Struct { .. },
+        ) => {
+            let fields = to_fields
+                .iter()
+                .map(|(name, to_def)| {
+                    let old = from_fields
+                        .iter()
+                        .find(|(from_name, _)| from_name == name)
+                        .zip(value.get(name));
+                    let new = match old {
+                        Some(((_, from_def), old)) => migrate(old, from_def, to_def)?,
+                        None => default_value(to_def)
+                            .ok_or_else(|| MigrateError::NoDefault(name.clone()))?,
+                    };
+                    Ok((name.clone(), new))
+                })
+                .collect::<Result<_, _>>()?;
+            Ok(Value::Struct { fields })
+        }
+        (
+            SomeIPType::StaticArray {
+                element: from_element,
+                ..
+            }
+            | SomeIPType::DynamicArray {
+                element: from_element,
+                ..
+            },
+            SomeIPType::StaticArray {
+                element: to_element,
+                ..
+            }
+            | SomeIPType::DynamicArray {
+                element: to_element,
+                ..
+            },
+            Value::Array(elements),
+        ) => {
+            let mut elements = elements
+                .iter()
+                .map(|element| migrate(element, from_element, to_element))
+                .collect::<Result<Vec<_>, _>>()?;
+            if let SomeIPType::StaticArray { length, .. } = to {
+                let length = *length as usize;
+                if elements.len() < length {
+                    let default = default_value(to_element).ok_or(MigrateError::TypeMismatch)?;
+                    elements.resize(length, default);
+                }
+                elements.truncate(length);
+            }
+            Ok(Value::Array(elements))
+        }
+        (from, to, Value::UInt(_) | Value::Int(_)) if is_integer(from) && is_integer(to) => {
+            let value = match value {
+                Value::UInt(v) => i128::from(*v),
+                Value::Int(v) => i128::from(*v),
+                _ => unreachable!(),
+            };
+            let (min, max, signed) = integer_range(to);
+            if !(min..=max).contains(&value) {
+                return Err(MigrateError::OutOfRange);
+            }
+            Ok(if signed {
+                Value::Int(value as i64)
+            } else {
+                Value::UInt(value as u64)
+            })
+        }
+        _ => Err(MigrateError::TypeMismatch),
+    }
+}
+
+fn is_integer(def: &SomeIPType) -> bool {
+    matches!(
+        def,
+        SomeIPType::UInt8
+            | SomeIPType::UInt16
+            | SomeIPType::UInt32
+            | SomeIPType::UInt64
+            | SomeIPType::SInt8
+            | SomeIPType::SInt16
+            | SomeIPType::SInt32
+            | SomeIPType::SInt64
+    )
+}
+
+/// The smallest and largest value of an integer type and its signedness.
+fn integer_range(def: &SomeIPType) -> (i128, i128, bool) {
+    match def {
+        SomeIPType::UInt8 => (0, u8::MAX.into(), false),
+        SomeIPType::UInt16 => (0, u16::MAX.into(), false),
+        SomeIPType::UInt32 => (0, u32::MAX.into(), false),
+        SomeIPType::UInt64 => (0, u64::MAX.into(), false),
+        SomeIPType::SInt8 => (i8::MIN.into(), i8::MAX.into(), true),
+        SomeIPType::SInt16 => (i16::MIN.into(), i16::MAX.into(), true),
+        SomeIPType::SInt32 => (i32::MIN.into(), i32::MAX.into(), true),
+        _ => (i64::MIN.into(), i64::MAX.into(), true),
+    }
+}
+
+/// The value a field of type `def` gets when it is added: zero, the first
+/// enum variant, or empty for dynamically sized types.
+pub fn default_value(def: &SomeIPType) -> Option<Value> {
+    let value = match def {
+        SomeIPType::Bool => Value::Bool(false),
+        SomeIPType::UInt8 | SomeIPType::UInt16 | SomeIPType::UInt32 | SomeIPType::UInt64 => {
+            Value::UInt(0)
+        }
+        SomeIPType::SInt8 | SomeIPType::SInt16 | SomeIPType::SInt32 | SomeIPType::SInt64 => {
+            Value::Int(0)
+        }
+        SomeIPType::UInt128 => Value::UInt128(0),
+        SomeIPType::SInt128 => Value::Int128(0),
+        SomeIPType::Float16
+        | SomeIPType::Float32
+        | SomeIPType::Float64
+        | SomeIPType::QFixed { .. } => Value::Float(0.0),
+        SomeIPType::Struct { fields, .. } => Value::Struct {
+            fields: fields
+                .iter()
+                .map(|(name, def)| Some((name.clone(), default_value(def)?)))
+                .collect::<Option<_>>()?,
+        },
+        SomeIPType::OptionalGroup { .. } => Value::Struct { fields: Vec::new() },
+        SomeIPType::Bitfield { fields, .. } => Value::Struct {
+            fields: fields
+                .iter()
+                .map(|(name, _)| (name.clone(), Value::UInt(0)))
+                .collect(),
+        },
+        SomeIPType::StaticArray { length, element } => {
+            let element = default_value(element)?;
+            Value::Array((0..*length).map(|_| element.clone()).collect())
+        }
+        SomeIPType::DynamicArray { .. } | SomeIPType::SentinelArray { .. } => {
+            Value::Array(Vec::new())
+        }
+        SomeIPType::Enum { variants, .. } => Value::Enum(variants.first()?.1.clone()),
+        SomeIPType::DynamicString { .. } | SomeIPType::CString { .. } => {
+            Value::String(String::new())
+        }
+        SomeIPType::Opaque { length } => Value::Bytes(vec![0; length.unwrap_or(0) as usize]),
+        _ => return None,
+    };
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StructLayout;
+
+    #[test]
+    fn test_migrate_added_field() {
+        let from = SomeIPType::Struct {
+            fields: vec![
+                ("id".to_string(), SomeIPType::UInt8),
+                ("value".to_string(), SomeIPType::SInt16),
+            ],
+            alignment: 1,
+            length_width: None,
+            layout: StructLayout::Sequential,
+        };
+        let to = SomeIPType::Struct {
+            fields: vec![
+                ("id".to_string(), SomeIPType::UInt16),
+                ("value".to_string(), SomeIPType::SInt32),
+                ("unit".to_string(), SomeIPType::UInt8),
+            ],
+            alignment: 1,
+            length_width: None,
+            layout: StructLayout::Sequential,
+        };
+        let value = Value::Struct {
+            fields: vec![
+                ("id".to_string(), Value::UInt(0x12)),
+                ("value".to_string(), Value::Int(-3)),
+            ],
+        };
+
+        assert_eq!(
+            migrate(&value, &from, &to),
+            Ok(Value::Struct {
+                fields: vec![
+                    ("id".to_string(), Value::UInt(0x12)),
+                    ("value".to_string(), Value::Int(-3)),
+                    ("unit".to_string(), Value::UInt(0)),
+                ]
+            })
+        );
+        assert_eq!(
+            migrate(&Value::Int(-3), &SomeIPType::SInt16, &SomeIPType::UInt32),
+            Err(MigrateError::OutOfRange)
+        );
+    }
+
+    #[test]
+    fn test_migrate_static_array_length() {
+        let array = |length| SomeIPType::StaticArray {
+            length,
+            element: Box::new(SomeIPType::UInt16),
+        };
+        let value = Value::Array(vec![Value::UInt(0x1), Value::UInt(0x2)]);
+
+        assert_eq!(
+            migrate(&value, &array(2), &array(3)),
+            Ok(Value::Array(vec![
+                Value::UInt(0x1),
+                Value::UInt(0x2),
+                Value::UInt(0)
+            ]))
+        );
+        assert_eq!(
+            migrate(&value, &array(2), &array(1)),
+            Ok(Value::Array(vec![Value::UInt(0x1)]))
+        );
+    }
+}
@@ -0,0 +1,26 @@
+//! Named types that schemas refer to with `SomeIPType::Ref`.
+
+use std::collections::HashMap;
+
+use crate::SomeIPType;
+
+/// Type definitions looked up by name while decoding.
+#[derive(Debug, Default)]
+pub struct TypeRegistry {
+    types: HashMap<String, SomeIPType>,
+}
+
+impl TypeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `def` under `name`, returning the definition it replaces.
+    pub fn register(&mut self, name: impl Into<String>, def: SomeIPType) -> Option<SomeIPType> {
+        self.types.insert(name.into(), def)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SomeIPType> {
+        self.types.get(name)
+    }
+}
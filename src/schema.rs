@@ -0,0 +1,214 @@
+//! Checks for mistakes in type definitions that would otherwise only show up
+//! as misparsed payloads.
+
+use std::collections::HashSet;
+
+use crate::{SomeIPType, StructLayout};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SchemaError {
+    /// A struct declares two fields with the same name.
+    DuplicateFieldName(String),
+    /// A length or type field is not 8, 16, 32 or 64 bits wide.
+    InvalidLengthWidth(u8),
+    /// An enum maps two variants to the same discriminant.
+    DuplicateDiscriminant(u64),
+    /// An enum declares two variants with the same name.
+    DuplicateVariantName(String),
+}
+
+impl SomeIPType {
+    /// A closed enum with `UInt8` discriminants, unless two variants share a
+    /// discriminant or a name; decoding and encoding would silently use the
+    /// first of them.
+    pub fn enum_checked(variants: Vec<(u64, String)>) -> Result<SomeIPType, SchemaError> {
+        validate_enum_variants(&variants)?;
+        Ok(SomeIPType::Enum {
+            variants,
+            open_enum: false,
+            base: None,
+        })
+    }
+}
+
+fn validate_enum_variants(variants: &[(u64, String)]) -> Result<(), SchemaError> {
+    let mut discriminants = HashSet::new();
+    let mut names = HashSet::new();
+    for (discriminant, name) in variants {
+        if !discriminants.insert(discriminant) {
+            return Err(SchemaError::DuplicateDiscriminant(*discriminant));
+        }
+        if !names.insert(name) {
+            return Err(SchemaError::DuplicateVariantName(name.clone()));
+        }
+    }
+    Ok(())
+}
+
+fn validate_length_width(width: u8) -> Result<(), SchemaError> {
+    match width {
+        8 | 16 | 32 | 64 => Ok(()),
+        _ => Err(SchemaError::InvalidLengthWidth(width)),
+    }
+}
+
+/// Validates `def` and every type nested in it.
+pub fn validate_schema(def: &SomeIPType) -> Result<(), SchemaError> {
+    match def {
+        SomeIPType::Struct {
+            fields,
+            length_width,
+            layout,
+            ..
+        } => {
+            if let Some(length_width) = length_width {
+                validate_length_width(*length_width)?;
+            }
+            if let StructLayout::BitmaskOptional { mask_width } = layout {
+                validate_length_width(*mask_width)?;
+            }
+            let mut names = HashSet::new();
+            for (name, field) in fields {
+                if !names.insert(name) {
+                    return Err(SchemaError::DuplicateFieldName(name.clone()));
+                }
+                validate_schema(field)?;
+            }
+        }
+        SomeIPType::DynamicArray {
+            length_width,
+            element,
+            ..
+        } => {
+            validate_length_width(*length_width)?;
+            validate_schema(element)?;
+        }
+        SomeIPType::StaticArray { element, .. } | SomeIPType::SentinelArray { element, .. } => {
+            validate_schema(element)?
+        }
+        SomeIPType::DynamicString { length_width, .. } => validate_length_width(*length_width)?,
+        SomeIPType::EnumSelectedStruct {
+            enum_def,
+            variant_structs,
+        } => {
+            validate_schema(enum_def)?;
+            for variant_def in variant_structs.values() {
+                validate_schema(variant_def)?;
+            }
+        }
+        SomeIPType::Scaled { base, .. } | SomeIPType::Constrained { base, .. } => {
+            validate_schema(base)?
+        }
+        SomeIPType::OptionalGroup {
+            mask_width,
+            members,
+        } => {
+            validate_length_width(*mask_width)?;
+            let mut names = HashSet::new();
+            for (name, member) in members {
+                if !names.insert(name) {
+                    return Err(SchemaError::DuplicateFieldName(name.clone()));
+                }
+                validate_schema(member)?;
+            }
+        }
+        SomeIPType::Enum { variants, .. } => validate_enum_variants(variants)?,
+        SomeIPType::HeaderCountBody { count, element } => {
+            validate_schema(count)?;
+            validate_schema(element)?;
+        }
+        SomeIPType::Bitfield { fields, .. } => {
+            let mut names = HashSet::new();
+            for (name, _) in fields {
+                if !names.insert(name) {
+                    return Err(SchemaError::DuplicateFieldName(name.clone()));
+                }
+            }
+        }
+        SomeIPType::SignalGroup { signals, .. } => {
+            let mut names = HashSet::new();
+            for signal in signals {
+                if !names.insert(&signal.name) {
+                    return Err(SchemaError::DuplicateFieldName(signal.name.clone()));
+                }
+            }
+        }
+        SomeIPType::Union {
+            length_width,
+            type_width,
+            variants,
+        } => {
+            validate_length_width(*length_width)?;
+            validate_length_width(*type_width)?;
+            for (_, variant_def) in variants {
+                validate_schema(variant_def)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_schema_duplicate_field_name() {
+        let def = SomeIPType::StaticArray {
+            length: 2,
+            element: Box::new(SomeIPType::Struct {
+                fields: vec![
+                    ("id".to_string(), SomeIPType::UInt16),
+                    ("value".to_string(), SomeIPType::UInt32),
+                    ("id".to_string(), SomeIPType::UInt8),
+                ],
+                alignment: 1,
+                length_width: None,
+                layout: StructLayout::Sequential,
+            }),
+        };
+
+        assert_eq!(
+            validate_schema(&def),
+            Err(SchemaError::DuplicateFieldName("id".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_enum_checked() {
+        let variant = |discriminant, name: &str| (discriminant, name.to_string());
+
+        assert_eq!(
+            SomeIPType::enum_checked(vec![variant(0, "OFF"), variant(1, "ON")]),
+            Ok(SomeIPType::Enum {
+                variants: vec![variant(0, "OFF"), variant(1, "ON")],
+                open_enum: false,
+                base: None,
+            })
+        );
+        assert_eq!(
+            SomeIPType::enum_checked(vec![variant(0, "OFF"), variant(0, "ON")]),
+            Err(SchemaError::DuplicateDiscriminant(0))
+        );
+        assert_eq!(
+            SomeIPType::enum_checked(vec![variant(0, "OFF"), variant(1, "OFF")]),
+            Err(SchemaError::DuplicateVariantName("OFF".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_validate_schema_ok() {
+        let def = SomeIPType::Struct {
+            fields: vec![
+                ("id".to_string(), SomeIPType::UInt16),
+                ("value".to_string(), SomeIPType::UInt32),
+            ],
+            alignment: 1,
+            length_width: None,
+            layout: StructLayout::Sequential,
+        };
+
+        assert_eq!(validate_schema(&def), Ok(()));
+    }
+}
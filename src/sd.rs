@@ -0,0 +1,858 @@
+//! SOME/IP Service Discovery (SD) payloads.
+//!
+//! SD messages are regular SOME/IP messages with service id 0xFFFF and
+//! method id 0x8100. Their payload is parsed with [`sd_message`] after the
+//! header has been consumed.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+use nom::{
+    IResult, Parser,
+    bytes::complete::take,
+    number::complete::{be_u8, be_u16, be_u24, be_u32, be_u128},
+};
+
+use crate::{Error, InnerError, SomeIPMessage};
+
+pub const SD_SERVICE_ID: u16 = 0xffff;
+pub const SD_METHOD_ID: u16 = 0x8100;
+/// Minor version of a FindService entry that accepts any offered minor version.
+pub const ANY_MINOR_VERSION: u32 = 0xffff_ffff;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SdMessage {
+    pub flags: u8,
+    pub entries: Vec<SdEntry>,
+    pub options: Vec<SdOption>,
+}
+
+impl SdMessage {
+    /// The sender rebooted and restarted its session ids.
+    pub fn is_reboot(&self) -> bool {
+        self.flags & 0x80 != 0
+    }
+
+    /// The sender supports receiving unicast SD messages.
+    pub fn is_unicast(&self) -> bool {
+        self.flags & 0x40 != 0
+    }
+
+    /// Every entry together with the options its option runs reference.
+    pub fn resolved_entries(&self) -> Result<Vec<ResolvedEntry<'_>>, OptionRunOutOfBounds> {
+        self.entries
+            .iter()
+            .map(|entry| {
+                Ok(ResolvedEntry {
+                    entry,
+                    options: entry.resolve_options(&self.options)?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// An entry with the options of its first and second option run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResolvedEntry<'a> {
+    pub entry: &'a SdEntry,
+    pub options: [&'a [SdOption]; 2],
+}
+
+/// An option run reaching past the options of its message.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OptionRunOutOfBounds {
+    pub run: OptionRun,
+    /// The number of options in the message.
+    pub options: usize,
+}
+
+impl fmt::Display for OptionRunOutOfBounds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "option run of {} options at index {} exceeds {} options",
+            self.run.count, self.run.index, self.options
+        )
+    }
+}
+
+impl std::error::Error for OptionRunOutOfBounds {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SdEntryType {
+    FindService,
+    OfferService,
+    SubscribeEventgroup,
+    SubscribeEventgroupAck,
+    Unknown(u8),
+}
+
+impl From<u8> for SdEntryType {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => Self::FindService,
+            0x01 => Self::OfferService,
+            0x06 => Self::SubscribeEventgroup,
+            0x07 => Self::SubscribeEventgroupAck,
+            _ => Self::Unknown(value),
+        }
+    }
+}
+
+/// A run of consecutive options referenced by an entry.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OptionRun {
+    pub index: u8,
+    pub count: u8,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SdEntry {
+    /// Entry types 0x00-0x03, e.g. FindService and OfferService.
+    Service {
+        entry_type: SdEntryType,
+        option_runs: [OptionRun; 2],
+        service_id: u16,
+        instance_id: u16,
+        major_version: u8,
+        ttl: u32,
+        minor_version: u32,
+    },
+    /// Entry types 0x04-0x07, e.g. SubscribeEventgroup and its Ack.
+    Eventgroup {
+        entry_type: SdEntryType,
+        option_runs: [OptionRun; 2],
+        service_id: u16,
+        instance_id: u16,
+        major_version: u8,
+        ttl: u32,
+        /// The subscriber asks for the current values of the eventgroup's
+        /// fields to be sent right after subscribing, not only on change.
+        /// Carried in the most significant bit of the byte holding the
+        /// counter.
+        initial_data_requested: bool,
+        counter: u8,
+        eventgroup_id: u16,
+    },
+}
+
+/// Transport protocol of an endpoint.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum L4Proto {
+    Tcp,
+    Udp,
+}
+
+impl L4Proto {
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x06 => Some(Self::Tcp),
+            0x11 => Some(Self::Udp),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Endpoint {
+    pub proto: L4Proto,
+    pub addr: IpAddr,
+    pub port: u16,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SdOption {
+    Ipv4Endpoint {
+        addr: Ipv4Addr,
+        proto: L4Proto,
+        port: u16,
+    },
+    Ipv6Endpoint {
+        addr: Ipv6Addr,
+        proto: L4Proto,
+        port: u16,
+    },
+    /// The multicast address an eventgroup is sent to.
+    Ipv4Multicast {
+        addr: Ipv4Addr,
+        proto: L4Proto,
+        port: u16,
+    },
+    Ipv6Multicast {
+        addr: Ipv6Addr,
+        proto: L4Proto,
+        port: u16,
+    },
+    /// Any option without a dedicated variant, with the reserved byte
+    /// following its type stripped.
+    Other { option_type: u8, data: Vec<u8> },
+}
+
+impl SdOption {
+    /// The address of an endpoint or multicast option.
+    pub fn endpoint(&self) -> Option<Endpoint> {
+        let (addr, proto, port) = match self {
+            SdOption::Ipv4Endpoint { addr, proto, port }
+            | SdOption::Ipv4Multicast { addr, proto, port } => (IpAddr::V4(*addr), proto, port),
+            SdOption::Ipv6Endpoint { addr, proto, port }
+            | SdOption::Ipv6Multicast { addr, proto, port } => (IpAddr::V6(*addr), proto, port),
+            SdOption::Other { .. } => return None,
+        };
+        Some(Endpoint {
+            proto: *proto,
+            addr,
+            port: *port,
+        })
+    }
+}
+
+impl SdEntry {
+    /// Whether this is a service entry asking for any minor version.
+    pub fn is_wildcard_minor(&self) -> bool {
+        matches!(
+            self,
+            SdEntry::Service {
+                minor_version: ANY_MINOR_VERSION,
+                ..
+            }
+        )
+    }
+
+    /// Lifetime of the entry in seconds, from its 24 bit TTL field.
+    pub fn ttl(&self) -> u32 {
+        match self {
+            SdEntry::Service { ttl, .. } | SdEntry::Eventgroup { ttl, .. } => *ttl,
+        }
+    }
+
+    /// A TTL of 0 withdraws the entry: an OfferService becomes a StopOffer,
+    /// a SubscribeEventgroup a StopSubscribeEventgroup.
+    pub fn is_stop(&self) -> bool {
+        self.ttl() == 0
+    }
+
+    pub fn option_runs(&self) -> &[OptionRun; 2] {
+        match self {
+            SdEntry::Service { option_runs, .. } | SdEntry::Eventgroup { option_runs, .. } => {
+                option_runs
+            }
+        }
+    }
+
+    /// The options of the first and second option run. Runs without options
+    /// resolve to an empty slice whatever their index.
+    pub fn resolve_options<'o>(
+        &self,
+        options: &'o [SdOption],
+    ) -> Result<[&'o [SdOption]; 2], OptionRunOutOfBounds> {
+        let resolve = |run: &OptionRun| {
+            if run.count == 0 {
+                return Ok(&options[..0]);
+            }
+            let start = usize::from(run.index);
+            options
+                .get(start..start + usize::from(run.count))
+                .ok_or(OptionRunOutOfBounds {
+                    run: *run,
+                    options: options.len(),
+                })
+        };
+        let [first, second] = self.option_runs();
+        Ok([resolve(first)?, resolve(second)?])
+    }
+
+    /// The options referenced by both option runs, skipping indices outside
+    /// of `options`.
+    pub fn referenced_options<'o>(
+        &self,
+        options: &'o [SdOption],
+    ) -> impl Iterator<Item = &'o SdOption> {
+        self.option_runs().iter().flat_map(move |run| {
+            let start = usize::from(run.index);
+            options.iter().skip(start).take(usize::from(run.count))
+        })
+    }
+}
+
+/// An OfferService entry with the endpoints of its referenced options.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OfferedService {
+    pub service_id: u16,
+    pub instance_id: u16,
+    pub major_version: u8,
+    /// 0 for a StopOffer.
+    pub ttl: u32,
+    pub minor_version: u32,
+    pub endpoints: Vec<Endpoint>,
+}
+
+/// The OfferService entries of `sd`, with the options they reference
+/// resolved into endpoints. Options that are not endpoints are left out.
+pub fn parse_offers(sd: &SdMessage) -> Result<Vec<OfferedService>, OptionRunOutOfBounds> {
+    sd.entries
+        .iter()
+        .filter_map(|entry| match entry {
+            SdEntry::Service {
+                entry_type: SdEntryType::OfferService,
+                service_id,
+                instance_id,
+                major_version,
+                ttl,
+                minor_version,
+                ..
+            } => Some(entry.resolve_options(&sd.options).map(|runs| {
+                OfferedService {
+                    service_id: *service_id,
+                    instance_id: *instance_id,
+                    major_version: *major_version,
+                    ttl: *ttl,
+                    minor_version: *minor_version,
+                    endpoints: runs
+                        .into_iter()
+                        .flatten()
+                        .filter_map(SdOption::endpoint)
+                        .collect(),
+                }
+            })),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Every currently offered service instance.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ServiceCatalog {
+    pub services: HashMap<(u16, u16), OfferedService>,
+}
+
+impl ServiceCatalog {
+    pub fn endpoints(&self, service_id: u16, instance_id: u16) -> Option<&[Endpoint]> {
+        self.services
+            .get(&(service_id, instance_id))
+            .map(|service| service.endpoints.as_slice())
+    }
+
+    /// The offer answering the FindService `entry`, which must match its
+    /// major version and, unless it is a wildcard, its minor version.
+    pub fn find(&self, entry: &SdEntry) -> Option<&OfferedService> {
+        let SdEntry::Service {
+            entry_type: SdEntryType::FindService,
+            service_id,
+            instance_id,
+            major_version,
+            minor_version,
+            ..
+        } = entry
+        else {
+            return None;
+        };
+        self.services
+            .get(&(*service_id, *instance_id))
+            .filter(|offer| {
+                offer.major_version == *major_version
+                    && (entry.is_wildcard_minor() || offer.minor_version == *minor_version)
+            })
+    }
+}
+
+/// Builds a catalog from the OfferService entries of the SD messages among
+/// `messages`, applied in order so a later StopOffer (TTL 0) removes an
+/// earlier offer. Non-SD and malformed SD messages are skipped.
+pub fn build_catalog(messages: &[SomeIPMessage]) -> ServiceCatalog {
+    let mut catalog = ServiceCatalog::default();
+    for message in messages {
+        if message.header.service_id != SD_SERVICE_ID || message.header.method_id != SD_METHOD_ID {
+            continue;
+        }
+        let Ok((_, sd)) = sd_message(message.payload) else {
+            continue;
+        };
+        let Ok(offers) = parse_offers(&sd) else {
+            continue;
+        };
+        for offer in offers {
+            let key = (offer.service_id, offer.instance_id);
+            if offer.ttl == 0 {
+                catalog.services.remove(&key);
+            } else {
+                catalog.services.insert(key, offer);
+            }
+        }
+    }
+    catalog
+}
+
+/// Parses an SD payload: flags, the entries array and the options array.
+pub fn sd_message(input: &[u8]) -> IResult<&[u8], SdMessage, Error<'_>> {
+    let (i1, flags) = be_u8(input)?;
+    let (i2, _reserved) = take(3usize).parse(i1)?;
+    let (i3, entries_length) = be_u32(i2)?;
+    let (i4, mut entries_bytes) = take(entries_length).parse(i3)?;
+    let (i5, options_length) = be_u32(i4)?;
+    let (i6, mut options_bytes) = take(options_length).parse(i5)?;
+
+    let mut entries = Vec::new();
+    while !entries_bytes.is_empty() {
+        let (rest, entry) = sd_entry(entries_bytes)?;
+        entries_bytes = rest;
+        entries.push(entry);
+    }
+
+    // every option takes its length plus the length and type fields
+    let mut options_size = 0;
+    while let [length_hi, length_lo, ..] = options_bytes[options_size.min(options_bytes.len())..] {
+        options_size += usize::from(u16::from_be_bytes([length_hi, length_lo])) + 3;
+    }
+    if options_size != options_bytes.len() {
+        return Err(nom::Err::Error(Error::new(
+            i4,
+            InnerError::SdLengthMismatch {
+                declared: options_length,
+                actual: options_size as u32,
+            },
+        )));
+    }
+
+    let mut options = Vec::new();
+    while !options_bytes.is_empty() {
+        let (rest, option) = sd_option(options_bytes)?;
+        options_bytes = rest;
+        options.push(option);
+    }
+
+    Ok((
+        i6,
+        SdMessage {
+            flags,
+            entries,
+            options,
+        },
+    ))
+}
+
+fn sd_entry(input: &[u8]) -> IResult<&[u8], SdEntry, Error<'_>> {
+    let (i1, entry_type) = be_u8(input)?;
+    let (i2, index_first) = be_u8(i1)?;
+    let (i3, index_second) = be_u8(i2)?;
+    let (i4, counts) = be_u8(i3)?;
+    let (i5, service_id) = be_u16(i4)?;
+    let (i6, instance_id) = be_u16(i5)?;
+    let (i7, major_version) = be_u8(i6)?;
+    let (i8, ttl) = be_u24(i7)?;
+    let option_runs = [
+        OptionRun {
+            index: index_first,
+            count: counts >> 4,
+        },
+        OptionRun {
+            index: index_second,
+            count: counts & 0x0f,
+        },
+    ];
+
+    if entry_type < 0x04 {
+        let (i9, minor_version) = be_u32(i8)?;
+        Ok((
+            i9,
+            SdEntry::Service {
+                entry_type: entry_type.into(),
+                option_runs,
+                service_id,
+                instance_id,
+                major_version,
+                ttl,
+                minor_version,
+            },
+        ))
+    } else {
+        let (i9, _reserved) = be_u8(i8)?;
+        let (i10, flags_counter) = be_u8(i9)?;
+        let (i11, eventgroup_id) = be_u16(i10)?;
+        Ok((
+            i11,
+            SdEntry::Eventgroup {
+                entry_type: entry_type.into(),
+                option_runs,
+                service_id,
+                instance_id,
+                major_version,
+                ttl,
+                initial_data_requested: flags_counter & 0x80 != 0,
+                counter: flags_counter & 0x0f,
+                eventgroup_id,
+            },
+        ))
+    }
+}
+
+fn sd_option(input: &[u8]) -> IResult<&[u8], SdOption, Error<'_>> {
+    let (i1, length) = be_u16(input)?;
+    let (i2, option_type) = be_u8(i1)?;
+    // the length covers the reserved byte after the type and the option data
+    let (i3, _reserved) = be_u8(i2)?;
+    let (i4, data) = take(length.saturating_sub(1)).parse(i3)?;
+    let option = match (option_type, data.len()) {
+        (0x04, 8) => {
+            let (addr, proto, port) = ip_endpoint(data, be_u32)?;
+            SdOption::Ipv4Endpoint {
+                addr: addr.into(),
+                proto,
+                port,
+            }
+        }
+        (0x06, 20) => {
+            let (addr, proto, port) = ip_endpoint(data, be_u128)?;
+            SdOption::Ipv6Endpoint {
+                addr: addr.into(),
+                proto,
+                port,
+            }
+        }
+        (0x14, 8) => {
+            let (addr, proto, port) = ip_endpoint(data, be_u32)?;
+            SdOption::Ipv4Multicast {
+                addr: addr.into(),
+                proto,
+                port,
+            }
+        }
+        (0x16, 20) => {
+            let (addr, proto, port) = ip_endpoint(data, be_u128)?;
+            SdOption::Ipv6Multicast {
+                addr: addr.into(),
+                proto,
+                port,
+            }
+        }
+        _ => SdOption::Other {
+            option_type,
+            data: data.to_vec(),
+        },
+    };
+    Ok((i4, option))
+}
+
+/// The address, transport protocol and port of an endpoint or multicast
+/// option, with `addr` parsing the 4 or 16 address bytes.
+fn ip_endpoint<'a, A>(
+    data: &'a [u8],
+    addr: impl Fn(&'a [u8]) -> IResult<&'a [u8], A, Error<'a>>,
+) -> Result<(A, L4Proto, u16), nom::Err<Error<'a>>> {
+    let (i1, addr) = addr(data)?;
+    let (i2, _reserved) = be_u8(i1)?;
+    let (i3, proto) = be_u8(i2)?;
+    let proto = L4Proto::from_u8(proto)
+        .ok_or_else(|| nom::Err::Error(Error::new(i2, InnerError::UnknownL4Proto(proto))))?;
+    let (_, port) = be_u16(i3)?;
+    Ok((addr, proto, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::some_ip_header;
+
+    #[test]
+    fn test_sd_message() {
+        let bytes: Vec<u8> = vec![
+            0xff, 0xff, 0x81, 0x0, 0x0, 0x0, 0x0, 0x30, 0x0, 0x0, 0x0, 0x3, 0x1, 0x1, 0x2, 0x0,
+            0xc0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x10, 0x1, 0x0, 0x0, 0x10, 0x0, 0xeb, 0x0, 0x0,
+            0x1, 0x0, 0x0, 0x1e, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xc, 0x0, 0x9, 0x4, 0x0, 0xc0,
+            0xa8, 0x58, 0x49, 0x0, 0x11, 0xc3, 0x50,
+        ];
+        let (payload, header) = some_ip_header(bytes.as_slice()).unwrap();
+        assert_eq!(header.service_id, SD_SERVICE_ID);
+        assert_eq!(header.method_id, SD_METHOD_ID);
+
+        let (remaining, sd) = sd_message(payload).unwrap();
+
+        assert_eq!(remaining.len(), 0);
+        assert!(sd.is_reboot());
+        assert!(sd.is_unicast());
+        assert_eq!(
+            sd.entries,
+            vec![SdEntry::Service {
+                entry_type: SdEntryType::OfferService,
+                option_runs: [
+                    OptionRun { index: 0, count: 1 },
+                    OptionRun { index: 0, count: 0 }
+                ],
+                service_id: 0xeb,
+                instance_id: 0x0,
+                major_version: 0x1,
+                ttl: 0x1e,
+                minor_version: 0x0,
+            }]
+        );
+        assert_eq!(
+            sd.options,
+            vec![SdOption::Ipv4Endpoint {
+                addr: Ipv4Addr::new(192, 168, 88, 73),
+                proto: L4Proto::Udp,
+                port: 50000,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_sd_entry_stop_offer() {
+        // the OfferService entry of test_sd_message with a TTL of 0x01001e
+        let mut bytes: Vec<u8> = vec![
+            0x1, 0x0, 0x0, 0x10, 0x0, 0xeb, 0x0, 0x0, 0x1, 0x1, 0x0, 0x1e, 0x0, 0x0, 0x0, 0x0,
+        ];
+        let (_, offer) = sd_entry(&bytes).unwrap();
+        assert_eq!(offer.ttl(), 0x01001e);
+        assert!(!offer.is_stop());
+
+        bytes[9..12].fill(0x0);
+        let (_, stop_offer) = sd_entry(&bytes).unwrap();
+        assert_eq!(stop_offer.ttl(), 0);
+        assert!(stop_offer.is_stop());
+    }
+
+    #[test]
+    fn test_sd_options_length_mismatch() {
+        // declares 13 option bytes, but the endpoint option takes 12
+        let bytes: Vec<u8> = vec![
+            0xc0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xd, 0x0, 0x9, 0x4, 0x0, 0xc0,
+            0xa8, 0x58, 0x49, 0x0, 0x11, 0xc3, 0x50, 0x0,
+        ];
+        let Err(nom::Err::Error(error)) = sd_message(&bytes) else {
+            panic!("inconsistent options length accepted");
+        };
+
+        assert_eq!(
+            error.error,
+            InnerError::SdLengthMismatch {
+                declared: 13,
+                actual: 12
+            }
+        );
+        assert_eq!(error.offset(&bytes), Some(8));
+    }
+
+    #[test]
+    fn test_sd_endpoint_options() {
+        // the option of the SD fixture in test_sd_message
+        let mut bytes: Vec<u8> = vec![
+            0x0, 0x9, 0x4, 0x0, 0xc0, 0xa8, 0x58, 0x49, 0x0, 0x11, 0xc3, 0x50,
+        ];
+        assert_eq!(
+            sd_option(&bytes),
+            Ok((
+                &[][..],
+                SdOption::Ipv4Endpoint {
+                    addr: Ipv4Addr::new(192, 168, 88, 73),
+                    proto: L4Proto::Udp,
+                    port: 50000,
+                }
+            ))
+        );
+
+        bytes[9] = 0x84;
+        let Err(nom::Err::Error(error)) = sd_option(&bytes) else {
+            panic!("unknown L4 protocol accepted");
+        };
+        assert_eq!(error.error, InnerError::UnknownL4Proto(0x84));
+        assert_eq!(error.offset(&bytes), Some(9));
+
+        let mut ipv6: Vec<u8> = vec![0x0, 0x15, 0x16, 0x0, 0xff, 0x14];
+        ipv6.extend([0x0; 13]);
+        ipv6.extend([0x1, 0x0, 0x6, 0x77, 0x1a]);
+        assert_eq!(
+            sd_option(&ipv6).unwrap().1.endpoint(),
+            Some(Endpoint {
+                proto: L4Proto::Tcp,
+                addr: IpAddr::V6(Ipv6Addr::new(0xff14, 0, 0, 0, 0, 0, 0, 1)),
+                port: 30490,
+            })
+        );
+    }
+
+    #[test]
+    fn test_sd_subscribe_initial_data_requested() {
+        let entry = |flags_counter: u8| -> Vec<u8> {
+            vec![
+                0x6,
+                0x0,
+                0x0,
+                0x10,
+                0x0,
+                0xeb,
+                0x0,
+                0x1,
+                0x1,
+                0x0,
+                0x0,
+                0x3,
+                0x0,
+                flags_counter,
+                0x0,
+                0x5,
+            ]
+        };
+
+        let bytes = entry(0x82);
+        let (_, subscribe) = sd_entry(bytes.as_slice()).unwrap();
+        assert!(matches!(
+            subscribe,
+            SdEntry::Eventgroup {
+                entry_type: SdEntryType::SubscribeEventgroup,
+                initial_data_requested: true,
+                counter: 2,
+                eventgroup_id: 5,
+                ..
+            }
+        ));
+
+        let bytes = entry(0x02);
+        let (_, subscribe) = sd_entry(bytes.as_slice()).unwrap();
+        assert!(matches!(
+            subscribe,
+            SdEntry::Eventgroup {
+                initial_data_requested: false,
+                counter: 2,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_build_catalog() {
+        let bytes: Vec<u8> = vec![
+            0xff, 0xff, 0x81, 0x0, 0x0, 0x0, 0x0, 0x30, 0x0, 0x0, 0x0, 0x3, 0x1, 0x1, 0x2, 0x0,
+            0xc0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x10, 0x1, 0x0, 0x0, 0x10, 0x0, 0xeb, 0x0, 0x0,
+            0x1, 0x0, 0x0, 0x1e, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xc, 0x0, 0x9, 0x4, 0x0, 0xc0,
+            0xa8, 0x58, 0x49, 0x0, 0x11, 0xc3, 0x50,
+        ];
+        let (payload, header) = some_ip_header(bytes.as_slice()).unwrap();
+        let catalog = build_catalog(&[SomeIPMessage { header, payload }]);
+
+        assert_eq!(catalog.services.len(), 1);
+        assert_eq!(
+            catalog.endpoints(0xeb, 0x0),
+            Some(
+                [Endpoint {
+                    proto: L4Proto::Udp,
+                    addr: IpAddr::V4(Ipv4Addr::new(192, 168, 88, 73)),
+                    port: 50000,
+                }]
+                .as_slice()
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_offers() {
+        let bytes: Vec<u8> = vec![
+            0xff, 0xff, 0x81, 0x0, 0x0, 0x0, 0x0, 0x30, 0x0, 0x0, 0x0, 0x3, 0x1, 0x1, 0x2, 0x0,
+            0xc0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x10, 0x1, 0x0, 0x0, 0x10, 0x0, 0xeb, 0x0, 0x0,
+            0x1, 0x0, 0x0, 0x1e, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xc, 0x0, 0x9, 0x4, 0x0, 0xc0,
+            0xa8, 0x58, 0x49, 0x0, 0x11, 0xc3, 0x50,
+        ];
+        let (payload, _) = some_ip_header(bytes.as_slice()).unwrap();
+        let (_, sd) = sd_message(payload).unwrap();
+
+        assert_eq!(
+            parse_offers(&sd),
+            Ok(vec![OfferedService {
+                service_id: 0xeb,
+                instance_id: 0x0,
+                major_version: 1,
+                ttl: 30,
+                minor_version: 0,
+                endpoints: vec![Endpoint {
+                    proto: L4Proto::Udp,
+                    addr: IpAddr::V4(Ipv4Addr::new(192, 168, 88, 73)),
+                    port: 50000,
+                }],
+            }])
+        );
+
+        // the first run now starts at index 1, past the only option
+        let mut bytes = bytes;
+        bytes[25] = 0x1;
+        let (payload, _) = some_ip_header(bytes.as_slice()).unwrap();
+        let (_, sd) = sd_message(payload).unwrap();
+        assert_eq!(
+            parse_offers(&sd),
+            Err(OptionRunOutOfBounds {
+                run: OptionRun { index: 1, count: 1 },
+                options: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn test_resolved_entries() {
+        let mut bytes: Vec<u8> = vec![
+            0xc0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x10, 0x1, 0x0, 0x0, 0x10, 0x0, 0xeb, 0x0, 0x0,
+            0x1, 0x0, 0x0, 0x1e, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xc, 0x0, 0x9, 0x4, 0x0, 0xc0,
+            0xa8, 0x58, 0x49, 0x0, 0x11, 0xc3, 0x50,
+        ];
+        let (_, sd) = sd_message(&bytes).unwrap();
+        let resolved = sd.resolved_entries().unwrap();
+
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(
+            resolved[0].options,
+            [
+                &[SdOption::Ipv4Endpoint {
+                    addr: Ipv4Addr::new(192, 168, 88, 73),
+                    proto: L4Proto::Udp,
+                    port: 50000,
+                }][..],
+                &[][..],
+            ]
+        );
+
+        // the first run now starts at index 1, past the only option
+        bytes[9] = 0x1;
+        let (_, sd) = sd_message(&bytes).unwrap();
+        assert_eq!(
+            sd.resolved_entries(),
+            Err(OptionRunOutOfBounds {
+                run: OptionRun { index: 1, count: 1 },
+                options: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn test_catalog_find_wildcard_minor() {
+        let mut catalog = ServiceCatalog::default();
+        catalog.services.insert(
+            (0xeb, 0x0),
+            OfferedService {
+                service_id: 0xeb,
+                instance_id: 0x0,
+                major_version: 1,
+                ttl: 30,
+                minor_version: 3,
+                endpoints: Vec::new(),
+            },
+        );
+        let find = |minor_version| SdEntry::Service {
+            entry_type: SdEntryType::FindService,
+            option_runs: Default::default(),
+            service_id: 0xeb,
+            instance_id: 0x0,
+            major_version: 1,
+            ttl: 3,
+            minor_version,
+        };
+
+        assert!(find(ANY_MINOR_VERSION).is_wildcard_minor());
+        assert_eq!(
+            catalog.find(&find(ANY_MINOR_VERSION)),
+            catalog.services.get(&(0xeb, 0x0))
+        );
+        assert!(catalog.find(&find(3)).is_some());
+        assert!(!find(2).is_wildcard_minor());
+        assert_eq!(catalog.find(&find(2)), None);
+    }
+}
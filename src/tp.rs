@@ -0,0 +1,94 @@
+//! SOME/IP-TP, which splits messages too large for a single UDP datagram
+//! into segments.
+//!
+//! Every segment carries the original header with the TP flag (0x20) set in
+//! the message type, followed by a 4 byte TP header and a part of the
+//! payload.
+
+use nom::{IResult, number::streaming::be_u32};
+
+use crate::Error;
+
+/// Segments other than the last carry a multiple of this many bytes.
+pub const TP_ALIGNMENT: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TpHeader {
+    /// Position of the segment's data in the reassembled payload, in bytes.
+    /// Always a multiple of 16.
+    pub offset: u32,
+    /// Further segments follow this one.
+    pub more_segments: bool,
+}
+
+impl TpHeader {
+    /// The 4 bytes of the header: the offset in units of 16 bytes in the
+    /// upper 28 bits, 3 reserved bits and the more segments flag.
+    pub fn to_bytes(&self) -> [u8; 4] {
+        (self.offset & !0xf | u32::from(self.more_segments)).to_be_bytes()
+    }
+}
+
+pub fn tp_header(input: &[u8]) -> IResult<&[u8], TpHeader, Error<'_>> {
+    let (i1, raw) = be_u32(input)?;
+    Ok((
+        i1,
+        TpHeader {
+            offset: raw & !0xf,
+            more_segments: raw & 0x1 != 0,
+        },
+    ))
+}
+
+/// Splits a reassembled `payload` into segments of at most `max_segment`
+/// bytes, rounded down to a multiple of 16 but at least 16.
+///
+/// An empty payload yields a single empty segment.
+pub fn segment_tp(payload: &[u8], max_segment: usize) -> Vec<(TpHeader, &[u8])> {
+    let segment_length = (max_segment - max_segment % TP_ALIGNMENT).max(TP_ALIGNMENT);
+    if payload.is_empty() {
+        return vec![(
+            TpHeader {
+                offset: 0,
+                more_segments: false,
+            },
+            payload,
+        )];
+    }
+    let count = payload.len().div_ceil(segment_length);
+    payload
+        .chunks(segment_length)
+        .enumerate()
+        .map(|(i, segment)| {
+            let header = TpHeader {
+                offset: (i * segment_length) as u32,
+                more_segments: i + 1 < count,
+            };
+            (header, segment)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segment_tp() {
+        let payload: Vec<u8> = (0..40).collect();
+        let segments = segment_tp(&payload, 16);
+
+        assert_eq!(
+            segments
+                .iter()
+                .map(|(header, segment)| (header.offset, header.more_segments, segment.len()))
+                .collect::<Vec<_>>(),
+            vec![(0, true, 16), (16, true, 16), (32, false, 8)]
+        );
+        assert_eq!(segments[1].1, &payload[16..32]);
+
+        let bytes = segments[1].0.to_bytes();
+        assert_eq!(bytes, [0x0, 0x0, 0x0, 0x11]);
+        assert_eq!(tp_header(&bytes), Ok((&[][..], segments[1].0)));
+    }
+}
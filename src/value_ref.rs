@@ -0,0 +1,260 @@
+//! Decoding into values that borrow from the input instead of copying it,
+//! for consumers that cannot afford an allocation per string.
+
+use std::borrow::Cow;
+
+use nom::IResult;
+
+use crate::{
+    DecodeOptions, DecodeState, Error, InnerError, LengthUnit, SomeIPType, StructLayout, Value,
+    decode_type, enum_discriminant, someip_cstring, someip_dynamic_length, someip_padding,
+    someip_string, take_bytes,
+};
+
+/// Like [`Value`], but strings, bytes and names borrow from the input and
+/// the type definition where possible.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValueRef<'a> {
+    Bool(bool),
+    Float(f64),
+    UInt(u64),
+    Int(i64),
+    UInt128(u128),
+    Int128(i128),
+    Struct {
+        fields: Vec<(Cow<'a, str>, ValueRef<'a>)>,
+    },
+    Array(Vec<ValueRef<'a>>),
+    Enum(Cow<'a, str>),
+    /// A discriminant of an open enum that names none of its variants.
+    EnumRaw(u64),
+    /// Owned only for UTF-16 strings, which must be transcoded.
+    String(Cow<'a, str>),
+    Bytes(Cow<'a, [u8]>),
+}
+
+impl ValueRef<'_> {
+    pub fn into_owned(self) -> Value {
+        match self {
+            ValueRef::Bool(v) => Value::Bool(v),
+            ValueRef::Float(v) => Value::Float(v),
+            ValueRef::UInt(v) => Value::UInt(v),
+            ValueRef::Int(v) => Value::Int(v),
+            ValueRef::UInt128(v) => Value::UInt128(v),
+            ValueRef::Int128(v) => Value::Int128(v),
+            ValueRef::Struct { fields } => Value::Struct {
+                fields: fields
+                    .into_iter()
+                    .map(|(name, value)| (name.into_owned(), value.into_owned()))
+                    .collect(),
+            },
+            ValueRef::Array(elements) => {
+                Value::Array(elements.into_iter().map(ValueRef::into_owned).collect())
+            }
+            ValueRef::Enum(name) => Value::Enum(name.into_owned()),
+            ValueRef::EnumRaw(v) => Value::EnumRaw(v),
+            ValueRef::String(s) => Value::String(s.into_owned()),
+            ValueRef::Bytes(bytes) => Value::Bytes(bytes.into_owned()),
+        }
+    }
+}
+
+impl From<Value> for ValueRef<'_> {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Bool(v) => ValueRef::Bool(v),
+            Value::Float(v) => ValueRef::Float(v),
+            Value::UInt(v) => ValueRef::UInt(v),
+            Value::Int(v) => ValueRef::Int(v),
+            Value::UInt128(v) => ValueRef::UInt128(v),
+            Value::Int128(v) => ValueRef::Int128(v),
+            Value::Struct { fields } => ValueRef::Struct {
+                fields: fields
+                    .into_iter()
+                    .map(|(name, value)| (Cow::Owned(name), value.into()))
+                    .collect(),
+            },
+            Value::Array(elements) => {
+                ValueRef::Array(elements.into_iter().map(ValueRef::from).collect())
+            }
+            Value::Enum(name) => ValueRef::Enum(Cow::Owned(name)),
+            Value::EnumRaw(v) => ValueRef::EnumRaw(v),
+            Value::String(s) => ValueRef::String(Cow::Owned(s)),
+            Value::Bytes(bytes) => ValueRef::Bytes(Cow::Owned(bytes)),
+            Value::Map(map) => ValueRef::Struct {
+                fields: map
+                    .into_iter()
+                    .map(|(name, value)| (Cow::Owned(name), value.into()))
+                    .collect(),
+            },
+        }
+    }
+}
+
+/// Parses a value like [`crate::some_ip_value`] without copying strings,
+/// opaque bytes or names.
+///
+/// Plain structs, element counted arrays, strings, opaque bytes and enums
+/// are decoded in place; other types are decoded as [`Value`] and converted,
+/// owning whatever they hold.
+pub fn some_ip_value_ref<'a>(
+    input: &'a [u8],
+    def: &'a SomeIPType,
+) -> IResult<&'a [u8], ValueRef<'a>, Error<'a>> {
+    let options = DecodeOptions::default();
+    decode_value_ref(input, def, &mut DecodeState::new(&options, false, input))
+}
+
+fn decode_value_ref<'a>(
+    input: &'a [u8],
+    def: &'a SomeIPType,
+    state: &mut DecodeState,
+) -> IResult<&'a [u8], ValueRef<'a>, Error<'a>> {
+    state.enter(input)?;
+    let result = decode_type_ref(input, def, state);
+    state.depth -= 1;
+    result
+}
+
+fn decode_type_ref<'a>(
+    input: &'a [u8],
+    def: &'a SomeIPType,
+    state: &mut DecodeState,
+) -> IResult<&'a [u8], ValueRef<'a>, Error<'a>> {
+    match def {
+        SomeIPType::Struct {
+            fields,
+            alignment,
+            length_width: None,
+            layout: StructLayout::Sequential,
+        } => {
+            let mut i1 = input;
+            let fields = fields
+                .iter()
+                .map(|(name, def)| {
+                    let (aligned, _padding) = someip_padding(i1, *alignment, state)?;
+                    let (new_input, value) = decode_value_ref(aligned, def, state)?;
+                    i1 = new_input;
+                    Ok((Cow::Borrowed(name.as_str()), value))
+                })
+                .collect::<Result<_, _>>()?;
+            Ok((i1, ValueRef::Struct { fields }))
+        }
+        SomeIPType::StaticArray { length, element } => {
+            array_ref(input, element, u64::from(*length), state)
+        }
+        SomeIPType::DynamicArray {
+            length_width,
+            element,
+            length_unit: LengthUnit::Elements,
+            min_length: None,
+            max_length: None,
+        } => {
+            let (i1, length) = someip_dynamic_length(input, *length_width, state.complete)?;
+            array_ref(i1, element, length, state)
+        }
+        SomeIPType::Enum {
+            variants,
+            open_enum,
+            base,
+        } => {
+            let (i1, discriminant) = enum_discriminant(input, base.as_deref(), state.complete)?;
+            match variants.iter().find(|(i, _)| *i == discriminant) {
+                Some((_, name)) => Ok((i1, ValueRef::Enum(Cow::Borrowed(name.as_str())))),
+                None if *open_enum => Ok((i1, ValueRef::EnumRaw(discriminant))),
+                None => Err(nom::Err::Error(Error::new(
+                    input,
+                    InnerError::UnknownDiscriminant(discriminant),
+                ))),
+            }
+        }
+        SomeIPType::StaticString { length, coding } => {
+            let (i1, str_bytes) = take_bytes(input, *length, state.complete)?;
+            Ok((
+                i1,
+                ValueRef::String(someip_string(str_bytes, state.coding(coding))?),
+            ))
+        }
+        SomeIPType::DynamicString {
+            length_width,
+            coding,
+        } => {
+            let (i1, length) = someip_dynamic_length(input, *length_width, state.complete)?;
+            let (i2, str_bytes) = take_bytes(i1, length, state.complete)?;
+            Ok((
+                i2,
+                ValueRef::String(someip_string(str_bytes, state.coding(coding))?),
+            ))
+        }
+        SomeIPType::CString { coding } => {
+            let (i1, str_bytes) = someip_cstring(input, state.coding(coding), state.complete)?;
+            Ok((
+                i1,
+                ValueRef::String(someip_string(str_bytes, state.coding(coding))?),
+            ))
+        }
+        SomeIPType::Opaque { length } => {
+            let (i1, bytes) = match length {
+                Some(length) => take_bytes(input, *length, state.complete)?,
+                None => (&input[input.len()..], input),
+            };
+            Ok((i1, ValueRef::Bytes(Cow::Borrowed(bytes))))
+        }
+        _ => {
+            let (i1, value) = decode_type(input, def, state)?;
+            Ok((i1, value.into()))
+        }
+    }
+}
+
+fn array_ref<'a>(
+    mut input: &'a [u8],
+    element: &'a SomeIPType,
+    length: u64,
+    state: &mut DecodeState,
+) -> IResult<&'a [u8], ValueRef<'a>, Error<'a>> {
+    let mut elements = Vec::new();
+    for _ in 0..length {
+        let (new_input, value) = decode_value_ref(input, element, state)?;
+        input = new_input;
+        elements.push(value);
+    }
+    Ok((input, ValueRef::Array(elements)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::some_ip_value;
+
+    #[test]
+    fn test_some_ip_value_ref_borrows_strings() {
+        let bytes: Vec<u8> = vec![0x0, 0x2, 0x0, 0x0, 0x0, 0x5, b'h', b'e', b'l', b'l', b'o'];
+        let def = SomeIPType::Struct {
+            fields: vec![
+                ("id".to_string(), SomeIPType::UInt16),
+                (
+                    "name".to_string(),
+                    SomeIPType::DynamicString {
+                        length_width: 32,
+                        coding: None,
+                    },
+                ),
+            ],
+            alignment: 1,
+            length_width: None,
+            layout: StructLayout::Sequential,
+        };
+        let (_, value) = some_ip_value_ref(&bytes, &def).unwrap();
+
+        let ValueRef::Struct { fields } = &value else {
+            panic!("not a struct: {value:?}");
+        };
+        let ValueRef::String(Cow::Borrowed(name)) = &fields[1].1 else {
+            panic!("string not borrowed: {:?}", fields[1].1);
+        };
+        assert_eq!(*name, "hello");
+        assert!(std::ptr::eq(name.as_ptr(), bytes[6..].as_ptr()));
+        assert_eq!(value.into_owned(), some_ip_value(&bytes, &def).unwrap().1);
+    }
+}
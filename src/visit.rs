@@ -0,0 +1,296 @@
+//! Decoding that reports values to a [`Visitor`] as they are read instead of
+//! building a [`Value`] tree, for consumers that only look at a few fields
+//! of large payloads.
+
+use std::fmt::Write;
+
+use nom::IResult;
+
+use crate::{
+    DecodeOptions, DecodeState, Error, LengthUnit, SomeIPType, StructLayout, Value, decode_type,
+    someip_cstring, someip_dynamic_length, someip_padding, someip_string, take_bytes,
+};
+
+/// Callbacks for the values of a payload, in the order they are decoded.
+///
+/// Every callback receives the path of the value, written as for
+/// [`Value::get_path`]; the path of the outermost value is empty. All
+/// callbacks do nothing unless overridden.
+#[allow(unused_variables)]
+pub trait Visitor {
+    fn on_bool(&mut self, path: &str, value: bool) {}
+    fn on_uint(&mut self, path: &str, value: u64) {}
+    fn on_int(&mut self, path: &str, value: i64) {}
+    fn on_uint128(&mut self, path: &str, value: u128) {}
+    fn on_int128(&mut self, path: &str, value: i128) {}
+    fn on_float(&mut self, path: &str, value: f64) {}
+    fn on_string(&mut self, path: &str, value: &str) {}
+    fn on_bytes(&mut self, path: &str, value: &[u8]) {}
+    fn on_enum(&mut self, path: &str, name: &str) {}
+    /// A discriminant of an open enum that names none of its variants.
+    fn on_enum_raw(&mut self, path: &str, discriminant: u64) {}
+    fn enter_struct(&mut self, path: &str) {}
+    fn leave_struct(&mut self, path: &str) {}
+    fn enter_array(&mut self, path: &str) {}
+    fn leave_array(&mut self, path: &str) {}
+}
+
+/// Parses a value like [`crate::some_ip_value`], reporting it to `visitor`
+/// instead of returning it.
+///
+/// Plain structs, element counted arrays, strings and opaque bytes are
+/// walked in place; other types are decoded as [`Value`] first and then
+/// reported.
+pub fn parse_with_visitor<'a>(
+    input: &'a [u8],
+    def: &SomeIPType,
+    visitor: &mut impl Visitor,
+) -> IResult<&'a [u8], (), Error<'a>> {
+    let options = DecodeOptions::default();
+    let mut walker = Walker {
+        state: DecodeState::new(&options, false, input),
+        path: String::new(),
+        visitor,
+    };
+    let i1 = walker.value(input, def)?;
+    Ok((i1, ()))
+}
+
+struct Walker<'o, 'v, V> {
+    state: DecodeState<'o>,
+    /// Path of the value being decoded.
+    path: String,
+    visitor: &'v mut V,
+}
+
+impl<V: Visitor> Walker<'_, '_, V> {
+    fn value<'a>(
+        &mut self,
+        input: &'a [u8],
+        def: &SomeIPType,
+    ) -> Result<&'a [u8], nom::Err<Error<'a>>> {
+        self.state.enter(input)?;
+        let result = self.walk(input, def);
+        self.state.depth -= 1;
+        result
+    }
+
+    fn walk<'a>(
+        &mut self,
+        input: &'a [u8],
+        def: &SomeIPType,
+    ) -> Result<&'a [u8], nom::Err<Error<'a>>> {
+        match def {
+            SomeIPType::Struct {
+                fields,
+                alignment,
+                length_width: None,
+                layout: StructLayout::Sequential,
+            } => {
+                self.visitor.enter_struct(&self.path);
+                let mut i1 = input;
+                for (name, def) in fields {
+                    let (aligned, _padding) = someip_padding(i1, *alignment, &self.state)?;
+                    let parent = self.push_field(name);
+                    i1 = self.value(aligned, def)?;
+                    self.path.truncate(parent);
+                }
+                self.visitor.leave_struct(&self.path);
+                Ok(i1)
+            }
+            SomeIPType::StaticArray { length, element } => {
+                self.array(input, element, u64::from(*length))
+            }
+            SomeIPType::DynamicArray {
+                length_width,
+                element,
+                length_unit: LengthUnit::Elements,
+                min_length: None,
+                max_length: None,
+            } => {
+                let (i1, length) =
+                    someip_dynamic_length(input, *length_width, self.state.complete)?;
+                self.array(i1, element, length)
+            }
+            SomeIPType::StaticString { length, coding } => {
+                let (i1, str_bytes) = take_bytes(input, *length, self.state.complete)?;
+                self.visitor.on_string(
+                    &self.path,
+                    &someip_string(str_bytes, self.state.coding(coding))?,
+                );
+                Ok(i1)
+            }
+            SomeIPType::DynamicString {
+                length_width,
+                coding,
+            } => {
+                let (i1, length) =
+                    someip_dynamic_length(input, *length_width, self.state.complete)?;
+                let (i2, str_bytes) = take_bytes(i1, length, self.state.complete)?;
+                self.visitor.on_string(
+                    &self.path,
+                    &someip_string(str_bytes, self.state.coding(coding))?,
+                );
+                Ok(i2)
+            }
+            SomeIPType::CString { coding } => {
+                let (i1, str_bytes) =
+                    someip_cstring(input, self.state.coding(coding), self.state.complete)?;
+                self.visitor.on_string(
+                    &self.path,
+                    &someip_string(str_bytes, self.state.coding(coding))?,
+                );
+                Ok(i1)
+            }
+            SomeIPType::Opaque { length } => {
+                let (i1, bytes) = match length {
+                    Some(length) => take_bytes(input, *length, self.state.complete)?,
+                    None => (&input[input.len()..], input),
+                };
+                self.visitor.on_bytes(&self.path, bytes);
+                Ok(i1)
+            }
+            _ => {
+                let (i1, value) = decode_type(input, def, &mut self.state)?;
+                self.report(&value);
+                Ok(i1)
+            }
+        }
+    }
+
+    fn array<'a>(
+        &mut self,
+        mut input: &'a [u8],
+        element: &SomeIPType,
+        length: u64,
+    ) -> Result<&'a [u8], nom::Err<Error<'a>>> {
+        self.visitor.enter_array(&self.path);
+        for index in 0..length {
+            let parent = self.push_index(index);
+            input = self.value(input, element)?;
+            self.path.truncate(parent);
+        }
+        self.visitor.leave_array(&self.path);
+        Ok(input)
+    }
+
+    /// Reports an already decoded `value` and everything it holds.
+    fn report(&mut self, value: &Value) {
+        match value {
+            Value::Bool(v) => self.visitor.on_bool(&self.path, *v),
+            Value::Float(v) => self.visitor.on_float(&self.path, *v),
+            Value::UInt(v) => self.visitor.on_uint(&self.path, *v),
+            Value::Int(v) => self.visitor.on_int(&self.path, *v),
+            Value::UInt128(v) => self.visitor.on_uint128(&self.path, *v),
+            Value::Int128(v) => self.visitor.on_int128(&self.path, *v),
+            Value::Struct { fields } => {
+                self.visitor.enter_struct(&self.path);
+                for (name, value) in fields {
+                    let parent = self.push_field(name);
+                    self.report(value);
+                    self.path.truncate(parent);
+                }
+                self.visitor.leave_struct(&self.path);
+            }
+            Value::Map(map) => {
+                self.visitor.enter_struct(&self.path);
+                for (name, value) in map {
+                    let parent = self.push_field(name);
+                    self.report(value);
+                    self.path.truncate(parent);
+                }
+                self.visitor.leave_struct(&self.path);
+            }
+            Value::Array(elements) => {
+                self.visitor.enter_array(&self.path);
+                for (index, element) in elements.iter().enumerate() {
+                    let parent = self.push_index(index as u64);
+                    self.report(element);
+                    self.path.truncate(parent);
+                }
+                self.visitor.leave_array(&self.path);
+            }
+            Value::Enum(name) => self.visitor.on_enum(&self.path, name),
+            Value::EnumRaw(v) => self.visitor.on_enum_raw(&self.path, *v),
+            Value::String(s) => self.visitor.on_string(&self.path, s),
+            Value::Bytes(bytes) => self.visitor.on_bytes(&self.path, bytes),
+        }
+    }
+
+    /// Appends a field to the path, returning the length to truncate it back
+    /// to.
+    fn push_field(&mut self, name: &str) -> usize {
+        let parent = self.path.len();
+        if parent > 0 {
+            self.path.push('.');
+        }
+        self.path.push_str(name);
+        parent
+    }
+
+    fn push_index(&mut self, index: u64) -> usize {
+        let parent = self.path.len();
+        write!(self.path, "[{index}]").unwrap();
+        parent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct IntegerCounter {
+        paths: Vec<String>,
+    }
+
+    impl Visitor for IntegerCounter {
+        fn on_uint(&mut self, path: &str, _value: u64) {
+            self.paths.push(path.to_string());
+        }
+
+        fn on_int(&mut self, path: &str, _value: i64) {
+            self.paths.push(path.to_string());
+        }
+    }
+
+    #[test]
+    fn test_parse_with_visitor_counts_integers() {
+        let bytes: Vec<u8> = vec![
+            0x0, 0x1, 0xff, 0xfe, 0x2, 0x0, 0xa, 0x0, 0xb, 0x0, 0x0, 0x0, 0x2, b'o', b'k', 0x7,
+        ];
+        let def = SomeIPType::Struct {
+            fields: vec![
+                ("id".to_string(), SomeIPType::UInt16),
+                ("offset".to_string(), SomeIPType::SInt16),
+                (
+                    "samples".to_string(),
+                    SomeIPType::DynamicArray {
+                        length_width: 8,
+                        element: Box::new(SomeIPType::UInt16),
+                        length_unit: LengthUnit::Elements,
+                        min_length: None,
+                        max_length: None,
+                    },
+                ),
+                (
+                    "name".to_string(),
+                    SomeIPType::DynamicString {
+                        length_width: 32,
+                        coding: None,
+                    },
+                ),
+            ],
+            alignment: 1,
+            length_width: None,
+            layout: StructLayout::Sequential,
+        };
+        let mut counter = IntegerCounter::default();
+
+        assert_eq!(
+            parse_with_visitor(&bytes, &def, &mut counter),
+            Ok((&[0x7][..], ()))
+        );
+        assert_eq!(counter.paths, ["id", "offset", "samples[0]", "samples[1]"]);
+    }
+}
@@ -0,0 +1,19 @@
+//! Lookups on XML interface descriptions shared by the FIBEX and ARXML
+//! loaders.
+
+use roxmltree::Node;
+
+/// Elements are matched by local name, whatever namespace prefix they use.
+pub(crate) fn is(node: &Node, name: &str) -> bool {
+    node.is_element() && node.tag_name().name() == name
+}
+
+pub(crate) fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| is(child, name))
+}
+
+pub(crate) fn text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name)
+        .and_then(|child| child.text())
+        .map(str::trim)
+}
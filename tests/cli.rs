@@ -0,0 +1,32 @@
+#![cfg(feature = "cli")]
+
+use std::process::Command;
+
+const MESSAGE: &str = "12340001 0000000a 00010002 01010000 cafe";
+
+fn someip_nom(args: &[&str]) -> serde_json::Value {
+    let output = Command::new(env!("CARGO_BIN_EXE_someip-nom"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn test_cli_hex_header() {
+    let header = someip_nom(&["--json", MESSAGE]);
+
+    assert_eq!(header["service_id"], 0x1234);
+    assert_eq!(header["method_id"], 0x1);
+    assert_eq!(header["session_id"], 0x2);
+}
+
+#[test]
+fn test_cli_payload_type() {
+    let def = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/uint16.json");
+    let message = someip_nom(&["--json", "--payload-type", def, MESSAGE]);
+
+    assert_eq!(message["header"]["length"], 10);
+    assert_eq!(message["payload"], serde_json::json!({ "UInt": 0xcafe }));
+}